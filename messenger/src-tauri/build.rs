@@ -1,3 +1,35 @@
+use std::fs;
+
+/// Variable the frontend reads the backend URL from; Rust builds the file transfer URLs itself
+const BACKEND_URL_VAR: &str = "VITE_BACKEND_API_URL";
+
 fn main() {
+    println!("cargo:rerun-if-env-changed={}", BACKEND_URL_VAR);
+    for file in ["../.env", "../.env.local"] {
+        println!("cargo:rerun-if-changed={}", file);
+    }
+
+    // Like Vite: the environment wins over .env.local, which wins over .env
+    // An empty value falls back to the default, as in the frontend
+    let backend_url = std::env::var(BACKEND_URL_VAR)
+        .ok()
+        .or_else(|| {
+            ["../.env.local", "../.env"]
+                .into_iter()
+                .find_map(|file| dotenv_value(file, BACKEND_URL_VAR))
+        })
+        .filter(|url| !url.is_empty());
+    if let Some(url) = backend_url {
+        println!("cargo:rustc-env=BACKEND_API_URL={}", url);
+    }
+
     tauri_build::build()
 }
+
+/// Value of `key` in a .env file, if the file exists and sets it
+fn dotenv_value(file: &str, key: &str) -> Option<String> {
+    fs::read_to_string(file).ok()?.lines().find_map(|line| {
+        let (name, value) = line.trim().split_once('=')?;
+        (name.trim() == key).then(|| value.trim().trim_matches(['"', '\'']).to_string())
+    })
+}
@@ -1,3 +1,4 @@
+use crate::persistence::JsonFile;
 use aes_gcm::{
     aead::{Aead, KeyInit, Payload},
     Aes256Gcm,
@@ -36,7 +37,7 @@ impl Default for AuthPreferences {
 /// Manages authentication preferences with encrypted password storage
 pub struct AuthPreferencesManager {
     preferences: Mutex<AuthPreferences>,
-    storage_path: PathBuf,
+    file: JsonFile,
     encryption_key_path: PathBuf,
 }
 
@@ -48,7 +49,8 @@ impl AuthPreferencesManager {
 
         let manager = Self {
             preferences: Mutex::new(AuthPreferences::default()),
-            storage_path,
+            // Restrictive permissions (0o600) on the preferences file and its backup
+            file: JsonFile::new(storage_path).with_mode(0o600),
             encryption_key_path: encryption_key_path.unwrap_or_default(),
         };
 
@@ -196,46 +198,29 @@ impl AuthPreferencesManager {
 
     /// Load preferences from disk
     fn load_from_disk(&self) -> Result<(), String> {
-        if !self.storage_path.exists() {
-            return Ok(());
-        }
-
-        let contents = fs::read_to_string(&self.storage_path)
-            .map_err(|e| format!("Failed to read auth preferences file: {}", e))?;
+        let preferences: Option<AuthPreferences> = self
+            .file
+            .load()
+            .map_err(|e| format!("Failed to load auth preferences: {}", e))?;
 
-        let preferences: AuthPreferences = serde_json::from_str(&contents)
-            .map_err(|e| format!("Failed to parse auth preferences: {}", e))?;
-
-        *self.preferences.lock().unwrap() = preferences;
+        if let Some(preferences) = preferences {
+            *self.preferences.lock().unwrap() = preferences;
+        }
         Ok(())
     }
 
+    /// Write the current preferences to disk, e.g. before the app exits
+    pub fn flush(&self) -> Result<(), String> {
+        self.save_to_disk()
+    }
+
     /// Save preferences to disk
     fn save_to_disk(&self) -> Result<(), String> {
         let preferences = self.preferences.lock().unwrap();
 
-        let json = serde_json::to_string_pretty(&*preferences)
-            .map_err(|e| format!("Failed to serialize auth preferences: {}", e))?;
-
-        // Ensure parent directory exists
-        if let Some(parent) = self.storage_path.parent() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create storage directory: {}", e))?;
-        }
-
-        fs::write(&self.storage_path, json)
-            .map_err(|e| format!("Failed to write auth preferences file: {}", e))?;
-
-        // Set restrictive permissions (0o600) on the preferences file
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let permissions = fs::Permissions::from_mode(0o600);
-            fs::set_permissions(&self.storage_path, permissions)
-                .map_err(|e| format!("Failed to set preferences file permissions: {}", e))?;
-        }
-
-        Ok(())
+        self.file
+            .save(&*preferences)
+            .map_err(|e| format!("Failed to write auth preferences file: {}", e))
     }
 }
 
@@ -0,0 +1,11 @@
+/// Base URL of the backend API, the same one the frontend uses
+/// Taken from VITE_BACKEND_API_URL at build time (see build.rs), with the same default.
+const API_BASE_URL: &str = match option_env!("BACKEND_API_URL") {
+    Some(url) => url,
+    None => "http://localhost:6666",
+};
+
+/// URL of the backend API route at `path`, e.g. "/api/files/upload"
+pub fn api_url(path: &str) -> String {
+    format!("{}{}", API_BASE_URL.trim_end_matches('/'), path)
+}
@@ -0,0 +1,146 @@
+use crate::chat_target::ChatTarget;
+use crate::quiet_hours::QuietHoursScheduler;
+use crate::settings::SettingsManager;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Manager, UserAttentionType, WebviewWindow};
+
+/// How long each of the two titles stays up while a chat window flashes
+const FLASH_INTERVAL: Duration = Duration::from_millis(1000);
+
+/// Chat windows currently flashing their title, so a burst of messages starts only one loop
+pub struct ChatAttention {
+    flashing: Mutex<HashSet<String>>,
+}
+
+impl ChatAttention {
+    /// Create a new ChatAttention with no flashing windows
+    pub fn new() -> Self {
+        Self {
+            flashing: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Ask the window manager for attention and flash the title of `window` until it is focused
+    fn flash(&self, app: &AppHandle, window: WebviewWindow, name: String) {
+        if let Err(e) = window.request_user_attention(Some(UserAttentionType::Informational)) {
+            eprintln!("Failed to request attention for {}: {}", window.label(), e);
+        }
+
+        if !self
+            .flashing
+            .lock()
+            .unwrap()
+            .insert(window.label().to_string())
+        {
+            return;
+        }
+
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let title = window.title().unwrap_or_else(|_| name.clone());
+            let says = format!("{} says:", name);
+            let mut showing_says = false;
+
+            // Stop once the window is focused, or closed (which makes is_focused fail)
+            while !window.is_focused().unwrap_or(true) {
+                showing_says = !showing_says;
+                let _ = window.set_title(if showing_says { &says } else { &name });
+                tokio::time::sleep(FLASH_INTERVAL).await;
+            }
+
+            let _ = window.set_title(&title);
+            let _ = window.request_user_attention(None);
+            app.state::<ChatAttention>()
+                .flashing
+                .lock()
+                .unwrap()
+                .remove(window.label());
+        });
+    }
+}
+
+/// Whether the notification settings allow drawing attention to a message from `sender_id`
+fn allowed(app: &AppHandle, sender_id: &str) -> bool {
+    let settings_manager = app.state::<SettingsManager>();
+    let settings = settings_manager.get_settings();
+    let policy = settings_manager.notification_policy_for(Some(sender_id));
+    let quiet = app
+        .state::<QuietHoursScheduler>()
+        .should_suppress(&settings.quiet_hours, false);
+
+    policy.shows_desktop_alert() && settings.notifications.events.message && !quiet
+}
+
+/// Labels of the windows that may show a message in `conversation_id` from `sender_id`
+/// A group chat window is found by its conversation, a one-on-one chat by the sender.
+pub(crate) fn chat_labels(sender_id: &str, conversation_id: Option<String>) -> Vec<String> {
+    let conversation = conversation_id
+        .map(|conversation_id| ChatTarget::Conversation {
+            conversation_id,
+            title: None,
+        })
+        .filter(|target| target.validate().is_ok())
+        .map(|target| target.label());
+    conversation
+        .into_iter()
+        .chain(ChatTarget::sender_labels(sender_id))
+        .collect()
+}
+
+/// Draw attention to the unfocused chat window showing a message from `sender_id` in
+/// `conversation_id`: flash it in the taskbar and alternate its title between `sender_name`
+/// and "<sender_name> says:" until it is focused
+/// Returns whether a window was flashed; nothing happens if the chat is focused or not open,
+/// or the notification settings do not allow it.
+#[tauri::command]
+pub fn flash_chat_window(
+    app: AppHandle,
+    attention: tauri::State<ChatAttention>,
+    sender_id: String,
+    sender_name: String,
+    conversation_id: Option<String>,
+) -> bool {
+    if !allowed(&app, &sender_id) {
+        return false;
+    }
+
+    let window = chat_labels(&sender_id, conversation_id)
+        .iter()
+        .find_map(|label| app.get_webview_window(label))
+        .filter(|window| !window.is_focused().unwrap_or(false));
+    match window {
+        Some(window) => {
+            attention.flash(&app, window, sender_name);
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn looks_for_the_group_window_before_one_on_one_chats() {
+        assert_eq!(
+            chat_labels("u1", Some("c1".to_string())),
+            vec!["chat-group-c1", "chat-u1", "chat-bot-u1"]
+        );
+    }
+
+    #[test]
+    fn looks_only_for_one_on_one_chats_without_a_valid_conversation() {
+        assert_eq!(chat_labels("u1", None), vec!["chat-u1", "chat-bot-u1"]);
+        assert_eq!(
+            chat_labels("u1", Some("../main".to_string())),
+            vec!["chat-u1", "chat-bot-u1"]
+        );
+        assert_eq!(
+            chat_labels("u1", Some(String::new())),
+            vec!["chat-u1", "chat-bot-u1"]
+        );
+    }
+}
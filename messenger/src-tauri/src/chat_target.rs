@@ -0,0 +1,297 @@
+use crate::chat_windows::CHAT_WINDOW_PREFIX;
+use serde::{Deserialize, Serialize};
+
+/// Longest id accepted for a chat target
+const MAX_ID_LENGTH: usize = 64;
+
+/// Who or what a chat window talks to
+///
+/// Each variant gets its own window label namespace, so a bot or group conversation never
+/// reuses the window of a contact that happens to share its id:
+/// - `Contact`: `chat-<userId>` (unchanged, so existing labels and saved geometry keep working)
+/// - `Bot`: `chat-bot-<botId>`
+/// - `Conversation`: `chat-group-<conversationId>`
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ChatTarget {
+    /// One-on-one chat with a contact
+    #[serde(rename_all = "camelCase")]
+    Contact {
+        user_id: String,
+        display_name: Option<String>,
+    },
+    /// Existing conversation, e.g. a group chat
+    #[serde(rename_all = "camelCase")]
+    Conversation {
+        conversation_id: String,
+        title: Option<String>,
+    },
+    /// One-on-one chat with an AI bot
+    #[serde(rename_all = "camelCase")]
+    Bot {
+        bot_id: String,
+        display_name: Option<String>,
+    },
+}
+
+impl ChatTarget {
+    const BOT_PREFIX: &'static str = "bot-";
+    const CONVERSATION_PREFIX: &'static str = "group-";
+
+    /// Check that the id is safe to use in a window label and URL
+    /// Contact ids must not start with a namespace prefix, or their labels could collide.
+    pub fn validate(&self) -> Result<(), String> {
+        let id = self.id();
+        let valid = !id.is_empty()
+            && id.len() <= MAX_ID_LENGTH
+            && id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+        if !valid {
+            return Err(format!("Invalid chat id: {:?}", id));
+        }
+
+        if let Self::Contact { user_id, .. } = self {
+            if user_id.starts_with(Self::BOT_PREFIX)
+                || user_id.starts_with(Self::CONVERSATION_PREFIX)
+            {
+                return Err(format!("Invalid contact id: {:?}", user_id));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Id of the contact, conversation or bot
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Contact { user_id, .. } => user_id,
+            Self::Conversation {
+                conversation_id, ..
+            } => conversation_id,
+            Self::Bot { bot_id, .. } => bot_id,
+        }
+    }
+
+    /// Stable label of the chat window for this target
+    pub fn label(&self) -> String {
+        match self {
+            Self::Contact { user_id, .. } => format!("{}{}", CHAT_WINDOW_PREFIX, user_id),
+            Self::Conversation {
+                conversation_id, ..
+            } => format!(
+                "{}{}{}",
+                CHAT_WINDOW_PREFIX,
+                Self::CONVERSATION_PREFIX,
+                conversation_id
+            ),
+            Self::Bot { bot_id, .. } => {
+                format!("{}{}{}", CHAT_WINDOW_PREFIX, Self::BOT_PREFIX, bot_id)
+            }
+        }
+    }
+
+    /// Page loaded by the chat window, with the target in its query string
+    pub fn url(&self) -> String {
+        let mut url = match self {
+            Self::Contact { user_id, .. } => {
+                format!("chat-window.html?contactUserId={}", user_id)
+            }
+            Self::Conversation {
+                conversation_id, ..
+            } => format!("chat-window.html?conversationId={}", conversation_id),
+            Self::Bot { bot_id, .. } => {
+                format!("chat-window.html?contactUserId={}&bot=true", bot_id)
+            }
+        };
+
+        let name = match self {
+            Self::Contact { display_name, .. } | Self::Bot { display_name, .. } => display_name,
+            Self::Conversation { title, .. } => title,
+        };
+        if let Some(name) = name {
+            url.push_str(&format!("&contactName={}", urlencoding::encode(name)));
+        }
+
+        url
+    }
+
+    /// Title of the chat window
+    pub fn title(&self) -> String {
+        let name = match self {
+            Self::Contact { display_name, .. } | Self::Bot { display_name, .. } => display_name,
+            Self::Conversation { title, .. } => title,
+        };
+
+        match name.as_deref().map(str::trim) {
+            Some(name) if !name.is_empty() => name.to_string(),
+            _ => match self {
+                Self::Contact { user_id, .. } => user_id.clone(),
+                Self::Conversation { .. } => "Group Conversation".to_string(),
+                Self::Bot { .. } => "AI Bot".to_string(),
+            },
+        }
+    }
+
+    /// Labels of the windows that may be chatting with `sender_id`
+    /// Messages only carry the sender, which can be a contact or a bot.
+    pub fn sender_labels(sender_id: &str) -> [String; 2] {
+        [
+            format!("{}{}", CHAT_WINDOW_PREFIX, sender_id),
+            format!("{}{}{}", CHAT_WINDOW_PREFIX, Self::BOT_PREFIX, sender_id),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ID: &str = "3f2504e0-4f89-11d3-9a0c-0305e82c3301";
+
+    fn contact(user_id: &str) -> ChatTarget {
+        ChatTarget::Contact {
+            user_id: user_id.to_string(),
+            display_name: None,
+        }
+    }
+
+    fn bot(bot_id: &str) -> ChatTarget {
+        ChatTarget::Bot {
+            bot_id: bot_id.to_string(),
+            display_name: None,
+        }
+    }
+
+    fn conversation(conversation_id: &str) -> ChatTarget {
+        ChatTarget::Conversation {
+            conversation_id: conversation_id.to_string(),
+            title: None,
+        }
+    }
+
+    #[test]
+    fn labels_each_kind_in_its_own_namespace() {
+        assert_eq!(contact(ID).label(), format!("chat-{}", ID));
+        assert_eq!(bot(ID).label(), format!("chat-bot-{}", ID));
+        assert_eq!(conversation(ID).label(), format!("chat-group-{}", ID));
+    }
+
+    #[test]
+    fn rejects_contact_ids_that_reach_into_another_namespace() {
+        // chat-bot-<id> and chat-group-<id> belong to bots and group conversations
+        assert!(contact(&format!("bot-{}", ID)).validate().is_err());
+        assert!(contact(&format!("group-{}", ID)).validate().is_err());
+
+        // Other kinds have a prefix of their own, so these cannot collide
+        assert!(bot(&format!("group-{}", ID)).validate().is_ok());
+        assert!(conversation(&format!("bot-{}", ID)).validate().is_ok());
+        assert_ne!(
+            bot(&format!("group-{}", ID)).label(),
+            conversation(ID).label()
+        );
+        assert!(contact("robot").validate().is_ok());
+    }
+
+    #[test]
+    fn valid_targets_never_share_a_label() {
+        let ids = [ID, "bot", "group", "bot-1", "group-1", "1", "a_b"];
+        let mut labels = std::collections::HashSet::new();
+        for id in ids {
+            for target in [contact(id), bot(id), conversation(id)] {
+                if target.validate().is_ok() {
+                    assert!(labels.insert(target.label()), "{:?}", target);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_ids() {
+        for id in [
+            "",
+            "../main",
+            "a/b",
+            "a?b",
+            "a&contactName=x",
+            "a b",
+            "a.b",
+            "é",
+            "a\n",
+        ] {
+            assert!(contact(id).validate().is_err(), "{:?}", id);
+            assert!(bot(id).validate().is_err(), "{:?}", id);
+            assert!(conversation(id).validate().is_err(), "{:?}", id);
+        }
+
+        assert!(contact(&"a".repeat(MAX_ID_LENGTH)).validate().is_ok());
+        assert!(contact(&"a".repeat(MAX_ID_LENGTH + 1)).validate().is_err());
+    }
+
+    #[test]
+    fn builds_urls_with_encoded_names() {
+        assert_eq!(
+            contact(ID).url(),
+            format!("chat-window.html?contactUserId={}", ID)
+        );
+        assert_eq!(
+            ChatTarget::Bot {
+                bot_id: "b1".to_string(),
+                display_name: Some("Helper & Co".to_string()),
+            }
+            .url(),
+            "chat-window.html?contactUserId=b1&bot=true&contactName=Helper%20%26%20Co"
+        );
+        assert_eq!(
+            conversation("c1").url(),
+            "chat-window.html?conversationId=c1"
+        );
+    }
+
+    #[test]
+    fn falls_back_to_a_title_per_kind() {
+        assert_eq!(contact(ID).title(), ID);
+        assert_eq!(bot(ID).title(), "AI Bot");
+        assert_eq!(conversation(ID).title(), "Group Conversation");
+        assert_eq!(
+            ChatTarget::Contact {
+                user_id: ID.to_string(),
+                display_name: Some("  Alice ".to_string()),
+            }
+            .title(),
+            "Alice"
+        );
+        assert_eq!(
+            ChatTarget::Conversation {
+                conversation_id: ID.to_string(),
+                title: Some("  ".to_string()),
+            }
+            .title(),
+            "Group Conversation"
+        );
+    }
+
+    #[test]
+    fn sender_labels_cover_contacts_and_bots() {
+        assert_eq!(
+            ChatTarget::sender_labels(ID),
+            [contact(ID).label(), bot(ID).label()]
+        );
+    }
+
+    #[test]
+    fn deserializes_tagged_targets() {
+        let target: ChatTarget =
+            serde_json::from_str(r#"{"type":"bot","botId":"b1","displayName":"Helper"}"#).unwrap();
+        assert_eq!(
+            target,
+            ChatTarget::Bot {
+                bot_id: "b1".to_string(),
+                display_name: Some("Helper".to_string()),
+            }
+        );
+        let target: ChatTarget =
+            serde_json::from_str(r#"{"type":"conversation","conversationId":"c1"}"#).unwrap();
+        assert_eq!(target, conversation("c1"));
+    }
+}
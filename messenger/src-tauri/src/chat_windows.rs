@@ -0,0 +1,581 @@
+use crate::persistence::JsonFile;
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, Monitor, WebviewWindow, WindowEvent};
+
+/// Prefix of every chat window label
+pub const CHAT_WINDOW_PREFIX: &str = "chat-";
+
+/// Size of a chat window that has no saved geometry
+pub const DEFAULT_CHAT_WINDOW_SIZE: (f64, f64) = (630.0, 530.0);
+
+/// Distance between cascaded chat windows
+const CASCADE_OFFSET: f64 = 30.0;
+
+/// How much of a saved window must be on a monitor for it to be restored there
+const MIN_VISIBLE: (f64, f64) = (100.0, 50.0);
+
+/// Position and size of a window in logical pixels
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WindowGeometry {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Why a chat window could not be opened
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ChatWindowError {
+    /// The target's id cannot be used for a window
+    InvalidTarget { message: String },
+    /// The webview could not be created, even with the default geometry
+    Build { label: String, message: String },
+}
+
+impl fmt::Display for ChatWindowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTarget { message } => write!(f, "{}", message),
+            Self::Build { label, message } => {
+                write!(f, "Failed to create chat window {}: {}", label, message)
+            }
+        }
+    }
+}
+
+impl From<ChatWindowError> for String {
+    fn from(error: ChatWindowError) -> Self {
+        error.to_string()
+    }
+}
+
+/// An open chat window as reported to the frontend
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChatWindowInfo {
+    pub label: String,
+    pub focused: bool,
+    pub idle_seconds: u64,
+    pub geometry: Option<WindowGeometry>,
+}
+
+/// Bookkeeping for one open chat window
+#[derive(Debug, Clone)]
+struct ChatWindowEntry {
+    label: String,
+    last_activity: Instant,
+    geometry: Option<WindowGeometry>,
+}
+
+/// Tracks open chat windows in the order they were opened, and remembers the last
+/// geometry of every chat so it reopens where the user left it
+pub struct ChatWindowRegistry {
+    windows: Mutex<Vec<ChatWindowEntry>>,
+    saved: Mutex<HashMap<String, WindowGeometry>>,
+    file: JsonFile,
+}
+
+impl ChatWindowRegistry {
+    /// Create a new ChatWindowRegistry, loading saved geometry from `storage_path`
+    pub fn new(storage_path: PathBuf) -> Self {
+        let file = JsonFile::new(storage_path);
+        let saved = file.load().unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            None
+        });
+
+        Self {
+            windows: Mutex::new(Vec::new()),
+            saved: Mutex::new(saved.unwrap_or_default()),
+            file,
+        }
+    }
+
+    /// Geometry for a new chat window: the saved one if it is still on screen, otherwise
+    /// the next free cascade slot
+    pub fn placement_for(&self, app: &AppHandle, label: &str) -> WindowGeometry {
+        let monitors = app.available_monitors().unwrap_or_default();
+
+        if let Some(saved) = self.saved.lock().unwrap().get(label) {
+            if monitors.iter().any(|monitor| is_visible_on(saved, monitor)) {
+                return *saved;
+            }
+        }
+
+        let (width, height) = DEFAULT_CHAT_WINDOW_SIZE;
+        let monitor = app
+            .get_webview_window("main")
+            .and_then(|window| window.current_monitor().ok().flatten())
+            .or_else(|| app.primary_monitor().ok().flatten())
+            .or_else(|| monitors.into_iter().next());
+        let Some(monitor) = monitor else {
+            return WindowGeometry {
+                x: 0.0,
+                y: 0.0,
+                width,
+                height,
+            };
+        };
+
+        let open: Vec<Option<WindowGeometry>> = self
+            .windows
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.geometry)
+            .collect();
+        let (x, y) = cascade_position(logical_work_area(&monitor), (width, height), &open);
+
+        WindowGeometry {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// Start tracking a newly created chat window
+    pub fn track(&self, window: &WebviewWindow, geometry: WindowGeometry) {
+        let label = window.label().to_string();
+        {
+            let mut windows = self.windows.lock().unwrap();
+            windows.retain(|entry| entry.label != label);
+            windows.push(ChatWindowEntry {
+                label: label.clone(),
+                last_activity: Instant::now(),
+                geometry: Some(geometry),
+            });
+        }
+
+        let tracked = window.clone();
+        window.on_window_event(move |event| {
+            let state = tracked.state::<AppState>();
+            let registry = state.chat_windows();
+            match event {
+                WindowEvent::Moved(_) | WindowEvent::Resized(_) => {
+                    if let Some(geometry) = geometry_of(&tracked) {
+                        registry.update_geometry(tracked.label(), geometry);
+                    }
+                }
+                WindowEvent::Focused(true) => registry.touch(tracked.label()),
+                WindowEvent::Destroyed => registry.untrack(tracked.label()),
+                _ => {}
+            }
+        });
+    }
+
+    /// Record activity in a chat window, e.g. focus or a new message
+    pub fn touch(&self, label: &str) {
+        if let Some(entry) = self
+            .windows
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .find(|entry| entry.label == label)
+        {
+            entry.last_activity = Instant::now();
+        }
+    }
+
+    /// Labels of the open chat windows, in the order they were opened
+    pub fn labels(&self) -> Vec<String> {
+        self.windows
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|entry| entry.label.clone())
+            .collect()
+    }
+
+    /// Labels of the chat windows without activity for at least `max_idle`
+    pub fn idle_labels(&self, max_idle: Duration) -> Vec<String> {
+        self.idle_labels_at(Instant::now(), max_idle)
+    }
+
+    fn idle_labels_at(&self, now: Instant, max_idle: Duration) -> Vec<String> {
+        self.windows
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|entry| now.saturating_duration_since(entry.last_activity) >= max_idle)
+            .map(|entry| entry.label.clone())
+            .collect()
+    }
+
+    fn update_geometry(&self, label: &str, geometry: WindowGeometry) {
+        if let Some(entry) = self
+            .windows
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .find(|entry| entry.label == label)
+        {
+            entry.geometry = Some(geometry);
+        }
+    }
+
+    /// Forget a closed window and persist where it was
+    fn untrack(&self, label: &str) {
+        let entry = {
+            let mut windows = self.windows.lock().unwrap();
+            let index = windows.iter().position(|entry| entry.label == label);
+            index.map(|index| windows.remove(index))
+        };
+
+        if let Some(geometry) = entry.and_then(|entry| entry.geometry) {
+            let mut saved = self.saved.lock().unwrap();
+            saved.insert(label.to_string(), geometry);
+            if let Err(e) = self.file.save(&*saved) {
+                eprintln!("Failed to save chat window geometry: {}", e);
+            }
+        }
+    }
+
+    /// Persist the geometry of the chat windows that are still open
+    pub fn flush(&self) -> Result<(), String> {
+        let windows = self.windows.lock().unwrap();
+        let mut saved = self.saved.lock().unwrap();
+        for entry in windows.iter() {
+            if let Some(geometry) = entry.geometry {
+                saved.insert(entry.label.clone(), geometry);
+            }
+        }
+        self.file.save(&*saved)
+    }
+
+    /// Describe the open chat windows
+    fn list(&self, app: &AppHandle) -> Vec<ChatWindowInfo> {
+        self.windows
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|entry| ChatWindowInfo {
+                label: entry.label.clone(),
+                focused: app
+                    .get_webview_window(&entry.label)
+                    .and_then(|window| window.is_focused().ok())
+                    .unwrap_or(false),
+                idle_seconds: entry.last_activity.elapsed().as_secs(),
+                geometry: entry.geometry,
+            })
+            .collect()
+    }
+}
+
+/// Current outer position and inner size of a window in logical pixels
+pub fn geometry_of(window: &WebviewWindow) -> Option<WindowGeometry> {
+    if window.is_minimized().unwrap_or(false) {
+        return None;
+    }
+
+    let scale = window.scale_factor().ok()?;
+    let position = window.outer_position().ok()?.to_logical::<f64>(scale);
+    let size = window.inner_size().ok()?.to_logical::<f64>(scale);
+
+    Some(WindowGeometry {
+        x: position.x,
+        y: position.y,
+        width: size.width,
+        height: size.height,
+    })
+}
+
+/// Work area of a monitor as (x, y, width, height) in logical pixels
+pub(crate) fn logical_work_area(monitor: &Monitor) -> (f64, f64, f64, f64) {
+    let scale = monitor.scale_factor();
+    let area = monitor.work_area();
+    (
+        area.position.x as f64 / scale,
+        area.position.y as f64 / scale,
+        area.size.width as f64 / scale,
+        area.size.height as f64 / scale,
+    )
+}
+
+/// Top left corner of the first free cascade slot for a window of `size` in a work area
+/// Slots start centered and step down and right until the window would run off the work area.
+/// When every slot is taken by one of the `open` windows they are reused in turn.
+fn cascade_position(
+    (area_x, area_y, area_width, area_height): (f64, f64, f64, f64),
+    (width, height): (f64, f64),
+    open: &[Option<WindowGeometry>],
+) -> (f64, f64) {
+    let start_x = area_x + ((area_width - width) / 2.0).max(0.0);
+    let start_y = area_y + ((area_height - height) / 2.0).max(0.0);
+
+    // Number of slots before a window would run off the bottom right of the work area
+    let room = (area_x + area_width - width - start_x).min(area_y + area_height - height - start_y);
+    let slots = (room / CASCADE_OFFSET).floor().max(0.0) as usize + 1;
+
+    let slot_position = |slot: usize| {
+        let offset = slot as f64 * CASCADE_OFFSET;
+        (start_x + offset, start_y + offset)
+    };
+    let is_taken = |(x, y): (f64, f64)| {
+        open.iter().flatten().any(|geometry| {
+            (geometry.x - x).abs() < CASCADE_OFFSET / 2.0
+                && (geometry.y - y).abs() < CASCADE_OFFSET / 2.0
+        })
+    };
+    let slot = (0..slots)
+        .find(|slot| !is_taken(slot_position(*slot)))
+        .unwrap_or(open.len() % slots);
+    slot_position(slot)
+}
+
+/// Whether enough of the window's top left corner is on the monitor to grab it
+fn is_visible_on(geometry: &WindowGeometry, monitor: &Monitor) -> bool {
+    is_visible_in(geometry, logical_work_area(monitor))
+}
+
+/// Whether enough of the window's top left corner is in a work area to grab it
+fn is_visible_in(geometry: &WindowGeometry, (x, y, width, height): (f64, f64, f64, f64)) -> bool {
+    geometry.x + MIN_VISIBLE.0 > x
+        && geometry.x < x + width - MIN_VISIBLE.0
+        && geometry.y >= y
+        && geometry.y < y + height - MIN_VISIBLE.1
+}
+
+/// Whether a window label belongs to a chat window
+pub fn is_chat_window(label: &str) -> bool {
+    label.starts_with(CHAT_WINDOW_PREFIX)
+}
+
+/// List the open chat windows in the order they were opened
+#[tauri::command]
+pub fn list_chat_windows(app: AppHandle, state: tauri::State<AppState>) -> Vec<ChatWindowInfo> {
+    state.chat_windows().list(&app)
+}
+
+/// Focus the chat window after the focused one, wrapping around
+/// Returns the label of the newly focused window, if any chat window is open.
+#[tauri::command]
+pub fn focus_next_chat_window(
+    app: AppHandle,
+    state: tauri::State<AppState>,
+) -> Result<Option<String>, String> {
+    let windows = state.chat_windows().list(&app);
+    if windows.is_empty() {
+        return Ok(None);
+    }
+
+    let next = windows
+        .iter()
+        .position(|window| window.focused)
+        .map(|index| (index + 1) % windows.len())
+        .unwrap_or(0);
+    let label = windows[next].label.clone();
+
+    if let Some(window) = app.get_webview_window(&label) {
+        let _ = window.unminimize();
+        window
+            .set_focus()
+            .map_err(|e| format!("Failed to focus chat window: {}", e))?;
+    }
+
+    Ok(Some(label))
+}
+
+/// Close every chat window
+/// Returns the number of windows asked to close.
+#[tauri::command]
+pub fn close_all_chat_windows(app: AppHandle, state: tauri::State<AppState>) -> usize {
+    close_windows(&app, state.chat_windows().labels())
+}
+
+/// Close the chat windows without activity for at least `idle_minutes`
+/// Returns the labels of the windows asked to close.
+#[tauri::command]
+pub fn close_idle_chat_windows(
+    app: AppHandle,
+    state: tauri::State<AppState>,
+    idle_minutes: u64,
+) -> Vec<String> {
+    let labels = state
+        .chat_windows()
+        .idle_labels(Duration::from_secs(idle_minutes.saturating_mul(60)));
+    close_windows(&app, labels.clone());
+    labels
+}
+
+fn close_windows(app: &AppHandle, labels: Vec<String>) -> usize {
+    labels
+        .into_iter()
+        .filter_map(|label| app.get_webview_window(&label))
+        .filter(|window| match window.close() {
+            Ok(()) => true,
+            Err(e) => {
+                eprintln!("Failed to close {}: {}", window.label(), e);
+                false
+            }
+        })
+        .count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const AREA: (f64, f64, f64, f64) = (0.0, 0.0, 1000.0, 800.0);
+    const SIZE: (f64, f64) = (600.0, 500.0);
+
+    fn at((x, y): (f64, f64)) -> Option<WindowGeometry> {
+        Some(WindowGeometry {
+            x,
+            y,
+            width: SIZE.0,
+            height: SIZE.1,
+        })
+    }
+
+    fn registry(dir: &tempfile::TempDir) -> ChatWindowRegistry {
+        ChatWindowRegistry::new(dir.path().join("chat-windows.json"))
+    }
+
+    fn open(registry: &ChatWindowRegistry, label: &str, last_activity: Instant) {
+        registry.windows.lock().unwrap().push(ChatWindowEntry {
+            label: label.to_string(),
+            last_activity,
+            geometry: None,
+        });
+    }
+
+    fn minutes(minutes: u64) -> Duration {
+        Duration::from_secs(minutes * 60)
+    }
+
+    #[test]
+    fn first_window_is_centered() {
+        assert_eq!(cascade_position(AREA, SIZE, &[]), (200.0, 150.0));
+        assert_eq!(
+            cascade_position((100.0, 50.0, 1000.0, 800.0), SIZE, &[]),
+            (300.0, 200.0)
+        );
+    }
+
+    #[test]
+    fn cascades_past_taken_slots() {
+        let first = cascade_position(AREA, SIZE, &[]);
+        let second = cascade_position(AREA, SIZE, &[at(first)]);
+        assert_eq!(second, (230.0, 180.0));
+        assert_eq!(
+            cascade_position(AREA, SIZE, &[at(first), at(second)]),
+            (260.0, 210.0)
+        );
+    }
+
+    #[test]
+    fn reuses_slots_freed_by_closed_or_moved_windows() {
+        assert_eq!(
+            cascade_position(AREA, SIZE, &[at((230.0, 180.0))]),
+            (200.0, 150.0)
+        );
+        // A window dragged a few pixels still holds its slot; one dragged away does not
+        assert_eq!(
+            cascade_position(AREA, SIZE, &[at((205.0, 145.0))]),
+            (230.0, 180.0)
+        );
+        assert_eq!(
+            cascade_position(AREA, SIZE, &[at((500.0, 10.0)), None]),
+            (200.0, 150.0)
+        );
+    }
+
+    #[test]
+    fn wraps_around_when_every_slot_is_taken() {
+        // 150 pixels of room below and right of the center leave 6 slots
+        let mut windows = Vec::new();
+        for _ in 0..6 {
+            let position = cascade_position(AREA, SIZE, &windows);
+            assert!(position.0 + SIZE.0 <= 1000.0 && position.1 + SIZE.1 <= 800.0);
+            windows.push(at(position));
+        }
+        assert_eq!(windows[5], at((350.0, 300.0)));
+        assert_eq!(cascade_position(AREA, SIZE, &windows), (200.0, 150.0));
+        windows.push(at((200.0, 150.0)));
+        assert_eq!(cascade_position(AREA, SIZE, &windows), (230.0, 180.0));
+    }
+
+    #[test]
+    fn windows_larger_than_the_work_area_start_at_its_corner() {
+        assert_eq!(
+            cascade_position((0.0, 0.0, 500.0, 400.0), SIZE, &[]),
+            (0.0, 0.0)
+        );
+        assert_eq!(
+            cascade_position((0.0, 0.0, 500.0, 400.0), SIZE, &[at((0.0, 0.0))]),
+            (0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn saved_geometry_must_be_grabbable() {
+        let visible = |x, y| {
+            is_visible_in(
+                &WindowGeometry {
+                    x,
+                    y,
+                    width: SIZE.0,
+                    height: SIZE.1,
+                },
+                AREA,
+            )
+        };
+        assert!(visible(0.0, 0.0));
+        assert!(visible(-50.0, 700.0));
+        assert!(visible(850.0, 100.0));
+        assert!(!visible(-500.0, 100.0));
+        assert!(!visible(900.0, 100.0));
+        assert!(!visible(100.0, -1.0));
+        assert!(!visible(100.0, 750.0));
+    }
+
+    #[test]
+    fn idle_labels_selects_windows_idle_for_at_least_the_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = registry(&dir);
+        let opened = Instant::now();
+        open(&registry, "chat-a", opened + minutes(20));
+        open(&registry, "chat-b", opened + minutes(29));
+        open(&registry, "chat-c", opened);
+        let now = opened + minutes(30);
+
+        assert_eq!(
+            registry.idle_labels_at(now, minutes(5)),
+            vec!["chat-a", "chat-c"]
+        );
+        assert_eq!(
+            registry.idle_labels_at(now, minutes(10)),
+            vec!["chat-a", "chat-c"]
+        );
+        assert_eq!(registry.idle_labels_at(now, minutes(20)), vec!["chat-c"]);
+        assert_eq!(registry.idle_labels_at(now, Duration::ZERO).len(), 3);
+        assert!(registry
+            .idle_labels_at(now, Duration::from_secs(u64::MAX))
+            .is_empty());
+    }
+
+    #[test]
+    fn touch_resets_idle_time() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = registry(&dir);
+        let opened = Instant::now();
+        open(&registry, "chat-a", opened);
+        open(&registry, "chat-b", opened);
+
+        std::thread::sleep(Duration::from_millis(1));
+        registry.touch("chat-a");
+        assert_eq!(
+            registry.idle_labels_at(opened + minutes(5), minutes(5)),
+            vec!["chat-b"]
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use crate::filenames;
+use std::path::PathBuf;
+
+/// Command line arguments the app understands
+///
+/// Supported flags:
+/// - `--minimized`: start hidden in the system tray (passed by autostart)
+/// - `--profile <name>`: use a separate profile for multi-instance support
+/// - `--data-dir <path>`: store all app data under `path` instead of the platform default
+///
+/// Values may also be given as `--flag=value`. Unknown arguments are ignored, since the OS
+/// may pass its own (e.g. `-psn_*` on macOS).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LaunchArgs {
+    pub minimized: bool,
+    pub profile: Option<String>,
+    pub data_dir: Option<PathBuf>,
+}
+
+impl LaunchArgs {
+    /// Parse the arguments of the current process
+    pub fn from_env() -> (Self, Vec<String>) {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    /// Parse arguments, excluding the program name
+    /// A flag with a missing or invalid value is skipped and its error returned alongside the
+    /// arguments, so one bad flag does not lose the others.
+    pub fn parse<I>(args: I) -> (Self, Vec<String>)
+    where
+        I: IntoIterator<Item = String>,
+    {
+        let mut launch_args = Self::default();
+        let mut errors = Vec::new();
+        let mut args = args.into_iter().peekable();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline_value) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value)),
+                _ => (arg.clone(), None),
+            };
+
+            // A flag missing its value must not take the next flag as one
+            let mut value = |name: &str| -> Result<String, String> {
+                inline_value
+                    .map(str::to_string)
+                    .or_else(|| args.next_if(|next| !next.starts_with("--")))
+                    .filter(|v| !v.is_empty())
+                    .ok_or_else(|| format!("Missing value for {}", name))
+            };
+
+            let result = match flag.as_str() {
+                "--minimized" => {
+                    launch_args.minimized = true;
+                    Ok(())
+                }
+                "--profile" => value("--profile").and_then(|profile| {
+                    validate_profile_name(&profile)?;
+                    launch_args.profile = Some(profile);
+                    Ok(())
+                }),
+                "--data-dir" => value("--data-dir").map(|data_dir| {
+                    launch_args.data_dir = Some(PathBuf::from(data_dir));
+                }),
+                _ => Ok(()),
+            };
+            if let Err(e) = result {
+                errors.push(e);
+            }
+        }
+
+        (launch_args, errors)
+    }
+}
+
+/// Profile names become directory names, so they must not contain path components
+/// They are also passed back on the command line when relaunching, so they must not look
+/// like a flag. Names Windows cannot use for a directory are refused on every platform, so
+/// data dirs can be moved between machines.
+pub fn validate_profile_name(name: &str) -> Result<(), String> {
+    let valid = !name.is_empty()
+        && name.len() <= 64
+        && !name.starts_with('-')
+        && name != "."
+        && name != ".."
+        && !filenames::is_reserved(name)
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '-' | '_' | '.' | ' '));
+
+    if valid {
+        Ok(())
+    } else {
+        Err(format!("Invalid profile name: {:?}", name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> (LaunchArgs, Vec<String>) {
+        LaunchArgs::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    /// Parse arguments that are all expected to be valid
+    fn parse_ok(args: &[&str]) -> LaunchArgs {
+        let (launch_args, errors) = parse(args);
+        assert_eq!(errors, Vec::<String>::new());
+        launch_args
+    }
+
+    #[test]
+    fn parses_nothing_to_defaults() {
+        assert_eq!(parse_ok(&[]), LaunchArgs::default());
+    }
+
+    #[test]
+    fn parses_separate_and_inline_values() {
+        let expected = LaunchArgs {
+            minimized: true,
+            profile: Some("work".to_string()),
+            data_dir: Some(PathBuf::from("/tmp/spirit")),
+        };
+        assert_eq!(
+            parse_ok(&[
+                "--minimized",
+                "--profile",
+                "work",
+                "--data-dir",
+                "/tmp/spirit"
+            ]),
+            expected
+        );
+        assert_eq!(
+            parse_ok(&["--profile=work", "--data-dir=/tmp/spirit", "--minimized"]),
+            expected
+        );
+    }
+
+    #[test]
+    fn keeps_equals_signs_in_inline_values() {
+        let args = parse_ok(&["--data-dir=/tmp/a=b"]);
+        assert_eq!(args.data_dir, Some(PathBuf::from("/tmp/a=b")));
+    }
+
+    #[test]
+    fn ignores_unknown_arguments() {
+        let args = parse_ok(&[
+            "-psn_0_12345",
+            "--unknown",
+            "spirit://chat/1",
+            "--minimized",
+        ]);
+        assert_eq!(
+            args,
+            LaunchArgs {
+                minimized: true,
+                ..LaunchArgs::default()
+            }
+        );
+    }
+
+    #[test]
+    fn later_values_win() {
+        let args = parse_ok(&["--profile", "a", "--profile", "b"]);
+        assert_eq!(args.profile.as_deref(), Some("b"));
+    }
+
+    #[test]
+    fn rejects_missing_values() {
+        for args in [
+            &["--profile"][..],
+            &["--profile="],
+            &["--data-dir"],
+            &["--data-dir", ""],
+        ] {
+            let (launch_args, errors) = parse(args);
+            assert_eq!(launch_args, LaunchArgs::default(), "{:?}", args);
+            assert_eq!(errors.len(), 1, "{:?}", args);
+        }
+    }
+
+    #[test]
+    fn a_missing_value_does_not_take_the_next_flag() {
+        let (args, errors) = parse(&["--profile", "--minimized"]);
+
+        assert!(args.minimized);
+        assert_eq!(args.profile, None);
+        assert_eq!(errors, vec!["Missing value for --profile".to_string()]);
+    }
+
+    #[test]
+    fn skips_only_the_invalid_flags() {
+        let (args, errors) = parse(&[
+            "--profile",
+            "../work",
+            "--minimized",
+            "--data-dir=",
+            "--profile=home",
+        ]);
+
+        assert_eq!(
+            args,
+            LaunchArgs {
+                minimized: true,
+                profile: Some("home".to_string()),
+                data_dir: None,
+            }
+        );
+        assert_eq!(errors.len(), 2);
+    }
+
+    #[test]
+    fn rejects_invalid_profile_names() {
+        for name in [
+            "..",
+            ".",
+            "a/b",
+            "a\\b",
+            "-x",
+            "--minimized",
+            &"a".repeat(65),
+        ] {
+            let (args, errors) = parse(&["--profile", name]);
+            assert_eq!(args.profile, None, "{:?}", name);
+            assert_eq!(errors.len(), 1, "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn validates_profile_names() {
+        for name in [
+            "work",
+            "Work 2",
+            "my.profile",
+            "a-b_c",
+            "ünï",
+            &"a".repeat(64),
+        ] {
+            assert_eq!(validate_profile_name(name), Ok(()), "{:?}", name);
+        }
+        for name in ["", ".", "..", "-", "-work", "a/b", "a\\b", "a:b", "a\0b"] {
+            assert!(validate_profile_name(name).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn rejects_windows_device_names() {
+        for name in [
+            "CON",
+            "con",
+            "Nul",
+            "aux",
+            "PRN",
+            "COM1",
+            "com9",
+            "LPT1",
+            "lpt9",
+            "nul.txt",
+            "CON.profile",
+            "con .log",
+            "Aux ",
+            "NUL.",
+        ] {
+            assert!(validate_profile_name(name).is_err(), "{:?}", name);
+        }
+        for name in [
+            "console", "nully", "COM10", "COM", "LPT0", "my con", "a.con",
+        ] {
+            assert_eq!(validate_profile_name(name), Ok(()), "{:?}", name);
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::settings::{CloseBehavior, SettingsManager};
+use crate::{shutdown, tray};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{AppHandle, Emitter, Manager, Window};
+use tauri_plugin_dialog::{DialogExt, MessageDialogButtons, MessageDialogResult};
+
+const MINIMIZE_BUTTON: &str = "Minimize to Tray";
+const QUIT_BUTTON: &str = "Quit";
+const CANCEL_BUTTON: &str = "Cancel";
+const REMEMBER_BUTTON: &str = "Remember";
+const JUST_THIS_TIME_BUTTON: &str = "Just This Time";
+
+/// Set while a close dialog is up, so closing again does not stack dialogs
+static ASKING: AtomicBool = AtomicBool::new(false);
+
+/// Handle a request to close the main window; the close itself is always prevented
+/// Without a tray icon a hidden window could not be brought back, so closing quits.
+pub fn on_main_window_close(window: &Window) {
+    let app = window.app_handle();
+    if app.tray_by_id(tray::TRAY_ID).is_none() {
+        shutdown::request(app, 0);
+        return;
+    }
+
+    let behavior = app
+        .state::<SettingsManager>()
+        .get_settings()
+        .startup
+        .close_behavior;
+    match behavior {
+        CloseBehavior::MinimizeToTray => {
+            let _ = window.hide();
+        }
+        CloseBehavior::Quit => shutdown::request(app, 0),
+        CloseBehavior::Ask => ask(window),
+    }
+}
+
+/// Ask whether to minimize or quit, then whether to remember the answer
+/// Native message dialogs have no checkbox, so remembering is asked in a follow-up dialog.
+/// Dismissing the first dialog leaves the main window open.
+fn ask(window: &Window) {
+    if ASKING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let app = window.app_handle().clone();
+    let window = window.clone();
+    app.dialog()
+        .message("Do you want to keep Spirit Messenger running in the system tray, or quit?")
+        .title("Close Spirit Messenger")
+        .parent(&window)
+        .buttons(MessageDialogButtons::YesNoCancelCustom(
+            MINIMIZE_BUTTON.to_string(),
+            QUIT_BUTTON.to_string(),
+            CANCEL_BUTTON.to_string(),
+        ))
+        .show_with_result(move |result| {
+            let choice = match result {
+                MessageDialogResult::Custom(button) if button == MINIMIZE_BUTTON => {
+                    CloseBehavior::MinimizeToTray
+                }
+                MessageDialogResult::Custom(button) if button == QUIT_BUTTON => CloseBehavior::Quit,
+                _ => {
+                    ASKING.store(false, Ordering::SeqCst);
+                    return;
+                }
+            };
+            ask_to_remember(&app, &window, choice);
+        });
+}
+
+/// Offer to make `choice` the close behavior, then carry it out
+/// Dismissing this dialog applies the choice just this time.
+fn ask_to_remember(app: &AppHandle, window: &Window, choice: CloseBehavior) {
+    let app = app.clone();
+    let window_for_result = window.clone();
+    app.dialog()
+        .message("Remember this choice the next time you close the main window? You can change it later in Options.")
+        .title("Close Spirit Messenger")
+        .parent(window)
+        .buttons(MessageDialogButtons::OkCancelCustom(
+            REMEMBER_BUTTON.to_string(),
+            JUST_THIS_TIME_BUTTON.to_string(),
+        ))
+        .show(move |remember| {
+            ASKING.store(false, Ordering::SeqCst);
+            if remember {
+                if let Err(e) = remember_choice(&app, choice) {
+                    eprintln!("{}", e);
+                }
+            }
+
+            match choice {
+                CloseBehavior::Quit => shutdown::request(&app, 0),
+                _ => {
+                    let _ = window_for_result.hide();
+                }
+            }
+        });
+}
+
+/// Save the answer as the close behavior
+fn remember_choice(app: &AppHandle, choice: CloseBehavior) -> Result<(), String> {
+    let settings_manager = app.state::<SettingsManager>();
+    let mut startup = settings_manager.get_settings().startup;
+    startup.close_behavior = choice;
+    settings_manager.update_startup_settings(startup.clone())?;
+
+    // Emit event to all windows
+    let _ = app.emit(
+        "settings-changed",
+        serde_json::json!({ "startup": startup }),
+    );
+    Ok(())
+}
@@ -0,0 +1,789 @@
+use crate::backend;
+use crate::downloads::{self, SaveFileError};
+use crate::filenames;
+use crate::shutdown::ShutdownCoordinator;
+use reqwest::header::{AUTHORIZATION, CONTENT_RANGE, RANGE};
+use reqwest::{Client, Response, StatusCode};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::fs::{self, OpenOptions};
+use tokio::io::{AsyncWriteExt, BufWriter};
+use tokio::sync::watch;
+
+/// Event emitted while a file downloads, and once when it completes, fails or is cancelled
+pub const PROGRESS_EVENT: &str = "file-transfer-progress";
+
+/// Largest file that is downloaded, the same limit as for sending files
+pub const MAX_DOWNLOAD_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Minimum time between two progress events for the same download
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Connections that stay silent for this long fail, keeping what was received for a resume
+const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Longest transfer ID accepted; IDs become part of the `.part` file name
+const MAX_TRANSFER_ID_LEN: usize = 64;
+
+/// Why a file could not be downloaded
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum DownloadError {
+    /// The transfer ID is not one the backend hands out
+    InvalidTransferId { transfer_id: String },
+    /// The same transfer is already downloading
+    AlreadyDownloading { transfer_id: String },
+    /// The download was cancelled
+    Cancelled,
+    /// The server answered with an error status
+    Http { status: u16 },
+    /// The file is larger than `MAX_DOWNLOAD_BYTES`
+    TooLarge { limit: u64 },
+    /// The connection failed or was closed early
+    Network { message: String },
+    /// The file could not be written to the download location
+    Save { error: SaveFileError },
+}
+
+impl fmt::Display for DownloadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidTransferId { transfer_id } => {
+                write!(f, "Invalid transfer ID: {}", transfer_id)
+            }
+            Self::AlreadyDownloading { transfer_id } => {
+                write!(f, "Transfer {} is already downloading", transfer_id)
+            }
+            Self::Cancelled => write!(f, "Download cancelled"),
+            Self::Http { status } => write!(f, "Download failed with status {}", status),
+            Self::TooLarge { limit } => {
+                write!(
+                    f,
+                    "File is larger than the {} MB limit",
+                    limit / 1024 / 1024
+                )
+            }
+            Self::Network { message } => write!(f, "Download failed: {}", message),
+            Self::Save { error } => error.fmt(f),
+        }
+    }
+}
+
+impl From<DownloadError> for String {
+    fn from(error: DownloadError) -> Self {
+        error.to_string()
+    }
+}
+
+impl From<SaveFileError> for DownloadError {
+    fn from(error: SaveFileError) -> Self {
+        Self::Save { error }
+    }
+}
+
+/// Stage of a download reported in progress events
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum TransferState {
+    Downloading,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// Payload of the `file-transfer-progress` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransferProgress {
+    pub transfer_id: String,
+    pub state: TransferState,
+    pub received_bytes: u64,
+    /// Size of the whole file, when the server sent it
+    pub total_bytes: Option<u64>,
+    /// Where the file was saved, once completed
+    pub path: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Downloads received files straight from the backend to disk
+/// Each running download can be cancelled by its transfer ID.
+pub struct DownloadManager {
+    client: Client,
+    active: Mutex<HashMap<String, watch::Sender<bool>>>,
+}
+
+impl DownloadManager {
+    /// Create a new DownloadManager with no running downloads
+    pub fn new() -> Self {
+        let client = Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .read_timeout(READ_TIMEOUT)
+            .build()
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to configure download client: {}", e);
+                Client::new()
+            });
+
+        Self {
+            client,
+            active: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Mark `transfer_id` as downloading and return the receiver its cancellation arrives on
+    fn begin(&self, transfer_id: &str) -> Result<watch::Receiver<bool>, DownloadError> {
+        let mut active = self.active.lock().unwrap();
+        if active.contains_key(transfer_id) {
+            return Err(DownloadError::AlreadyDownloading {
+                transfer_id: transfer_id.to_string(),
+            });
+        }
+
+        let (sender, receiver) = watch::channel(false);
+        active.insert(transfer_id.to_string(), sender);
+        Ok(receiver)
+    }
+
+    fn finish(&self, transfer_id: &str) {
+        self.active.lock().unwrap().remove(transfer_id);
+    }
+
+    /// Cancel the download of `transfer_id`; returns whether it was running
+    pub fn cancel(&self, transfer_id: &str) -> bool {
+        match self.active.lock().unwrap().get(transfer_id) {
+            Some(sender) => {
+                sender.send_replace(true);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Download `url` into the file at `part_path`, resuming after what it already holds
+/// A server that ignores the Range request, or cannot resume from there, sends the whole file
+/// again and the part file starts over. `on_progress` gets the bytes received so far and the
+/// size of the whole file, when known. Returns the size of the downloaded file.
+///
+/// Does not depend on the app, so it can run against any HTTP server.
+pub async fn fetch_to_part(
+    client: &Client,
+    url: &str,
+    auth_token: Option<&str>,
+    part_path: &Path,
+    cancel: &mut watch::Receiver<bool>,
+    mut on_progress: impl FnMut(u64, Option<u64>),
+) -> Result<u64, DownloadError> {
+    let mut offset = fs::metadata(part_path).await.map(|m| m.len()).unwrap_or(0);
+
+    let (mut response, total) = loop {
+        let response = tokio::select! {
+            response = send(client, url, auth_token, offset) => response?,
+            Ok(_) = cancel.wait_for(|cancelled| *cancelled) => {
+                return Err(DownloadError::Cancelled);
+            }
+        };
+        match response.status() {
+            StatusCode::PARTIAL_CONTENT if offset > 0 => {
+                if let Some((start, total)) = content_range(&response) {
+                    if start == offset {
+                        let total =
+                            total.or_else(|| response.content_length().map(|len| offset + len));
+                        break (response, total);
+                    }
+                }
+            }
+            StatusCode::RANGE_NOT_SATISFIABLE if offset > 0 => {}
+            status if status.is_success() => {
+                offset = 0;
+                let total = response.content_length();
+                break (response, total);
+            }
+            status => {
+                return Err(DownloadError::Http {
+                    status: status.as_u16(),
+                })
+            }
+        }
+
+        // What was downloaded cannot be resumed, e.g. the file changed; start over
+        offset = 0;
+    };
+
+    if total.is_some_and(|total| total > MAX_DOWNLOAD_BYTES) {
+        return Err(DownloadError::TooLarge {
+            limit: MAX_DOWNLOAD_BYTES,
+        });
+    }
+
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(part_path)
+        .await
+        .map_err(|e| write_error(part_path, &e))?;
+    file.set_len(offset)
+        .await
+        .map_err(|e| write_error(part_path, &e))?;
+    let mut writer = BufWriter::new(file);
+
+    let mut received = offset;
+    on_progress(received, total);
+
+    loop {
+        let chunk = tokio::select! {
+            chunk = response.chunk() => chunk.map_err(|e| DownloadError::Network {
+                message: e.to_string(),
+            })?,
+            Ok(_) = cancel.wait_for(|cancelled| *cancelled) => {
+                return Err(DownloadError::Cancelled);
+            }
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+
+        received += chunk.len() as u64;
+        if received > MAX_DOWNLOAD_BYTES {
+            return Err(DownloadError::TooLarge {
+                limit: MAX_DOWNLOAD_BYTES,
+            });
+        }
+        writer
+            .write_all(&chunk)
+            .await
+            .map_err(|e| write_error(part_path, &e))?;
+        on_progress(received, total);
+    }
+
+    writer
+        .flush()
+        .await
+        .map_err(|e| write_error(part_path, &e))?;
+    writer
+        .into_inner()
+        .sync_all()
+        .await
+        .map_err(|e| write_error(part_path, &e))?;
+
+    match total {
+        Some(total) if received != total => Err(DownloadError::Network {
+            message: format!("connection closed after {} of {} bytes", received, total),
+        }),
+        _ => Ok(received),
+    }
+}
+
+async fn send(
+    client: &Client,
+    url: &str,
+    auth_token: Option<&str>,
+    offset: u64,
+) -> Result<Response, DownloadError> {
+    let mut request = client.get(url);
+    if let Some(token) = auth_token {
+        request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+    }
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={}-", offset));
+    }
+
+    request.send().await.map_err(|e| DownloadError::Network {
+        message: e.to_string(),
+    })
+}
+
+/// Start and total size from a `Content-Range: bytes 100-999/1000` header
+fn content_range(response: &Response) -> Option<(u64, Option<u64>)> {
+    let value = response.headers().get(CONTENT_RANGE)?.to_str().ok()?;
+    let (span, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, _) = span.split_once('-')?;
+    Some((start.trim().parse().ok()?, total.trim().parse().ok()))
+}
+
+/// Move the finished part file into `dir` under the sender's (sanitized) file name
+/// The name is reserved with an empty file first, so nothing existing is ever replaced. Does
+/// blocking I/O, so run it off the async runtime.
+fn move_into_place(part_path: &Path, dir: &Path, filename: &str) -> Result<PathBuf, SaveFileError> {
+    let name = filenames::sanitize_filename(filename);
+    let (placeholder, path) =
+        filenames::create_unique(dir, &name).map_err(|e| write_error_for(&dir.join(&name), &e))?;
+    drop(placeholder);
+
+    if let Err(e) = std::fs::rename(part_path, &path) {
+        let _ = std::fs::remove_file(&path);
+        return Err(write_error_for(&path, &e));
+    }
+    Ok(path)
+}
+
+fn write_error(path: &Path, error: &io::Error) -> DownloadError {
+    write_error_for(path, error).into()
+}
+
+fn write_error_for(path: &Path, error: &io::Error) -> SaveFileError {
+    SaveFileError::Write {
+        path: path.to_string_lossy().to_string(),
+        message: error.to_string(),
+    }
+}
+
+/// Transfer IDs are UUIDs; anything else could escape the download folder in the part file name
+fn is_valid_transfer_id(transfer_id: &str) -> bool {
+    !transfer_id.is_empty()
+        && transfer_id.len() <= MAX_TRANSFER_ID_LEN
+        && transfer_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+fn emit_progress(app: &AppHandle, progress: TransferProgress) {
+    if let Err(e) = app.emit(PROGRESS_EVENT, progress) {
+        eprintln!("Failed to emit {} event: {}", PROGRESS_EVENT, e);
+    }
+}
+
+/// Download a received file from the backend into the download location from the file settings
+/// The file is streamed to "<transfer_id>.part" there, so a failed download resumes where it
+/// stopped when started again, and renamed to the sender's file name once complete. Progress is
+/// reported with `file-transfer-progress` events. Returns where the file was saved.
+#[tauri::command]
+pub async fn start_file_download(
+    app: AppHandle,
+    manager: tauri::State<'_, DownloadManager>,
+    transfer_id: String,
+    auth_token: Option<String>,
+    filename: String,
+    contact_name: Option<String>,
+) -> Result<String, DownloadError> {
+    if !is_valid_transfer_id(&transfer_id) {
+        return Err(DownloadError::InvalidTransferId { transfer_id });
+    }
+    // Only ever sent to the configured backend, along with the user's token
+    let url = backend::api_url(&format!("/api/files/transfer/{}/download", transfer_id));
+    let dir = downloads::app_download_dir(&app, contact_name.as_deref())?;
+    let part_path = dir.join(format!("{}.part", transfer_id));
+
+    let mut cancel = manager.begin(&transfer_id)?;
+    let mut last_progress = None::<Instant>;
+    let mut received_bytes = 0;
+    let mut total_bytes = None;
+
+    let result = fetch_to_part(
+        &manager.client,
+        &url,
+        auth_token.as_deref(),
+        &part_path,
+        &mut cancel,
+        |received, total| {
+            received_bytes = received;
+            total_bytes = total;
+            if last_progress.is_some_and(|at| at.elapsed() < PROGRESS_INTERVAL) {
+                return;
+            }
+            last_progress = Some(Instant::now());
+            emit_progress(
+                &app,
+                TransferProgress {
+                    transfer_id: transfer_id.clone(),
+                    state: TransferState::Downloading,
+                    received_bytes: received,
+                    total_bytes: total,
+                    path: None,
+                    error: None,
+                },
+            );
+        },
+    )
+    .await;
+
+    let result = match result {
+        Ok(_) => {
+            // Quitting waits for the file to be moved into place
+            let coordinator = app.state::<ShutdownCoordinator>();
+            let _guard = coordinator.guard();
+            let (part, dir) = (part_path.clone(), dir.clone());
+            tauri::async_runtime::spawn_blocking(move || move_into_place(&part, &dir, &filename))
+                .await
+                .unwrap_or_else(|e| {
+                    Err(write_error_for(
+                        &part_path,
+                        &io::Error::other(e.to_string()),
+                    ))
+                })
+                .map_err(DownloadError::from)
+        }
+        Err(e) => Err(e),
+    };
+    manager.finish(&transfer_id);
+
+    let mut progress = TransferProgress {
+        transfer_id,
+        state: TransferState::Completed,
+        received_bytes,
+        total_bytes,
+        path: None,
+        error: None,
+    };
+    match &result {
+        Ok(path) => progress.path = Some(path.to_string_lossy().to_string()),
+        Err(e) => {
+            // Keep what was received for a resume, unless it will never be of use
+            if matches!(e, DownloadError::Cancelled | DownloadError::TooLarge { .. }) {
+                let _ = fs::remove_file(&part_path).await;
+            }
+            eprintln!(
+                "Download of transfer {} stopped: {}",
+                progress.transfer_id, e
+            );
+            progress.state = match e {
+                DownloadError::Cancelled => TransferState::Cancelled,
+                _ => TransferState::Failed,
+            };
+            progress.error = Some(e.to_string());
+        }
+    }
+    emit_progress(&app, progress);
+
+    result.map(|path| path.to_string_lossy().to_string())
+}
+
+/// Cancel a running download; returns whether it was running
+/// The partly downloaded file is removed.
+#[tauri::command]
+pub fn cancel_file_download(manager: tauri::State<DownloadManager>, transfer_id: String) -> bool {
+    manager.cancel(&transfer_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::Arc;
+    use std::thread;
+
+    const BODY: &[u8] = b"0123456789";
+
+    /// What the test server does with one request
+    enum Reply {
+        /// Send these bytes, then close the connection
+        Send(Vec<u8>),
+        /// Send these bytes, then keep the connection open
+        Stall(Vec<u8>),
+    }
+
+    fn response(status: &str, headers: &[&str], body: &[u8]) -> Vec<u8> {
+        let mut response = format!("HTTP/1.1 {}\r\nConnection: close\r\n", status);
+        for header in headers {
+            response.push_str(header);
+            response.push_str("\r\n");
+        }
+        response.push_str("\r\n");
+        let mut response = response.into_bytes();
+        response.extend_from_slice(body);
+        response
+    }
+
+    fn full(body: &[u8]) -> Reply {
+        Reply::Send(response(
+            "200 OK",
+            &[&format!("Content-Length: {}", body.len())],
+            body,
+        ))
+    }
+
+    /// Serve requests on a local port with `handler`, which gets each request's head
+    /// Returns the URL and the heads of the requests served so far.
+    fn serve(
+        handler: impl Fn(&str) -> Reply + Send + 'static,
+    ) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/file", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let seen = requests.clone();
+        thread::spawn(move || {
+            let mut stalled: Vec<TcpStream> = Vec::new();
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut head = String::new();
+                while reader.read_line(&mut head).unwrap() > 2 {}
+                seen.lock().unwrap().push(head.to_lowercase());
+
+                match handler(&head.to_lowercase()) {
+                    Reply::Send(bytes) => {
+                        let _ = stream.write_all(&bytes);
+                    }
+                    Reply::Stall(bytes) => {
+                        let _ = stream.write_all(&bytes);
+                        stalled.push(stream);
+                    }
+                }
+            }
+        });
+
+        (url, requests)
+    }
+
+    fn range_of(head: &str) -> Option<u64> {
+        head.lines()
+            .find_map(|line| line.strip_prefix("range: bytes="))
+            .and_then(|range| range.trim().trim_end_matches('-').parse().ok())
+    }
+
+    async fn fetch(url: &str, part_path: &Path) -> Result<u64, DownloadError> {
+        let (_cancel, mut cancelled) = watch::channel(false);
+        fetch_to_part(
+            &Client::new(),
+            url,
+            Some("token"),
+            part_path,
+            &mut cancelled,
+            |_, _| {},
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn downloads_the_whole_file() {
+        let (url, requests) = serve(|_| full(BODY));
+        let dir = tempfile::tempdir().unwrap();
+        let part_path = dir.path().join("t.part");
+
+        let mut progress = Vec::new();
+        let (_cancel, mut cancelled) = watch::channel(false);
+        let size = fetch_to_part(
+            &Client::new(),
+            &url,
+            Some("token"),
+            &part_path,
+            &mut cancelled,
+            |received, total| progress.push((received, total)),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(size, 10);
+        assert_eq!(fs::read(&part_path).unwrap(), BODY);
+        assert_eq!(progress.first(), Some(&(0, Some(10))));
+        assert_eq!(progress.last(), Some(&(10, Some(10))));
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].contains("authorization: bearer token"));
+        assert!(!requests[0].contains("range:"));
+    }
+
+    #[tokio::test]
+    async fn resumes_from_the_part_file() {
+        let (url, requests) = serve(|head| match range_of(head) {
+            Some(start) => Reply::Send(response(
+                "206 Partial Content",
+                &[
+                    &format!("Content-Range: bytes {}-9/10", start),
+                    &format!("Content-Length: {}", 10 - start),
+                ],
+                &BODY[start as usize..],
+            )),
+            None => full(BODY),
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let part_path = dir.path().join("t.part");
+        fs::write(&part_path, &BODY[..4]).unwrap();
+
+        assert_eq!(fetch(&url, &part_path).await.unwrap(), 10);
+        assert_eq!(fs::read(&part_path).unwrap(), BODY);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(range_of(&requests[0]), Some(4));
+    }
+
+    #[tokio::test]
+    async fn starts_over_when_the_server_ignores_the_range() {
+        let (url, requests) = serve(|_| full(BODY));
+        let dir = tempfile::tempdir().unwrap();
+        let part_path = dir.path().join("t.part");
+        fs::write(&part_path, b"stale").unwrap();
+
+        assert_eq!(fetch(&url, &part_path).await.unwrap(), 10);
+        assert_eq!(fs::read(&part_path).unwrap(), BODY);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn starts_over_when_the_range_cannot_be_satisfied() {
+        let (url, requests) = serve(|head| match range_of(head) {
+            Some(_) => Reply::Send(response(
+                "416 Range Not Satisfiable",
+                &["Content-Range: bytes */10", "Content-Length: 0"],
+                b"",
+            )),
+            None => full(BODY),
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let part_path = dir.path().join("t.part");
+        fs::write(&part_path, b"longer than the file").unwrap();
+
+        assert_eq!(fetch(&url, &part_path).await.unwrap(), 10);
+        assert_eq!(fs::read(&part_path).unwrap(), BODY);
+
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(range_of(&requests[0]), Some(20));
+        assert_eq!(range_of(&requests[1]), None);
+    }
+
+    #[tokio::test]
+    async fn starts_over_when_the_server_resumes_elsewhere() {
+        let (url, requests) = serve(|head| match range_of(head) {
+            Some(_) => Reply::Send(response(
+                "206 Partial Content",
+                &["Content-Range: bytes 0-9/10", "Content-Length: 10"],
+                BODY,
+            )),
+            None => full(BODY),
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let part_path = dir.path().join("t.part");
+        fs::write(&part_path, b"0123").unwrap();
+
+        assert_eq!(fetch(&url, &part_path).await.unwrap(), 10);
+        assert_eq!(fs::read(&part_path).unwrap(), BODY);
+        assert_eq!(requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn fails_on_error_status() {
+        let (url, _) =
+            serve(|_| Reply::Send(response("404 Not Found", &["Content-Length: 0"], b"")));
+        let dir = tempfile::tempdir().unwrap();
+        let part_path = dir.path().join("t.part");
+
+        assert!(matches!(
+            fetch(&url, &part_path).await,
+            Err(DownloadError::Http { status: 404 })
+        ));
+        assert!(!part_path.exists());
+    }
+
+    #[tokio::test]
+    async fn fails_when_the_connection_closes_early() {
+        let (url, _) =
+            serve(|_| Reply::Send(response("200 OK", &["Content-Length: 10"], &BODY[..6])));
+        let dir = tempfile::tempdir().unwrap();
+        let part_path = dir.path().join("t.part");
+
+        assert!(matches!(
+            fetch(&url, &part_path).await,
+            Err(DownloadError::Network { .. })
+        ));
+        // What arrived is kept for a resume
+        assert_eq!(fs::read(&part_path).unwrap(), &BODY[..6]);
+    }
+
+    #[tokio::test]
+    async fn refuses_files_over_the_limit() {
+        let (url, _) = serve(|_| {
+            Reply::Stall(response(
+                "200 OK",
+                &[&format!("Content-Length: {}", MAX_DOWNLOAD_BYTES + 1)],
+                BODY,
+            ))
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let part_path = dir.path().join("t.part");
+
+        assert!(matches!(
+            fetch(&url, &part_path).await,
+            Err(DownloadError::TooLarge {
+                limit: MAX_DOWNLOAD_BYTES
+            })
+        ));
+        assert!(!part_path.exists());
+    }
+
+    #[tokio::test]
+    async fn stops_when_cancelled() {
+        let (url, _) =
+            serve(|_| Reply::Stall(response("200 OK", &["Content-Length: 10"], b"0123")));
+        let dir = tempfile::tempdir().unwrap();
+        let part_path = dir.path().join("t.part");
+
+        let client = Client::new();
+        let (cancel, mut cancelled) = watch::channel(false);
+        let result = fetch_to_part(
+            &client,
+            &url,
+            None,
+            &part_path,
+            &mut cancelled,
+            |received, _| {
+                if received > 0 {
+                    cancel.send_replace(true);
+                }
+            },
+        );
+        let result = tokio::time::timeout(Duration::from_secs(5), result)
+            .await
+            .expect("cancelling did not stop the download");
+
+        assert!(matches!(result, Err(DownloadError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn stops_when_cancelled_before_the_server_answers() {
+        let (url, _) = serve(|_| Reply::Stall(Vec::new()));
+        let dir = tempfile::tempdir().unwrap();
+        let part_path = dir.path().join("t.part");
+
+        let client = Client::new();
+        let (cancel, mut cancelled) = watch::channel(false);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            cancel.send_replace(true);
+        });
+        let result = fetch_to_part(&client, &url, None, &part_path, &mut cancelled, |_, _| {});
+        let result = tokio::time::timeout(Duration::from_secs(5), result)
+            .await
+            .expect("cancelling did not stop the request");
+
+        assert!(matches!(result, Err(DownloadError::Cancelled)));
+        assert!(!part_path.exists());
+    }
+
+    #[test]
+    fn accepts_only_transfer_ids_safe_in_file_names() {
+        assert!(is_valid_transfer_id("6f1c2a0e-8d4b-4c7a-9a51-0c2f3e4d5b6a"));
+        let too_long = "a".repeat(65);
+        for id in ["", "../x", "a/b", "a\\b", "a.part", &too_long] {
+            assert!(!is_valid_transfer_id(id), "{:?}", id);
+        }
+    }
+
+    #[test]
+    fn moves_finished_files_without_replacing_existing_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("photo.jpg"), b"existing").unwrap();
+        let part_path = dir.path().join("t.part");
+        fs::write(&part_path, BODY).unwrap();
+
+        let path = move_into_place(&part_path, dir.path(), "../photo.jpg").unwrap();
+
+        assert_eq!(path, dir.path().join("photo (1).jpg"));
+        assert_eq!(fs::read(&path).unwrap(), BODY);
+        assert_eq!(fs::read(dir.path().join("photo.jpg")).unwrap(), b"existing");
+        assert!(!part_path.exists());
+    }
+}
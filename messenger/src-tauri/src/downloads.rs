@@ -0,0 +1,267 @@
+use crate::filenames;
+use crate::settings::{FileSettings, SettingsManager};
+use serde::Serialize;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tauri::{AppHandle, Manager};
+
+/// Why a received file could not be saved
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum SaveFileError {
+    /// Neither a download location nor the Downloads folder is available
+    NoDownloadLocation { message: String },
+    /// The download location (or the contact's folder in it) cannot be written to
+    LocationUnwritable { path: String, message: String },
+    /// Writing the file itself failed
+    Write { path: String, message: String },
+}
+
+impl fmt::Display for SaveFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NoDownloadLocation { message } => {
+                write!(f, "Failed to get downloads directory: {}", message)
+            }
+            Self::LocationUnwritable { path, message } => {
+                write!(f, "Cannot save files to {}: {}", path, message)
+            }
+            Self::Write { path, message } => write!(f, "Failed to write {}: {}", path, message),
+        }
+    }
+}
+
+impl From<SaveFileError> for String {
+    fn from(error: SaveFileError) -> Self {
+        error.to_string()
+    }
+}
+
+/// Folder received files go to, for the app's current file settings
+pub fn app_download_dir(
+    app: &AppHandle,
+    contact_name: Option<&str>,
+) -> Result<PathBuf, SaveFileError> {
+    let files = app.state::<SettingsManager>().get_settings().files;
+    download_dir(&files, contact_name, || {
+        app.path()
+            .download_dir()
+            .map_err(|e| SaveFileError::NoDownloadLocation {
+                message: e.to_string(),
+            })
+    })
+}
+
+/// Folder received files go to: the download location from `files`, or the `fallback` folder
+/// (the Downloads folder) when none is set or it no longer exists
+/// With per-contact folders turned on, files from a contact go to a folder named after them,
+/// like "My Received Files\<contact>".
+pub fn download_dir(
+    files: &FileSettings,
+    contact_name: Option<&str>,
+    fallback: impl FnOnce() -> Result<PathBuf, SaveFileError>,
+) -> Result<PathBuf, SaveFileError> {
+    let mut dir = match configured_location(files) {
+        Some(location) => location,
+        None => fallback()?,
+    };
+    if files.per_contact_folders {
+        if let Some(folder) = contact_name
+            .map(filenames::sanitize_component)
+            .filter(|folder| !folder.is_empty())
+        {
+            dir.push(folder);
+        }
+    }
+
+    fs::create_dir_all(&dir).map_err(|e| unwritable(&dir, &e))?;
+    Ok(dir)
+}
+
+/// The configured download location, if it is set and still exists
+fn configured_location(files: &FileSettings) -> Option<PathBuf> {
+    let location = files.download_location.trim();
+    if location.is_empty() {
+        return None;
+    }
+
+    let path = PathBuf::from(location);
+    if path.is_dir() {
+        Some(path)
+    } else {
+        eprintln!(
+            "Download location {:?} is missing, using the Downloads folder",
+            path
+        );
+        None
+    }
+}
+
+/// Write a received file into `dir`
+/// The sender chooses `filename`, so it is sanitized to stay inside `dir`, and renamed to
+/// "name (1).ext" and so on instead of overwriting an existing file.
+pub fn write_file(dir: &Path, filename: &str, data: &[u8]) -> Result<PathBuf, SaveFileError> {
+    let name = filenames::sanitize_filename(filename);
+    let (mut file, path) =
+        filenames::create_unique(dir, &name).map_err(|e| create_error(dir, &name, &e))?;
+
+    if let Err(e) = file.write_all(data).and_then(|()| file.sync_all()) {
+        drop(file);
+        let _ = fs::remove_file(&path);
+        return Err(SaveFileError::Write {
+            path: path.to_string_lossy().to_string(),
+            message: e.to_string(),
+        });
+    }
+    Ok(path)
+}
+
+/// A file that cannot be created blames the folder when it refuses writes, the file otherwise
+fn create_error(dir: &Path, name: &str, error: &io::Error) -> SaveFileError {
+    match error.kind() {
+        io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem => {
+            unwritable(dir, error)
+        }
+        _ => SaveFileError::Write {
+            path: dir.join(name).to_string_lossy().to_string(),
+            message: error.to_string(),
+        },
+    }
+}
+
+fn unwritable(path: &Path, error: &io::Error) -> SaveFileError {
+    SaveFileError::LocationUnwritable {
+        path: path.to_string_lossy().to_string(),
+        message: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn settings(download_location: &str, per_contact_folders: bool) -> FileSettings {
+        FileSettings {
+            download_location: download_location.to_string(),
+            per_contact_folders,
+            ..FileSettings::default()
+        }
+    }
+
+    #[test]
+    fn ignores_unset_or_missing_locations() {
+        let dir = tempfile::tempdir().unwrap();
+        let missing = dir.path().join("missing");
+
+        assert_eq!(configured_location(&settings("", false)), None);
+        assert_eq!(configured_location(&settings("  \t", false)), None);
+        assert_eq!(
+            configured_location(&settings(&missing.to_string_lossy(), false)),
+            None
+        );
+    }
+
+    #[test]
+    fn uses_the_configured_location_trimmed() {
+        let dir = tempfile::tempdir().unwrap();
+        let location = format!(" {} ", dir.path().to_string_lossy());
+
+        assert_eq!(
+            configured_location(&settings(&location, false)),
+            Some(dir.path().to_path_buf())
+        );
+    }
+
+    #[test]
+    fn falls_back_when_no_location_is_usable() {
+        let fallback = tempfile::tempdir().unwrap();
+        let missing = fallback.path().join("missing");
+
+        let dir = download_dir(&settings(&missing.to_string_lossy(), false), None, || {
+            Ok(fallback.path().to_path_buf())
+        })
+        .unwrap();
+
+        assert_eq!(dir, fallback.path());
+        assert!(!missing.exists());
+    }
+
+    #[test]
+    fn reports_a_missing_fallback() {
+        let result = download_dir(&settings("", false), None, || {
+            Err(SaveFileError::NoDownloadLocation {
+                message: "none".to_string(),
+            })
+        });
+
+        assert!(matches!(
+            result,
+            Err(SaveFileError::NoDownloadLocation { .. })
+        ));
+    }
+
+    #[test]
+    fn saves_into_a_folder_per_contact() {
+        let location = tempfile::tempdir().unwrap();
+        let files = settings(&location.path().to_string_lossy(), true);
+        let fallback = || -> Result<PathBuf, SaveFileError> { unreachable!() };
+
+        let dir = download_dir(&files, Some("../Bob: the builder"), fallback).unwrap();
+        assert_eq!(dir, location.path().join("Bob_ the builder"));
+        assert!(dir.is_dir());
+
+        // Nothing usable in the name, or no contact at all, saves into the location itself
+        assert_eq!(
+            download_dir(&files, Some(" .. "), fallback).unwrap(),
+            location.path()
+        );
+        assert_eq!(
+            download_dir(&files, None, fallback).unwrap(),
+            location.path()
+        );
+    }
+
+    #[test]
+    fn ignores_the_contact_without_per_contact_folders() {
+        let location = tempfile::tempdir().unwrap();
+        let files = settings(&location.path().to_string_lossy(), false);
+
+        let dir = download_dir(&files, Some("Bob"), || unreachable!()).unwrap();
+
+        assert_eq!(dir, location.path());
+    }
+
+    #[test]
+    fn refused_writes_blame_the_location() {
+        let dir = Path::new("/downloads");
+        for kind in [
+            io::ErrorKind::PermissionDenied,
+            io::ErrorKind::ReadOnlyFilesystem,
+        ] {
+            let error = create_error(dir, "a.txt", &io::Error::from(kind));
+            match error {
+                SaveFileError::LocationUnwritable { path, .. } => {
+                    assert_eq!(path, dir.to_string_lossy())
+                }
+                other => panic!("unexpected error for {:?}: {:?}", kind, other),
+            }
+        }
+
+        let error = create_error(dir, "a.txt", &io::Error::from(io::ErrorKind::Other));
+        assert!(matches!(error, SaveFileError::Write { path, .. } if path.ends_with("a.txt")));
+    }
+
+    #[test]
+    fn writes_files_without_replacing_existing_ones() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("photo.jpg"), b"existing").unwrap();
+
+        let path = write_file(dir.path(), "../photo.jpg", b"new").unwrap();
+
+        assert_eq!(path, dir.path().join("photo (1).jpg"));
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(fs::read(dir.path().join("photo.jpg")).unwrap(), b"existing");
+    }
+}
@@ -0,0 +1,464 @@
+use crate::upload_manager::SelectedFile;
+use rand::Rng;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// How long a grant can be used after it was handed out
+const GRANT_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// Where a granted file came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrantSource {
+    /// Picked by the user in a file dialog
+    Dialog,
+    /// Written by the mic recorder
+    Recording,
+}
+
+/// Why a file could not be accessed
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum FileAccessError {
+    /// The file is somewhere the app does not give out access to
+    Denied { message: String },
+    /// No grant with this ID was handed out, or it was revoked
+    UnknownGrant,
+    /// The grant is older than `GRANT_TTL`
+    Expired,
+    /// The file could not be read
+    Io { message: String },
+}
+
+impl fmt::Display for FileAccessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Denied { message } => write!(f, "File access denied: {}", message),
+            Self::UnknownGrant => write!(f, "Unknown file grant"),
+            Self::Expired => write!(f, "File grant expired"),
+            Self::Io { message } => write!(f, "Failed to read file: {}", message),
+        }
+    }
+}
+
+impl From<FileAccessError> for String {
+    fn from(error: FileAccessError) -> Self {
+        error.to_string()
+    }
+}
+
+/// A file the webview was given access to, identified by its grant instead of its path
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrantedFile {
+    pub grant_id: String,
+    pub name: String,
+    pub size: u64,
+    pub mime_type: String,
+}
+
+struct Grant {
+    /// Canonical path, so the file cannot be swapped for a link while the grant is held
+    path: PathBuf,
+    source: GrantSource,
+    expires_at: Instant,
+}
+
+/// Hands out short-lived grants to read files the user picked or the mic recorder wrote
+/// The app's own data (settings, auth preferences, the encryption key) is never granted, and
+/// every grant, read and refusal is logged.
+pub struct FileAccessBroker {
+    grants: Mutex<HashMap<String, Grant>>,
+    /// Directories holding the app's own data
+    denied_dirs: Vec<PathBuf>,
+    /// Where the mic recorder writes; inside the app data, but allowed for recordings
+    recordings_dir: Option<PathBuf>,
+}
+
+impl FileAccessBroker {
+    /// Create a new FileAccessBroker with no grants
+    pub fn new(denied_dirs: Vec<PathBuf>, recordings_dir: Option<PathBuf>) -> Self {
+        Self {
+            grants: Mutex::new(HashMap::new()),
+            denied_dirs,
+            recordings_dir,
+        }
+    }
+
+    /// Grant read access to the file at `path` and describe it
+    pub fn grant(&self, path: &Path, source: GrantSource) -> Result<GrantedFile, FileAccessError> {
+        let path = match self.check(path, source) {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("File access: refused {:?} ({:?}): {}", path, source, e);
+                return Err(e);
+            }
+        };
+        let file = SelectedFile::describe(&path).map_err(|e| FileAccessError::Io {
+            message: e.to_string(),
+        })?;
+
+        let grant_id = format!("{:032x}", rand::thread_rng().gen::<u128>());
+        let now = Instant::now();
+        let mut grants = self.grants.lock().unwrap();
+        grants.retain(|_, grant| grant.expires_at > now);
+        grants.insert(
+            grant_id.clone(),
+            Grant {
+                path: path.clone(),
+                source,
+                expires_at: now + GRANT_TTL,
+            },
+        );
+        println!(
+            "File access: granted {} for {:?} ({:?})",
+            grant_id, path, source
+        );
+
+        Ok(GrantedFile {
+            grant_id,
+            name: file.name,
+            size: file.size,
+            mime_type: file.mime_type,
+        })
+    }
+
+    /// Path of the file behind `grant_id`, checked again; `purpose` is logged with the access
+    pub fn resolve(&self, grant_id: &str, purpose: &str) -> Result<PathBuf, FileAccessError> {
+        let result = self.lookup(grant_id);
+        match &result {
+            Ok(path) => println!(
+                "File access: {} used for {} ({:?})",
+                grant_id, purpose, path
+            ),
+            Err(e) => eprintln!("File access: {} refused for {}: {}", grant_id, purpose, e),
+        }
+        result
+    }
+
+    /// Stop accepting `grant_id`
+    pub fn revoke(&self, grant_id: &str) {
+        if self.grants.lock().unwrap().remove(grant_id).is_some() {
+            println!("File access: revoked {}", grant_id);
+        }
+    }
+
+    fn lookup(&self, grant_id: &str) -> Result<PathBuf, FileAccessError> {
+        let mut grants = self.grants.lock().unwrap();
+        let grant = grants.get(grant_id).ok_or(FileAccessError::UnknownGrant)?;
+        if grant.expires_at <= Instant::now() {
+            grants.remove(grant_id);
+            return Err(FileAccessError::Expired);
+        }
+        let (path, source) = (grant.path.clone(), grant.source);
+        drop(grants);
+
+        // The file may have been replaced by a link since it was granted
+        if self.check(&path, source)? != path {
+            return Err(FileAccessError::Denied {
+                message: format!("{:?} changed since it was granted", path),
+            });
+        }
+        Ok(path)
+    }
+
+    /// Canonical path of `path`, if files from `source` may be read there
+    fn check(&self, path: &Path, source: GrantSource) -> Result<PathBuf, FileAccessError> {
+        let path = fs::canonicalize(path).map_err(|e| FileAccessError::Io {
+            message: e.to_string(),
+        })?;
+
+        let in_recordings = self
+            .recordings_dir
+            .as_deref()
+            .and_then(|dir| fs::canonicalize(dir).ok())
+            .is_some_and(|dir| path.starts_with(dir));
+        let allowed = match source {
+            GrantSource::Recording => in_recordings,
+            GrantSource::Dialog => !self
+                .denied_dirs
+                .iter()
+                .filter_map(|dir| fs::canonicalize(dir).ok())
+                .any(|dir| path.starts_with(dir)),
+        };
+
+        if allowed {
+            Ok(path)
+        } else {
+            Err(FileAccessError::Denied {
+                message: format!("{:?} is not readable through a {:?} grant", path, source),
+            })
+        }
+    }
+}
+
+/// Read the whole file behind a grant
+#[tauri::command]
+pub async fn read_granted_file(
+    broker: tauri::State<'_, FileAccessBroker>,
+    grant_id: String,
+) -> Result<Vec<u8>, FileAccessError> {
+    let path = broker.resolve(&grant_id, "read")?;
+    fs::read(&path).map_err(|e| FileAccessError::Io {
+        message: e.to_string(),
+    })
+}
+
+/// Give up a grant that is no longer needed
+#[tauri::command]
+pub fn revoke_file_grant(broker: tauri::State<FileAccessBroker>, grant_id: String) {
+    broker.revoke(&grant_id);
+}
+
+/// Stop the mic recorder and grant access to the recording it wrote
+#[tauri::command]
+pub async fn stop_voice_recording(
+    broker: tauri::State<'_, FileAccessBroker>,
+) -> Result<GrantedFile, String> {
+    let path = tauri_plugin_mic_recorder::stop_recording().await?;
+    Ok(broker.grant(&path, GrantSource::Recording)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A temp dir laid out as `data/` (the app's own data, with `recordings/` inside it),
+    /// `cache/` (also denied) and `home/` (where the user picks files)
+    struct Fixture {
+        root: tempfile::TempDir,
+        broker: FileAccessBroker,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let root = tempfile::tempdir().unwrap();
+            for dir in ["data/recordings", "cache", "home"] {
+                fs::create_dir_all(root.path().join(dir)).unwrap();
+            }
+            for file in [
+                "data/settings.json",
+                "data/.encryption_key",
+                "data/recordings/voice.wav",
+                "cache/cached.bin",
+                "home/photo.png",
+                "home/other.png",
+            ] {
+                fs::write(root.path().join(file), file).unwrap();
+            }
+
+            let broker = FileAccessBroker::new(
+                vec![root.path().join("data"), root.path().join("cache")],
+                Some(root.path().join("data/recordings")),
+            );
+            Self { root, broker }
+        }
+
+        fn path(&self, relative: &str) -> PathBuf {
+            self.root.path().join(relative)
+        }
+
+        fn grant(
+            &self,
+            relative: &str,
+            source: GrantSource,
+        ) -> Result<GrantedFile, FileAccessError> {
+            self.broker.grant(&self.path(relative), source)
+        }
+    }
+
+    fn assert_denied(result: Result<GrantedFile, FileAccessError>) {
+        assert!(
+            matches!(result, Err(FileAccessError::Denied { .. })),
+            "{:?}",
+            result
+        );
+    }
+
+    #[test]
+    fn grants_and_resolves_picked_files() {
+        let fixture = Fixture::new();
+        let file = fixture
+            .grant("home/photo.png", GrantSource::Dialog)
+            .unwrap();
+
+        assert_eq!(file.name, "photo.png");
+        assert_eq!(file.size, "home/photo.png".len() as u64);
+        assert_eq!(
+            fixture.broker.resolve(&file.grant_id, "test").unwrap(),
+            fs::canonicalize(fixture.path("home/photo.png")).unwrap()
+        );
+    }
+
+    #[test]
+    fn refuses_files_under_each_denied_dir() {
+        let fixture = Fixture::new();
+        for file in [
+            "data/settings.json",
+            "data/.encryption_key",
+            "data/recordings/voice.wav",
+            "cache/cached.bin",
+            "home/../data/settings.json",
+            "home/../cache/cached.bin",
+            "data/recordings/../.encryption_key",
+        ] {
+            assert_denied(fixture.grant(file, GrantSource::Dialog));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn refuses_links_into_denied_dirs() {
+        use std::os::unix::fs::symlink;
+
+        let fixture = Fixture::new();
+        symlink(
+            fixture.path("data/.encryption_key"),
+            fixture.path("home/key"),
+        )
+        .unwrap();
+        symlink(fixture.path("cache"), fixture.path("home/cache")).unwrap();
+
+        assert_denied(fixture.grant("home/key", GrantSource::Dialog));
+        assert_denied(fixture.grant("home/cache/cached.bin", GrantSource::Dialog));
+    }
+
+    #[test]
+    fn recording_grants_stay_inside_the_recordings_dir() {
+        let fixture = Fixture::new();
+        assert!(fixture
+            .grant("data/recordings/voice.wav", GrantSource::Recording)
+            .is_ok());
+
+        for file in [
+            "home/photo.png",
+            "data/settings.json",
+            "data/recordings/../settings.json",
+        ] {
+            assert_denied(fixture.grant(file, GrantSource::Recording));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn recording_grants_refuse_links_out_of_the_recordings_dir() {
+        use std::os::unix::fs::symlink;
+
+        let fixture = Fixture::new();
+        symlink(
+            fixture.path("data/.encryption_key"),
+            fixture.path("data/recordings/key.wav"),
+        )
+        .unwrap();
+
+        assert_denied(fixture.grant("data/recordings/key.wav", GrantSource::Recording));
+    }
+
+    #[test]
+    fn recording_grants_need_a_recordings_dir() {
+        let fixture = Fixture::new();
+        let broker = FileAccessBroker::new(vec![fixture.path("data")], None);
+
+        assert!(matches!(
+            broker.grant(&fixture.path("home/photo.png"), GrantSource::Recording),
+            Err(FileAccessError::Denied { .. })
+        ));
+    }
+
+    #[test]
+    fn unknown_and_revoked_grants_fail() {
+        let fixture = Fixture::new();
+        assert!(matches!(
+            fixture.broker.resolve("missing", "test"),
+            Err(FileAccessError::UnknownGrant)
+        ));
+
+        let file = fixture
+            .grant("home/photo.png", GrantSource::Dialog)
+            .unwrap();
+        fixture.broker.revoke(&file.grant_id);
+        assert!(matches!(
+            fixture.broker.resolve(&file.grant_id, "test"),
+            Err(FileAccessError::UnknownGrant)
+        ));
+    }
+
+    #[test]
+    fn expired_grants_fail() {
+        let fixture = Fixture::new();
+        let file = fixture
+            .grant("home/photo.png", GrantSource::Dialog)
+            .unwrap();
+        fixture
+            .broker
+            .grants
+            .lock()
+            .unwrap()
+            .get_mut(&file.grant_id)
+            .unwrap()
+            .expires_at = Instant::now();
+
+        assert!(matches!(
+            fixture.broker.resolve(&file.grant_id, "test"),
+            Err(FileAccessError::Expired)
+        ));
+        // Expired grants are dropped
+        assert!(matches!(
+            fixture.broker.resolve(&file.grant_id, "test"),
+            Err(FileAccessError::UnknownGrant)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn files_swapped_for_links_after_the_grant_fail() {
+        use std::os::unix::fs::symlink;
+
+        let fixture = Fixture::new();
+        for target in ["data/.encryption_key", "home/other.png"] {
+            let file = fixture
+                .grant("home/photo.png", GrantSource::Dialog)
+                .unwrap();
+
+            fs::rename(
+                fixture.path("home/photo.png"),
+                fixture.path("home/photo.bak"),
+            )
+            .unwrap();
+            symlink(fixture.path(target), fixture.path("home/photo.png")).unwrap();
+            assert!(
+                matches!(
+                    fixture.broker.resolve(&file.grant_id, "test"),
+                    Err(FileAccessError::Denied { .. })
+                ),
+                "{}",
+                target
+            );
+
+            fs::remove_file(fixture.path("home/photo.png")).unwrap();
+            fs::rename(
+                fixture.path("home/photo.bak"),
+                fixture.path("home/photo.png"),
+            )
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn deleted_files_fail() {
+        let fixture = Fixture::new();
+        let file = fixture
+            .grant("home/photo.png", GrantSource::Dialog)
+            .unwrap();
+        fs::remove_file(fixture.path("home/photo.png")).unwrap();
+
+        assert!(matches!(
+            fixture.broker.resolve(&file.grant_id, "test"),
+            Err(FileAccessError::Io { .. })
+        ));
+    }
+}
@@ -0,0 +1,275 @@
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Longest file name produced, in bytes
+/// Below the usual 255 byte limit, to leave room for a " (n)" collision suffix.
+const MAX_NAME_BYTES: usize = 200;
+
+/// Longest extension kept apart from the rest of the name when shortening, in bytes
+const MAX_EXTENSION_BYTES: usize = 16;
+
+/// Name used when nothing usable is left of a file name
+const FALLBACK_NAME: &str = "file";
+
+/// Characters that are not allowed in file names on at least one platform
+const FORBIDDEN_CHARS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// Device names Windows reserves, whatever the extension
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Most " (n)" names tried before giving up on a collision
+const MAX_COLLISION_ATTEMPTS: u32 = 10_000;
+
+/// Turn a name chosen by someone else into a safe name for a file in a single folder
+///
+/// - only the last path component is kept, so `../../.bashrc` becomes `bashrc`
+/// - control characters and bidirectional overrides are dropped, characters forbidden on
+///   any platform become `_`
+/// - leading dots and trailing dots and spaces are removed, so the file is neither hidden nor
+///   `.` or `..`
+/// - Windows device names such as `CON` or `nul.txt` get a leading `_`
+/// - long names are shortened to `MAX_NAME_BYTES`, keeping the extension
+///
+/// Never returns an empty name.
+pub fn sanitize_filename(name: &str) -> String {
+    let name = sanitize_component(name);
+    if name.is_empty() {
+        FALLBACK_NAME.to_string()
+    } else {
+        name
+    }
+}
+
+/// Like `sanitize_filename`, but returns an empty string when nothing usable is left
+pub fn sanitize_component(name: &str) -> String {
+    let last = name.rsplit(['/', '\\']).next().unwrap_or_default();
+
+    let cleaned: String = last
+        .chars()
+        .filter(|c| !c.is_control() && !is_bidi_control(*c))
+        .map(|c| if FORBIDDEN_CHARS.contains(&c) { '_' } else { c })
+        .collect();
+    let cleaned = cleaned
+        .trim_start_matches(|c: char| c == '.' || c.is_whitespace())
+        .trim_end_matches(|c: char| c == '.' || c.is_whitespace());
+    if cleaned.is_empty() {
+        return String::new();
+    }
+
+    let (stem, extension) = split_extension(cleaned);
+    let stem = if is_reserved(stem) {
+        format!("_{}", stem)
+    } else {
+        stem.to_string()
+    };
+
+    shorten(&stem, extension)
+}
+
+/// Unicode controls that can make a name display differently from what it is, e.g.
+/// `exe.txt` shown for `txt.exe`
+fn is_bidi_control(c: char) -> bool {
+    matches!(c, '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}' | '\u{2066}'..='\u{2069}')
+}
+
+/// Whether Windows treats `name` as a device, e.g. `nul`, `COM1.txt` or `Con .log`
+/// Windows ignores trailing dots and spaces, and everything from the first dot on.
+pub fn is_reserved(name: &str) -> bool {
+    let device = name
+        .split('.')
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(|c: char| c == '.' || c.is_whitespace());
+    RESERVED_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(device))
+}
+
+/// `report.final.pdf` -> (`report.final`, Some(`pdf`))
+/// Overlong "extensions" are treated as part of the name.
+fn split_extension(name: &str) -> (&str, Option<&str>) {
+    match name.rsplit_once('.') {
+        Some((stem, extension))
+            if !stem.is_empty()
+                && !extension.is_empty()
+                && extension.len() <= MAX_EXTENSION_BYTES
+                && !extension.contains(char::is_whitespace) =>
+        {
+            (stem, Some(extension))
+        }
+        _ => (name, None),
+    }
+}
+
+/// Join a stem and extension, cutting the stem so the name fits in `MAX_NAME_BYTES`
+fn shorten(stem: &str, extension: Option<&str>) -> String {
+    let extension_len = extension.map_or(0, |extension| extension.len() + 1);
+    let max_stem = MAX_NAME_BYTES - extension_len;
+
+    let mut end = stem.len().min(max_stem);
+    while !stem.is_char_boundary(end) {
+        end -= 1;
+    }
+    let stem = stem[..end].trim_end_matches(|c: char| c == '.' || c.is_whitespace());
+    let stem = if stem.is_empty() { FALLBACK_NAME } else { stem };
+
+    match extension {
+        Some(extension) => format!("{}.{}", stem, extension),
+        None => stem.to_string(),
+    }
+}
+
+/// `name.ext` -> `name (n).ext`
+fn numbered(name: &str, n: u32) -> String {
+    match split_extension(name) {
+        (stem, Some(extension)) => format!("{} ({}).{}", stem, n, extension),
+        (stem, None) => format!("{} ({})", stem, n),
+    }
+}
+
+/// Create a new file named `name` in `dir`, or `name (1).ext`, `name (2).ext`... if it exists
+/// Existing files are never overwritten, even if one appears while the name is being chosen.
+pub fn create_unique(dir: &Path, name: &str) -> io::Result<(File, PathBuf)> {
+    for n in 0..=MAX_COLLISION_ATTEMPTS {
+        let candidate = if n == 0 {
+            name.to_string()
+        } else {
+            numbered(name, n)
+        };
+        let path = dir.join(candidate);
+
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((file, path)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::AlreadyExists,
+        format!("Too many files named like {:?}", name),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn keeps_only_the_last_path_component() {
+        assert_eq!(sanitize_filename("../../.bashrc"), "bashrc");
+        assert_eq!(sanitize_filename("/etc/passwd"), "passwd");
+        assert_eq!(
+            sanitize_filename("C:\\Windows\\System32\\evil.dll"),
+            "evil.dll"
+        );
+        assert_eq!(sanitize_filename("C:\\x"), "x");
+        assert_eq!(sanitize_filename("..\\..\\startup.bat"), "startup.bat");
+        assert_eq!(sanitize_filename("dir/sub\\mixed/name.txt"), "name.txt");
+    }
+
+    #[test]
+    fn replaces_forbidden_characters() {
+        assert_eq!(sanitize_filename("C:x"), "C_x");
+        assert_eq!(sanitize_filename("what?<>|*\".txt"), "what______.txt");
+    }
+
+    #[test]
+    fn prefixes_windows_device_names() {
+        assert_eq!(sanitize_filename("CON"), "_CON");
+        assert_eq!(sanitize_filename("nul.txt"), "_nul.txt");
+        assert_eq!(sanitize_filename("CON.tar.gz"), "_CON.tar.gz");
+        assert_eq!(sanitize_filename("com1"), "_com1");
+        assert_eq!(sanitize_filename("LPT9.log"), "_LPT9.log");
+        assert_eq!(sanitize_filename("CONSOLE.txt"), "CONSOLE.txt");
+        assert_eq!(sanitize_filename("COM10"), "COM10");
+    }
+
+    #[test]
+    fn drops_control_and_bidi_characters() {
+        assert_eq!(
+            sanitize_filename("invoice\u{202E}fdp.exe"),
+            "invoicefdp.exe"
+        );
+        assert_eq!(
+            sanitize_filename("a\u{2066}b\u{2069}c\u{200F}.txt"),
+            "abc.txt"
+        );
+        assert_eq!(
+            sanitize_filename("line\nbreak\ttab\0nul.txt"),
+            "linebreaktabnul.txt"
+        );
+        assert_eq!(sanitize_filename("\u{7}\u{1b}[31mred.txt"), "[31mred.txt");
+    }
+
+    #[test]
+    fn never_returns_dot_names_or_empty() {
+        for name in [
+            ".", "..", "", "....", " . . ", "/", "\\", "../..", "\u{202E}", "\n",
+        ] {
+            assert_eq!(sanitize_filename(name), FALLBACK_NAME, "{:?}", name);
+            assert_eq!(sanitize_component(name), "", "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn trims_hidden_and_trailing_dots() {
+        assert_eq!(sanitize_filename(".hidden"), "hidden");
+        assert_eq!(sanitize_filename("name.txt. . "), "name.txt");
+        assert_eq!(sanitize_filename("  spaced.txt  "), "spaced.txt");
+    }
+
+    #[test]
+    fn shortens_long_names_on_char_boundaries() {
+        // 'é' is two bytes, so an odd limit would cut one in half
+        let name = format!("{}.txt", "é".repeat(300));
+        assert!(name.len() > 255);
+        let sanitized = sanitize_filename(&name);
+        assert!(sanitized.len() <= MAX_NAME_BYTES);
+        assert!(sanitized.ends_with(".txt"));
+        assert!(sanitized.trim_end_matches(".txt").chars().all(|c| c == 'é'));
+
+        let name = format!("a{}", "日本".repeat(100));
+        let sanitized = sanitize_filename(&name);
+        assert!(sanitized.len() <= MAX_NAME_BYTES);
+        assert!(sanitized.starts_with('a'));
+
+        let name = format!("{}.{}", "x".repeat(300), "y".repeat(40));
+        let sanitized = sanitize_filename(&name);
+        assert_eq!(sanitized.len(), MAX_NAME_BYTES);
+    }
+
+    #[test]
+    fn numbers_names_before_the_extension() {
+        assert_eq!(numbered("name.ext", 1), "name (1).ext");
+        assert_eq!(numbered("archive.tar.gz", 2), "archive.tar (2).gz");
+        assert_eq!(numbered("README", 3), "README (3)");
+        assert_eq!(numbered("hidden.", 1), "hidden. (1)");
+    }
+
+    #[test]
+    fn create_unique_never_overwrites() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("name.ext"), "first").unwrap();
+        fs::write(dir.path().join("name (1).ext"), "second").unwrap();
+
+        let (_, path) = create_unique(dir.path(), "name.ext").unwrap();
+        assert_eq!(path, dir.path().join("name (2).ext"));
+        assert_eq!(
+            fs::read_to_string(dir.path().join("name.ext")).unwrap(),
+            "first"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("name (1).ext")).unwrap(),
+            "second"
+        );
+
+        let (_, path) = create_unique(dir.path(), "other.ext").unwrap();
+        assert_eq!(path, dir.path().join("other.ext"));
+    }
+}
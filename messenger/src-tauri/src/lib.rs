@@ -1,31 +1,110 @@
 mod auth_preferences;
+mod backend;
+mod chat_attention;
+mod chat_target;
+mod chat_windows;
+mod cli;
+mod close_behavior;
+mod deep_link;
+mod download_manager;
+mod downloads;
+mod file_access;
+mod filenames;
+mod notification_policy;
+mod nudges;
+mod persistence;
+mod profiles;
+mod quiet_hours;
+mod recent_chats;
 mod settings;
+mod shutdown;
+mod single_instance;
+mod toasts;
+mod tray;
+mod tray_icon;
+mod upload_manager;
 
 use crate::auth_preferences::AuthPreferencesManager;
+use crate::chat_attention::ChatAttention;
+use crate::chat_target::ChatTarget;
+use crate::chat_windows::{
+    ChatWindowError, ChatWindowRegistry, WindowGeometry, DEFAULT_CHAT_WINDOW_SIZE,
+};
+use crate::cli::LaunchArgs;
+use crate::deep_link::DeepLink;
+use crate::download_manager::DownloadManager;
+use crate::downloads::SaveFileError;
+use crate::file_access::{FileAccessBroker, GrantSource, GrantedFile};
+use crate::notification_policy::{
+    NotificationEvent, NotificationEventKind, NotificationPolicyEngine, RequestedOutputs,
+    SoundDecision,
+};
+use crate::nudges::{NudgeLimiter, ShakingWindows};
+use crate::quiet_hours::{QuietHoursScheduler, SystemClock};
+use crate::recent_chats::RecentChats;
 use crate::settings::SettingsManager;
+use crate::shutdown::ShutdownCoordinator;
+use crate::single_instance::InstanceStatus;
+use crate::toasts::ToastManager;
+use crate::tray::TrayManager;
+use crate::upload_manager::UploadManager;
 use log::error;
-use std::fs;
-use tauri::{
-    menu::{Menu, MenuItem},
-    tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
-    AppHandle, Emitter, Manager, WebviewWindowBuilder, WindowEvent,
-};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Manager, RunEvent, WebviewWindowBuilder, WindowEvent};
+use tauri_plugin_deep_link::DeepLinkExt;
 
 /// Global application state
 pub struct AppState {
     profile: Option<String>,
+    data_root: PathBuf,
+    launch_args: LaunchArgs,
+    chat_windows: ChatWindowRegistry,
+    recent_chats: RecentChats,
 }
 
 impl AppState {
     /// Create a new AppState
-    pub fn new(profile: Option<String>) -> Self {
-        Self { profile }
+    pub fn new(
+        profile: Option<String>,
+        data_root: PathBuf,
+        launch_args: LaunchArgs,
+        chat_windows: ChatWindowRegistry,
+        recent_chats: RecentChats,
+    ) -> Self {
+        Self {
+            profile,
+            data_root,
+            launch_args,
+            chat_windows,
+            recent_chats,
+        }
     }
 
     /// Get the current profile name (for multi-instance support)
     pub fn get_profile(&self) -> Option<String> {
         self.profile.clone()
     }
+
+    /// Root data directory that holds the default profile and the `profiles` directory
+    pub fn data_root(&self) -> &Path {
+        &self.data_root
+    }
+
+    /// Arguments this instance was launched with
+    pub fn launch_args(&self) -> &LaunchArgs {
+        &self.launch_args
+    }
+
+    /// Registry of open chat windows
+    pub fn chat_windows(&self) -> &ChatWindowRegistry {
+        &self.chat_windows
+    }
+
+    /// Contacts with the most recent chat activity
+    pub fn recent_chats(&self) -> &RecentChats {
+        &self.recent_chats
+    }
 }
 
 /// Get the current profile name (for multi-instance support)
@@ -48,9 +127,25 @@ fn request_notification_permission(app: AppHandle) -> Result<String, String> {
     Ok(format!("{:?}", permission))
 }
 
-/// Show a system notification with title and body
-#[tauri::command]
-async fn show_notification(app: AppHandle, title: String, body: String) -> Result<(), String> {
+/// Map a sound type to its file in the public/sounds directory
+pub(crate) fn sound_file_for(sound_type: &str) -> Option<&'static str> {
+    match sound_type {
+        "message" => Some("new_mesage.mp3"),
+        "contact_online" => Some("contact_online.mp3"),
+        "contact_offline" => Some("contact_online.mp3"), // Reuse same sound for offline
+        "nudge" => Some("nudge.mp3"),
+        "video_call" => Some("video_call.mp3"),
+        _ => None,
+    }
+}
+
+/// Show a desktop notification through the notification plugin
+pub(crate) fn send_desktop_notification(
+    app: &AppHandle,
+    title: String,
+    body: String,
+    with_sound: bool,
+) -> Result<(), String> {
     use tauri_plugin_notification::NotificationExt;
 
     // Check if permission is granted
@@ -61,82 +156,129 @@ async fn show_notification(app: AppHandle, title: String, body: String) -> Resul
 
     println!("Notification permission state: {:?}", permission);
 
-    // Show notification with sound
-    app.notification()
-        .builder()
-        .title(title)
-        .body(body)
-        .sound("default") // Add default system sound
-        .show()
-        .map_err(|e| {
-            eprintln!("Failed to show notification: {}", e);
-            format!("Failed to show notification: {}", e)
-        })?;
+    let mut builder = app.notification().builder().title(title).body(body);
+    if with_sound {
+        // Add default system sound
+        builder = builder.sound("default");
+    }
+
+    builder.show().map_err(|e| {
+        eprintln!("Failed to show notification: {}", e);
+        format!("Failed to show notification: {}", e)
+    })?;
 
     println!("Notification shown successfully");
     Ok(())
 }
 
-/// Play a sound file from the public/sounds directory
+/// Show a system notification with title and body
+/// The notification policy decides whether it is shown, taking into account the sender's
+/// overrides, the event toggles, focus of the chat window it is about, quiet hours and rate limits.
 #[tauri::command]
-async fn play_sound(app: AppHandle, sound_type: String, volume: f32) -> Result<(), String> {
-    // Map sound types to file paths
-    let sound_file = match sound_type.as_str() {
-        "message" => "new_mesage.mp3",
-        "contact_online" => "contact_online.mp3",
-        "contact_offline" => "contact_online.mp3", // Reuse same sound for offline
-        "nudge" => "nudge.mp3",
-        "video_call" => "video_call.mp3",
-        _ => return Err(format!("Unknown sound type: {}", sound_type)),
+async fn show_notification(
+    app: AppHandle,
+    title: String,
+    body: String,
+    sender_id: Option<String>,
+    conversation_id: Option<String>,
+    kind: Option<NotificationEventKind>,
+) -> Result<(), String> {
+    let event = NotificationEvent {
+        kind: kind.unwrap_or(NotificationEventKind::Message),
+        sender_id,
+        conversation_id,
+        title: Some(title),
+        body: Some(body),
+    };
+    let requested = RequestedOutputs {
+        alert: true,
+        sound: false,
     };
 
-    // Construct the asset URL
-    let asset_url = format!("sounds/{}", sound_file);
-
-    // Emit an event to the frontend to play the sound
-    // We use the frontend's Audio API because Tauri doesn't have built-in audio playback
-    app.emit(
-        "play-sound",
-        serde_json::json!({
-            "soundFile": asset_url,
-            "volume": volume.clamp(0.0, 1.0)
-        }),
-    )
-    .map_err(|e| format!("Failed to emit play-sound event: {}", e))?;
-
+    notification_policy::dispatch(&app, &event, requested)?;
     Ok(())
 }
 
-/// Open a file dialog for selecting a file to send
-/// Note: In Tauri v2, file dialogs should be handled from the frontend
+/// Play a sound file from the public/sounds directory
+/// Whether to play and at what volume is decided by the notification policy
 #[tauri::command]
-async fn open_file_dialog(_app: AppHandle) -> Result<Option<String>, String> {
-    // File dialog should be handled from frontend using HTML input element
-    // This is a placeholder for compatibility
-    Err("File dialog should be handled from frontend".to_string())
+async fn play_sound(
+    app: AppHandle,
+    sound_type: String,
+    sender_id: Option<String>,
+    conversation_id: Option<String>,
+) -> Result<(), String> {
+    let kind = NotificationEventKind::from_sound_type(&sound_type)
+        .ok_or_else(|| format!("Unknown sound type: {}", sound_type))?;
+
+    let event = NotificationEvent {
+        kind,
+        sender_id,
+        conversation_id,
+        title: None,
+        body: None,
+    };
+    let requested = RequestedOutputs {
+        alert: false,
+        sound: true,
+    };
+
+    notification_policy::dispatch(&app, &event, requested)?;
+    Ok(())
 }
 
-/// Read a file from disk and return its bytes
-/// Used for uploading recorded audio files
+/// Preview a sound from the public/sounds directory, e.g. in the settings
+/// Always plays regardless of the enabled setting, quiet hours or rate limits; only the volume
+/// is applied.
 #[tauri::command]
-async fn read_file_bytes(path: String) -> Result<Vec<u8>, String> {
-    fs::read(&path).map_err(|e| format!("Failed to read file '{}': {}", path, e))
+async fn preview_sound(app: AppHandle, sound_type: String) -> Result<(), String> {
+    let sound_file =
+        sound_file_for(&sound_type).ok_or_else(|| format!("Unknown sound type: {}", sound_type))?;
+    let sound = SoundDecision {
+        sound_file: format!("sounds/{}", sound_file),
+        volume: app
+            .state::<SettingsManager>()
+            .notification_policy_for(None)
+            .volume(),
+    };
+    notification_policy::play(&app, &sound)
 }
 
-/// Save a file to the designated downloads folder
+/// Open a file dialog for selecting a file to send
+/// Returns a short-lived grant to read the picked file, or None when the dialog is cancelled.
 #[tauri::command]
-async fn save_file(app: AppHandle, file_data: Vec<u8>, filename: String) -> Result<String, String> {
-    // Get the downloads directory
-    let downloads_dir = app
-        .path()
-        .download_dir()
-        .map_err(|e| format!("Failed to get downloads directory: {}", e))?;
+async fn open_file_dialog(
+    app: AppHandle,
+    broker: tauri::State<'_, FileAccessBroker>,
+    window: tauri::WebviewWindow,
+) -> Result<Option<GrantedFile>, String> {
+    use tauri_plugin_dialog::DialogExt;
+
+    let Some(picked) = app.dialog().file().set_parent(&window).blocking_pick_file() else {
+        return Ok(None);
+    };
+    let path = picked
+        .into_path()
+        .map_err(|e| format!("Failed to get selected file path: {}", e))?;
 
-    // Create the full path
-    let file_path = downloads_dir.join(&filename);
+    Ok(Some(broker.grant(&path, GrantSource::Dialog)?))
+}
 
-    // Write the file
-    fs::write(&file_path, file_data).map_err(|e| format!("Failed to write file: {}", e))?;
+/// Save a received file to the download location from the file settings
+/// Pass the sender's name to save into their folder when per-contact folders are turned on.
+#[tauri::command]
+async fn save_file(
+    app: AppHandle,
+    file_data: Vec<u8>,
+    filename: String,
+    contact_name: Option<String>,
+) -> Result<String, SaveFileError> {
+    let downloads_dir = downloads::app_download_dir(&app, contact_name.as_deref())?;
+
+    // Write the file; quitting waits for it to finish
+    let coordinator = app.state::<ShutdownCoordinator>();
+    let _guard = coordinator.guard();
+    let file_path = downloads::write_file(&downloads_dir, &filename, &file_data)?;
 
     Ok(file_path.to_string_lossy().to_string())
 }
@@ -162,54 +304,123 @@ async fn set_auto_launch(app: AppHandle, enabled: bool) -> Result<(), String> {
     Ok(())
 }
 
+/// Open the chat window for a contact, group conversation or bot, or focus it if already open
 #[tauri::command]
-fn open_chat_window(
-    handle: AppHandle,
-    _webview_window: tauri::WebviewWindow,
-    dialog_window: String,
-    contact_name: Option<String>,
-) -> Result<(), String> {
-    let dialog_label = format!("chat-{}", dialog_window);
-    let title = contact_name
-        .clone()
-        .unwrap_or_else(|| dialog_window.clone());
-    let url = if let Some(name) = contact_name {
-        format!(
-            "chat-window.html?contactUserId={}&contactName={}",
-            dialog_window,
-            urlencoding::encode(&name)
-        )
-    } else {
-        format!("chat-window.html?contactId={}", dialog_window)
-    };
+fn open_chat_window(handle: AppHandle, target: ChatTarget) -> Result<(), ChatWindowError> {
+    show_chat_window(&handle, &target)
+}
+
+/// Focus the chat window for `target`, or open it
+/// New windows reopen where the chat was last closed, or cascade from the center of the screen.
+/// If the window cannot be created it is retried once with the default geometry; if that fails
+/// too, the user is told through a native notification and the error is returned.
+pub(crate) fn show_chat_window(
+    handle: &AppHandle,
+    target: &ChatTarget,
+) -> Result<(), ChatWindowError> {
+    target
+        .validate()
+        .map_err(|message| ChatWindowError::InvalidTarget { message })?;
+    let dialog_label = target.label();
+
+    if let ChatTarget::Contact {
+        user_id,
+        display_name,
+    } = target
+    {
+        recent_chats::record_activity(handle, user_id, display_name.as_deref());
+    }
 
     if let Some(existing_window) = handle.get_webview_window(&dialog_label) {
         if let Err(e) = existing_window.set_focus() {
             error!("Error focusing the chat window: {:?}", e);
         }
-    } else {
-        let _ =
-            WebviewWindowBuilder::new(&handle, &dialog_label, tauri::WebviewUrl::App(url.into()))
-                .title(title)
-                .decorations(false)
-                .resizable(true)
-                .transparent(true)
-                .inner_size(630.0, 530.0)
-                .min_inner_size(600.0, 500.0)
-                .center()
-                .build()
-                .unwrap();
+        return Ok(());
     }
+
+    let state = handle.state::<AppState>();
+    let geometry = state.chat_windows().placement_for(handle, &dialog_label);
+
+    let window = match build_chat_window(handle, target, Some(geometry)) {
+        Ok(window) => window,
+        Err(e) => {
+            eprintln!(
+                "Failed to create chat window {}, retrying with default geometry: {}",
+                dialog_label, e
+            );
+            build_chat_window(handle, target, None).map_err(|e| {
+                let error = ChatWindowError::Build {
+                    label: dialog_label.clone(),
+                    message: e.to_string(),
+                };
+                eprintln!("{}", error);
+
+                let title = format!("Couldn't open chat with {}", target.title());
+                let body = "The chat window could not be created. Please try again.".to_string();
+                if let Err(e) = send_desktop_notification(handle, title, body, false) {
+                    eprintln!("Failed to report chat window error: {}", e);
+                }
+                error
+            })?
+        }
+    };
+
+    let geometry = chat_windows::geometry_of(&window).unwrap_or(geometry);
+    state.chat_windows().track(&window, geometry);
     Ok(())
 }
 
+/// Create the chat window for `target`, at `geometry` or centered with the default size
+fn build_chat_window(
+    handle: &AppHandle,
+    target: &ChatTarget,
+    geometry: Option<WindowGeometry>,
+) -> tauri::Result<tauri::WebviewWindow> {
+    let (min_width, min_height) = (600.0, 500.0);
+    let (width, height) = geometry
+        .map(|geometry| (geometry.width, geometry.height))
+        .unwrap_or(DEFAULT_CHAT_WINDOW_SIZE);
+
+    let builder = WebviewWindowBuilder::new(
+        handle,
+        target.label(),
+        tauri::WebviewUrl::App(target.url().into()),
+    )
+    .title(target.title())
+    .decorations(false)
+    .resizable(true)
+    .transparent(true)
+    .inner_size(width.max(min_width), height.max(min_height))
+    .min_inner_size(min_width, min_height);
+
+    match geometry {
+        Some(geometry) => builder.position(geometry.x, geometry.y),
+        None => builder.center(),
+    }
+    .build()
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .plugin(tauri_plugin_mic_recorder::init())
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(tauri_plugin_positioner::init())
         .plugin(tauri_plugin_os::init())
-        .plugin(tauri_plugin_window_state::Builder::default().build())
+        .plugin(
+            // Visibility is decided in setup so that --minimized can keep the window in the tray
+            // Chat window geometry is managed by the chat window registry, toasts by the toast manager
+            tauri_plugin_window_state::Builder::default()
+                .with_state_flags(
+                    tauri_plugin_window_state::StateFlags::all()
+                        & !tauri_plugin_window_state::StateFlags::VISIBLE,
+                )
+                .with_filter(|label| {
+                    !chat_windows::is_chat_window(label)
+                        && !label.starts_with(toasts::TOAST_WINDOW_PREFIX)
+                })
+                .build(),
+        )
         .plugin(tauri_plugin_opener::init())
         .plugin(tauri_plugin_notification::init())
         .plugin(tauri_plugin_dialog::init())
@@ -219,22 +430,76 @@ pub fn run() {
             Some(vec!["--minimized"]),
         ))
         .setup(|app| {
-            // Get the app data directory for storage
-            let mut app_data_dir = app
-                .path()
-                .app_data_dir()
-                .expect("Failed to get app data directory");
-
-            // Support multiple instances via TAURI_PROFILE environment variable
-            // Usage: TAURI_PROFILE=user1 ./app or TAURI_PROFILE=user2 ./app
-            let profile = std::env::var("TAURI_PROFILE").ok();
+            // A bad argument must not keep the app from starting, nor drop the others
+            let (launch_args, arg_errors) = LaunchArgs::from_env();
+            for e in arg_errors {
+                error!("Ignoring launch argument: {}", e);
+            }
+
+            // Get the app data directory for storage, unless overridden with --data-dir
+            let data_root = match launch_args.data_dir.clone() {
+                Some(data_dir) => data_dir,
+                None => app
+                    .path()
+                    .app_data_dir()
+                    .expect("Failed to get app data directory"),
+            };
+
+            // Support multiple instances via --profile (see the profile commands)
+            // TAURI_PROFILE is still honored as a fallback for existing scripts
+            let profile = match launch_args.profile.clone() {
+                Some(profile) => Some(profile),
+                None => match std::env::var("TAURI_PROFILE") {
+                    Ok(profile) => match cli::validate_profile_name(&profile) {
+                        Ok(()) => Some(profile),
+                        Err(e) => {
+                            error!("Ignoring TAURI_PROFILE: {}", e);
+                            None
+                        }
+                    },
+                    Err(_) => None,
+                },
+            };
+            let app_data_dir = profiles::profile_data_dir(&data_root, profile.as_deref());
             if let Some(ref profile_name) = profile {
-                // Create a profile-specific subdirectory
-                app_data_dir = app_data_dir.join("profiles").join(profile_name);
                 println!("Using profile: {}", profile_name);
                 println!("Data directory: {:?}", app_data_dir);
             }
 
+            // A named profile being renamed or deleted is waited for, so the instance never
+            // starts in a directory that is about to move
+            let change_lock = match profile.as_deref() {
+                Some(name) => profiles::lock_changes(&data_root, name, true).unwrap_or_else(|e| {
+                    error!("{}", e);
+                    None
+                }),
+                None => None,
+            };
+
+            // Only one instance per profile; later launches hand their arguments to this one
+            let instance_status =
+                single_instance::acquire(&app_data_dir, std::env::args().skip(1).collect());
+            drop(change_lock);
+            let mut instance_lock = match instance_status? {
+                InstanceStatus::Primary(lock) => lock,
+                InstanceStatus::Forwarded => {
+                    println!("Profile already running, forwarded launch arguments");
+                    std::process::exit(0);
+                }
+            };
+            single_instance::listen(app.handle().clone(), &mut instance_lock);
+            app.manage(instance_lock);
+
+            // Ask which profile to use when several exist and none was requested
+            let show_profile_picker = profile.is_none()
+                && !launch_args.minimized
+                && !profiles::list_named_profiles(&data_root)
+                    .unwrap_or_else(|e| {
+                        eprintln!("{}", e);
+                        Vec::new()
+                    })
+                    .is_empty();
+
             let settings_storage_path = app_data_dir.join("settings.json");
             let auth_prefs_storage_path = app_data_dir.join("auth_preferences.json");
 
@@ -244,60 +509,121 @@ pub fn run() {
 
             // Initialize settings manager
             let settings_manager = SettingsManager::new(settings_storage_path);
+            let start_minimized =
+                launch_args.minimized || settings_manager.get_settings().startup.start_minimized;
             app.manage(settings_manager);
 
+            // Initialize quiet hours and deliver digests when quiet periods end
+            app.manage(QuietHoursScheduler::new(Arc::new(SystemClock)));
+            quiet_hours::spawn_digest_task(app.handle().clone());
+
+            // Initialize the notification policy engine
+            app.manage(NotificationPolicyEngine::new());
+
+            // Initialize the toast manager
+            app.manage(ToastManager::new());
+
+            // Flashes unfocused chat windows when messages arrive
+            app.manage(ChatAttention::new());
+
+            // Rate limits nudges per chat
+            app.manage(NudgeLimiter::new());
+
+            // Keeps a chat window from shaking twice at once
+            app.manage(ShakingWindows::new());
+
+            // Streams received files from the backend to the download location
+            app.manage(DownloadManager::new());
+
+            // Streams files being sent from disk to the backend
+            app.manage(UploadManager::new());
+
+            // Files are read by grant, never from the app's own data
+            let default_data_dir = app.path().app_data_dir()?;
+            app.manage(FileAccessBroker::new(
+                vec![
+                    data_root.clone(),
+                    app_data_dir.clone(),
+                    default_data_dir.clone(),
+                    app.path().app_config_dir()?,
+                    app.path().app_local_data_dir()?,
+                ],
+                Some(default_data_dir.join("tauri-plugin-mic-recorder")),
+            ));
+
+            // Quitting, from the tray or with SIGTERM, goes through the shutdown coordinator
+            app.manage(ShutdownCoordinator::new());
+            #[cfg(unix)]
+            shutdown::listen_for_sigterm(app.handle().clone());
+
             // Initialize app state
-            let state = AppState::new(profile);
+            let chat_windows = ChatWindowRegistry::new(app_data_dir.join("chat_windows.json"));
+            let recent_chats = RecentChats::new(app_data_dir.join("recent_chats.json"));
+            let state = AppState::new(profile, data_root, launch_args, chat_windows, recent_chats);
             app.manage(state);
 
-            // Create system tray menu
-            let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
-            let show = MenuItem::with_id(app, "show", "Show Window", true, None::<&str>)?;
-            let menu = Menu::with_items(app, &[&show, &quit])?;
-
-            // Build tray icon
-            let _tray = TrayIconBuilder::new()
-                .icon(app.default_window_icon().unwrap().clone())
-                .menu(&menu)
-                .show_menu_on_left_click(false)
-                .on_menu_event(|app, event| match event.id.as_ref() {
-                    "quit" => {
-                        app.exit(0);
-                    }
-                    "show" => {
-                        if let Some(window) = app.get_webview_window("main") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
-                        }
-                    }
-                    _ => {}
-                })
-                .on_tray_icon_event(|tray, event| {
-                    if let TrayIconEvent::Click {
-                        button: MouseButton::Left,
-                        button_state: MouseButtonState::Up,
-                        ..
-                    } = event
-                    {
-                        let app = tray.app_handle();
-                        if let Some(window) = app.get_webview_window("main") {
-                            let _ = window.show();
-                            let _ = window.set_focus();
+            // Create the system tray, showing presence and unread messages, with status, options and
+            // sign out entries
+            app.manage(TrayManager::new());
+            // Without a tray (e.g. GNOME without extensions) the app keeps working, but closing
+            // the main window quits and the window is never started hidden
+            let has_tray = match tray::build(app.handle()) {
+                Ok(()) => true,
+                Err(e) => {
+                    eprintln!("Failed to create tray icon: {}", e);
+                    false
+                }
+            };
+
+            // The main window is created hidden; show it unless starting minimized to the tray
+            if show_profile_picker {
+                profiles::open_profile_picker(app.handle())?;
+            } else if !start_minimized || !has_tray {
+                if let Some(window) = app.get_webview_window("main") {
+                    window.show()?;
+                    window.set_focus()?;
+                }
+            }
+
+            // spirit:// links arrive as arguments on Linux and Windows (later launches forward
+            // theirs to this instance) and as open URL events on macOS
+            #[cfg(any(target_os = "linux", all(debug_assertions, windows)))]
+            if let Err(e) = app.deep_link().register_all() {
+                eprintln!("Failed to register link scheme: {}", e);
+            }
+            deep_link::dispatch_all(app.handle(), &DeepLink::from_args(std::env::args().skip(1)));
+
+            let handle = app.handle().clone();
+            app.deep_link().on_open_url(move |event| {
+                let links: Vec<DeepLink> = event
+                    .urls()
+                    .iter()
+                    .filter_map(|url| match DeepLink::from_url(url) {
+                        Ok(link) => Some(link),
+                        Err(e) => {
+                            eprintln!("Ignoring link {}: {}", url, e);
+                            None
                         }
-                    }
-                })
-                .build(app)?;
+                    })
+                    .collect();
+                deep_link::dispatch_all(&handle, &links);
+            });
 
             Ok(())
         })
-        .on_window_event(|window, event| {
-            if let WindowEvent::CloseRequested { api, .. } = event {
-                // Only minimize main window to tray, close others normally
-                if window.label() == "main" {
-                    api.prevent_close();
-                    let _ = window.hide();
-                }
+        .on_window_event(|window, event| match event {
+            // The close behavior setting decides what closing the main window does; others
+            // close normally
+            WindowEvent::CloseRequested { api, .. } if window.label() == "main" => {
+                api.prevent_close();
+                close_behavior::on_main_window_close(window);
+            }
+            WindowEvent::Destroyed => {
+                window
+                    .state::<ShutdownCoordinator>()
+                    .forget_window(window.label());
             }
+            _ => {}
         })
         .invoke_handler(tauri::generate_handler![
             auth_preferences::get_auth_preferences,
@@ -308,17 +634,61 @@ pub fn run() {
             settings::update_notification_settings,
             settings::update_startup_settings,
             settings::update_file_settings,
+            settings::update_quiet_hours,
+            settings::get_contact_notification_override,
+            settings::set_contact_notification_override,
+            settings::clear_contact_notification_override,
             settings::reset_settings,
             get_profile,
+            profiles::list_profiles,
+            profiles::create_profile,
+            profiles::rename_profile,
+            profiles::delete_profile,
+            profiles::switch_profile,
             open_chat_window,
+            chat_windows::list_chat_windows,
+            chat_windows::focus_next_chat_window,
+            chat_windows::close_all_chat_windows,
+            chat_windows::close_idle_chat_windows,
+            chat_attention::flash_chat_window,
+            nudges::reserve_sent_nudge,
+            nudges::release_sent_nudge,
+            nudges::receive_nudge,
+            nudges::shake_window,
             request_notification_permission,
             show_notification,
             play_sound,
+            preview_sound,
+            notification_policy::dispatch_notification,
+            toasts::show_toast,
+            toasts::pause_toast,
+            toasts::resume_toast,
+            toasts::toast_clicked,
+            tray::set_tray_state,
+            shutdown::register_shutdown_hook,
+            shutdown::complete_shutdown_hook,
             open_file_dialog,
             save_file,
-            read_file_bytes,
+            file_access::read_granted_file,
+            file_access::revoke_file_grant,
+            file_access::stop_voice_recording,
+            download_manager::start_file_download,
+            download_manager::cancel_file_download,
+            upload_manager::queue_upload,
+            upload_manager::upload_file,
+            upload_manager::cancel_upload,
+            upload_manager::forget_upload,
             set_auto_launch
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            // Every way of quitting runs the shutdown steps before the app actually exits
+            if let RunEvent::ExitRequested { code, api, .. } = event {
+                if !app.state::<ShutdownCoordinator>().is_finished() {
+                    api.prevent_exit();
+                    shutdown::request(app, code.unwrap_or(0));
+                }
+            }
+        });
 }
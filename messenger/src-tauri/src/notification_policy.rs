@@ -0,0 +1,733 @@
+use crate::chat_attention;
+use crate::quiet_hours::{QuietHoursScheduler, SuppressedAlert};
+use crate::recent_chats;
+use crate::settings::{
+    AppSettings, EffectiveNotificationPolicy, NotificationEventToggles, SettingsManager,
+};
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Kinds of events that can produce a notification or sound
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NotificationEventKind {
+    Message,
+    ContactOnline,
+    ContactOffline,
+    ContactRequest,
+    Nudge,
+    Call,
+    File,
+}
+
+impl NotificationEventKind {
+    /// Map a frontend sound type to the event it announces
+    pub fn from_sound_type(sound_type: &str) -> Option<Self> {
+        match sound_type {
+            "message" => Some(Self::Message),
+            "contact_online" => Some(Self::ContactOnline),
+            "contact_offline" => Some(Self::ContactOffline),
+            "nudge" => Some(Self::Nudge),
+            "video_call" => Some(Self::Call),
+            _ => None,
+        }
+    }
+
+    /// Sound type played for this event, if it has one
+    fn sound_type(self) -> Option<&'static str> {
+        match self {
+            Self::Message => Some("message"),
+            Self::ContactOnline => Some("contact_online"),
+            Self::ContactOffline => Some("contact_offline"),
+            Self::Nudge => Some("nudge"),
+            Self::Call => Some("video_call"),
+            Self::ContactRequest | Self::File => None,
+        }
+    }
+
+    /// Minimum time between two alerts (or two sounds) of this kind from the same sender
+    fn min_interval(self) -> Duration {
+        match self {
+            Self::Message | Self::File => Duration::from_secs(2),
+            Self::ContactOnline | Self::ContactOffline => Duration::from_secs(5),
+            Self::ContactRequest | Self::Nudge | Self::Call => Duration::ZERO,
+        }
+    }
+
+    /// Whether a focused chat window with the sender mutes the (alert, sound) for this event
+    fn muted_by_focused_chat(self) -> (bool, bool) {
+        match self {
+            Self::Message => (true, true),
+            // The chat window shows nudges and files itself, but the nudge sound still plays
+            Self::Nudge | Self::File => (true, false),
+            _ => (false, false),
+        }
+    }
+}
+
+impl NotificationEventToggles {
+    /// Whether notifications for this kind of event are switched on
+    pub fn allows(&self, kind: NotificationEventKind) -> bool {
+        match kind {
+            NotificationEventKind::Message => self.message,
+            NotificationEventKind::ContactOnline => self.contact_online,
+            NotificationEventKind::ContactOffline => self.contact_offline,
+            NotificationEventKind::ContactRequest => self.contact_request,
+            NotificationEventKind::Nudge => self.nudge,
+            NotificationEventKind::Call => self.call,
+            NotificationEventKind::File => self.file,
+        }
+    }
+}
+
+/// An event to run through the notification policy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationEvent {
+    pub kind: NotificationEventKind,
+    pub sender_id: Option<String>,
+    /// Conversation the event happened in, so a focused group chat window counts as well
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+    pub title: Option<String>,
+    pub body: Option<String>,
+}
+
+/// Which outputs the caller wants the policy to consider
+#[derive(Debug, Clone, Copy)]
+pub struct RequestedOutputs {
+    pub alert: bool,
+    pub sound: bool,
+}
+
+/// Sound to play for an event
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SoundDecision {
+    pub sound_file: String,
+    pub volume: f32, // 0.0-1.0
+}
+
+/// What to do for an event
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NotificationDecision {
+    pub desktop_alert: bool,
+    pub alert_sound: bool, // Desktop alert carries the system sound (no app sound requested)
+    pub sound: Option<SoundDecision>,
+    pub queued_for_digest: bool,
+}
+
+/// Everything outside the event itself that the policy depends on
+pub struct PolicyContext<'a> {
+    pub settings: &'a AppSettings,
+    pub chat_focused: bool,
+    pub quiet_hours_active: bool,
+    pub now: Instant,
+}
+
+type RateKey = (NotificationEventKind, Option<String>);
+
+/// Single source of truth for whether and how an event notifies the user
+pub struct NotificationPolicyEngine {
+    last_alert: Mutex<HashMap<RateKey, Instant>>,
+    last_sound: Mutex<HashMap<RateKey, Instant>>,
+}
+
+impl NotificationPolicyEngine {
+    /// Create a new NotificationPolicyEngine with empty rate limit state
+    pub fn new() -> Self {
+        Self {
+            last_alert: Mutex::new(HashMap::new()),
+            last_sound: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Decide what to do for an event
+    /// Delivered outputs count against the rate limits, so call this once per event.
+    pub fn decide(
+        &self,
+        event: &NotificationEvent,
+        requested: RequestedOutputs,
+        ctx: &PolicyContext,
+    ) -> NotificationDecision {
+        let mut decision = NotificationDecision::default();
+
+        let contact_override = event
+            .sender_id
+            .as_deref()
+            .and_then(|id| ctx.settings.contact_overrides.get(id));
+        let policy = ctx.settings.notifications.resolve(contact_override);
+
+        if !policy.enabled || !ctx.settings.notifications.events.allows(event.kind) {
+            return decision;
+        }
+
+        let (focus_mutes_alert, focus_mutes_sound) = event.kind.muted_by_focused_chat();
+        let key: RateKey = (event.kind, event.sender_id.clone());
+
+        if requested.alert && policy.desktop_alerts && !(ctx.chat_focused && focus_mutes_alert) {
+            if ctx.quiet_hours_active {
+                decision.queued_for_digest = true;
+            } else if Self::take_slot(&self.last_alert, &key, event.kind, ctx.now) {
+                decision.desktop_alert = true;
+                decision.alert_sound = !requested.sound && policy.sound_enabled;
+            }
+        }
+
+        if requested.sound
+            && policy.sound_enabled
+            && !ctx.quiet_hours_active
+            && !(ctx.chat_focused && focus_mutes_sound)
+        {
+            if let Some(sound_file) = Self::sound_file(event.kind, &policy) {
+                if Self::take_slot(&self.last_sound, &key, event.kind, ctx.now) {
+                    decision.sound = Some(SoundDecision {
+                        sound_file: format!("sounds/{}", sound_file),
+                        volume: policy.volume(),
+                    });
+                }
+            }
+        }
+
+        decision
+    }
+
+    /// Sound file for an event, honoring a contact's custom message sound
+    fn sound_file(
+        kind: NotificationEventKind,
+        policy: &EffectiveNotificationPolicy,
+    ) -> Option<&'static str> {
+        let sound_type = match (kind, &policy.sound_type) {
+            (NotificationEventKind::Message, Some(custom)) => custom.as_str(),
+            _ => kind.sound_type()?,
+        };
+        crate::sound_file_for(sound_type)
+    }
+
+    /// Record a delivery for `key` unless it falls inside the kind's rate limit window
+    fn take_slot(
+        slots: &Mutex<HashMap<RateKey, Instant>>,
+        key: &RateKey,
+        kind: NotificationEventKind,
+        now: Instant,
+    ) -> bool {
+        let mut slots = slots.lock().unwrap();
+        if let Some(last) = slots.get(key) {
+            if now.saturating_duration_since(*last) < kind.min_interval() {
+                return false;
+            }
+        }
+        slots.insert(key.clone(), now);
+        true
+    }
+}
+
+/// Labels of the chat windows that show an event: the group chat of its conversation, or the
+/// one-on-one chat with its sender
+fn event_chat_labels(event: &NotificationEvent) -> Vec<String> {
+    event
+        .sender_id
+        .as_deref()
+        .map(|sender_id| chat_attention::chat_labels(sender_id, event.conversation_id.clone()))
+        .unwrap_or_default()
+}
+
+/// Whether a chat window showing the event is open and focused
+fn is_chat_focused(app: &AppHandle, event: &NotificationEvent) -> bool {
+    event_chat_labels(event)
+        .iter()
+        .filter_map(|label| app.get_webview_window(label))
+        .any(|window| window.is_focused().unwrap_or(false))
+}
+
+/// Run an event through the policy and carry out the decision
+pub(crate) fn dispatch(
+    app: &AppHandle,
+    event: &NotificationEvent,
+    requested: RequestedOutputs,
+) -> Result<NotificationDecision, String> {
+    // Incoming messages count as activity in the sender's chat window and recent chats
+    let is_chat_event = matches!(
+        event.kind,
+        NotificationEventKind::Message | NotificationEventKind::Nudge | NotificationEventKind::File
+    );
+    if let Some(sender_id) = event.sender_id.as_deref().filter(|_| is_chat_event) {
+        let state = app.state::<AppState>();
+        for label in event_chat_labels(event) {
+            state.chat_windows().touch(&label);
+        }
+        recent_chats::record_activity(app, sender_id, None);
+    }
+
+    let decision = evaluate(app, event, requested);
+
+    if decision.desktop_alert {
+        crate::send_desktop_notification(
+            app,
+            event.title.clone().unwrap_or_default(),
+            event.body.clone().unwrap_or_default(),
+            decision.alert_sound,
+        )?;
+    }
+
+    if let Some(sound) = &decision.sound {
+        play(app, sound)?;
+    }
+
+    Ok(decision)
+}
+
+/// Play a sound in the main window
+pub(crate) fn play(app: &AppHandle, sound: &SoundDecision) -> Result<(), String> {
+    // We use the frontend's Audio API because Tauri doesn't have built-in audio playback
+    app.emit(
+        "play-sound",
+        serde_json::json!({
+            "soundFile": sound.sound_file,
+            "volume": sound.volume
+        }),
+    )
+    .map_err(|e| format!("Failed to emit play-sound event: {}", e))
+}
+
+/// Run an event through the policy and queue it for the digest if quiet hours hold it back
+/// The caller carries out the rest of the decision.
+pub(crate) fn evaluate(
+    app: &AppHandle,
+    event: &NotificationEvent,
+    requested: RequestedOutputs,
+) -> NotificationDecision {
+    let settings = app.state::<SettingsManager>().get_settings();
+    let scheduler = app.state::<QuietHoursScheduler>();
+
+    let ctx = PolicyContext {
+        settings: &settings,
+        chat_focused: is_chat_focused(app, event),
+        quiet_hours_active: scheduler.should_suppress(
+            &settings.quiet_hours,
+            event.kind == NotificationEventKind::Call,
+        ),
+        now: Instant::now(),
+    };
+    let decision = app
+        .state::<NotificationPolicyEngine>()
+        .decide(event, requested, &ctx);
+
+    if decision.queued_for_digest {
+        scheduler.queue(SuppressedAlert {
+            title: event.title.clone().unwrap_or_default(),
+            body: event.body.clone().unwrap_or_default(),
+            sender_id: event.sender_id.clone(),
+        });
+    }
+
+    decision
+}
+
+/// Run an event through the notification policy, showing the alert and playing the sound it allows
+#[tauri::command]
+pub async fn dispatch_notification(
+    app: AppHandle,
+    event: NotificationEvent,
+) -> Result<NotificationDecision, String> {
+    let requested = RequestedOutputs {
+        alert: event.title.is_some(),
+        sound: true,
+    };
+    dispatch(&app, &event, requested)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::ContactNotificationOverride;
+
+    const BOTH: RequestedOutputs = RequestedOutputs {
+        alert: true,
+        sound: true,
+    };
+
+    fn event(kind: NotificationEventKind, sender_id: Option<&str>) -> NotificationEvent {
+        NotificationEvent {
+            kind,
+            sender_id: sender_id.map(str::to_string),
+            conversation_id: None,
+            title: None,
+            body: None,
+        }
+    }
+
+    fn ctx(settings: &AppSettings, now: Instant) -> PolicyContext<'_> {
+        PolicyContext {
+            settings,
+            chat_focused: false,
+            quiet_hours_active: false,
+            now,
+        }
+    }
+
+    fn sound(file: &str, volume: f32) -> Option<SoundDecision> {
+        Some(SoundDecision {
+            sound_file: format!("sounds/{}", file),
+            volume,
+        })
+    }
+
+    #[test]
+    fn alerts_and_plays_the_event_sound_by_default() {
+        let engine = NotificationPolicyEngine::new();
+        let settings = AppSettings::default();
+
+        let decision = engine.decide(
+            &event(NotificationEventKind::Message, Some("alice")),
+            BOTH,
+            &ctx(&settings, Instant::now()),
+        );
+
+        assert_eq!(
+            decision,
+            NotificationDecision {
+                desktop_alert: true,
+                alert_sound: false,
+                sound: sound("new_mesage.mp3", 0.8),
+                queued_for_digest: false,
+            }
+        );
+    }
+
+    #[test]
+    fn an_alert_without_an_app_sound_carries_the_system_sound() {
+        let engine = NotificationPolicyEngine::new();
+        let mut settings = AppSettings::default();
+        let requested = RequestedOutputs {
+            alert: true,
+            sound: false,
+        };
+
+        let decision = engine.decide(
+            &event(NotificationEventKind::ContactRequest, Some("alice")),
+            requested,
+            &ctx(&settings, Instant::now()),
+        );
+        assert!(decision.desktop_alert && decision.alert_sound);
+        assert_eq!(decision.sound, None);
+
+        settings.notifications.sound_enabled = false;
+        let decision = engine.decide(
+            &event(NotificationEventKind::ContactRequest, Some("bob")),
+            requested,
+            &ctx(&settings, Instant::now()),
+        );
+        assert!(decision.desktop_alert && !decision.alert_sound);
+    }
+
+    #[test]
+    fn the_master_toggle_silences_everything() {
+        let engine = NotificationPolicyEngine::new();
+        let mut settings = AppSettings::default();
+        settings.notifications.enabled = false;
+
+        for kind in [
+            NotificationEventKind::Message,
+            NotificationEventKind::ContactOnline,
+            NotificationEventKind::Nudge,
+            NotificationEventKind::Call,
+            NotificationEventKind::File,
+        ] {
+            assert_eq!(
+                engine.decide(
+                    &event(kind, Some("alice")),
+                    BOTH,
+                    &ctx(&settings, Instant::now())
+                ),
+                NotificationDecision::default(),
+                "{:?}",
+                kind
+            );
+        }
+    }
+
+    #[test]
+    fn event_toggles_silence_only_their_event() {
+        let engine = NotificationPolicyEngine::new();
+        let mut settings = AppSettings::default();
+        settings.notifications.events.contact_online = false;
+        let now = Instant::now();
+
+        assert_eq!(
+            engine.decide(
+                &event(NotificationEventKind::ContactOnline, Some("alice")),
+                BOTH,
+                &ctx(&settings, now)
+            ),
+            NotificationDecision::default()
+        );
+        let decision = engine.decide(
+            &event(NotificationEventKind::ContactOffline, Some("alice")),
+            BOTH,
+            &ctx(&settings, now),
+        );
+        assert!(decision.desktop_alert);
+        assert_eq!(decision.sound, sound("contact_online.mp3", 0.8));
+    }
+
+    #[test]
+    fn desktop_alerts_and_sounds_switch_off_separately() {
+        let engine = NotificationPolicyEngine::new();
+        let mut settings = AppSettings::default();
+        settings.notifications.desktop_alerts = false;
+        let now = Instant::now();
+
+        let decision = engine.decide(
+            &event(NotificationEventKind::Message, Some("alice")),
+            BOTH,
+            &ctx(&settings, now),
+        );
+        assert!(!decision.desktop_alert);
+        assert!(decision.sound.is_some());
+
+        settings.notifications.desktop_alerts = true;
+        settings.notifications.sound_enabled = false;
+        let decision = engine.decide(
+            &event(NotificationEventKind::Message, Some("bob")),
+            BOTH,
+            &ctx(&settings, now),
+        );
+        assert!(decision.desktop_alert);
+        assert_eq!(decision.sound, None);
+    }
+
+    #[test]
+    fn a_focused_chat_mutes_by_event_kind() {
+        let engine = NotificationPolicyEngine::new();
+        let settings = AppSettings::default();
+        let focused = PolicyContext {
+            chat_focused: true,
+            ..ctx(&settings, Instant::now())
+        };
+
+        // Messages are read in the chat itself
+        assert_eq!(
+            engine.decide(
+                &event(NotificationEventKind::Message, Some("alice")),
+                BOTH,
+                &focused
+            ),
+            NotificationDecision::default()
+        );
+
+        // The nudge shows in the chat, but its sound still plays
+        let decision = engine.decide(
+            &event(NotificationEventKind::Nudge, Some("alice")),
+            BOTH,
+            &focused,
+        );
+        assert!(!decision.desktop_alert);
+        assert_eq!(decision.sound, sound("nudge.mp3", 0.8));
+
+        // File offers show in the chat and have no sound of their own
+        assert_eq!(
+            engine.decide(
+                &event(NotificationEventKind::File, Some("alice")),
+                BOTH,
+                &focused
+            ),
+            NotificationDecision::default()
+        );
+
+        // Calls are announced whatever has focus
+        let decision = engine.decide(
+            &event(NotificationEventKind::Call, Some("alice")),
+            BOTH,
+            &focused,
+        );
+        assert!(decision.desktop_alert);
+        assert_eq!(decision.sound, sound("video_call.mp3", 0.8));
+    }
+
+    #[test]
+    fn quiet_hours_send_alerts_to_the_digest_without_sound() {
+        let engine = NotificationPolicyEngine::new();
+        let settings = AppSettings::default();
+        let quiet = PolicyContext {
+            quiet_hours_active: true,
+            ..ctx(&settings, Instant::now())
+        };
+
+        let decision = engine.decide(
+            &event(NotificationEventKind::Message, Some("alice")),
+            BOTH,
+            &quiet,
+        );
+        assert_eq!(
+            decision,
+            NotificationDecision {
+                queued_for_digest: true,
+                ..NotificationDecision::default()
+            }
+        );
+
+        // Nothing was delivered, so the rate limit is untouched once quiet hours end
+        let decision = engine.decide(
+            &event(NotificationEventKind::Message, Some("alice")),
+            BOTH,
+            &ctx(&settings, quiet.now),
+        );
+        assert!(decision.desktop_alert && decision.sound.is_some());
+    }
+
+    #[test]
+    fn rate_limits_apply_per_sender_and_kind() {
+        let engine = NotificationPolicyEngine::new();
+        let settings = AppSettings::default();
+        let start = Instant::now();
+        let online = |sender| event(NotificationEventKind::ContactOnline, Some(sender));
+
+        let first = engine.decide(&online("alice"), BOTH, &ctx(&settings, start));
+        assert!(first.desktop_alert && first.sound.is_some());
+
+        // Too soon for the same contact
+        let at = start + Duration::from_secs(4);
+        assert_eq!(
+            engine.decide(&online("alice"), BOTH, &ctx(&settings, at)),
+            NotificationDecision::default()
+        );
+
+        // Other contacts and other kinds have their own limits
+        let other = engine.decide(&online("bob"), BOTH, &ctx(&settings, at));
+        assert!(other.desktop_alert && other.sound.is_some());
+        let offline = engine.decide(
+            &event(NotificationEventKind::ContactOffline, Some("alice")),
+            BOTH,
+            &ctx(&settings, at),
+        );
+        assert!(offline.desktop_alert && offline.sound.is_some());
+
+        // The window has passed
+        let later = engine.decide(
+            &online("alice"),
+            BOTH,
+            &ctx(&settings, start + Duration::from_secs(5)),
+        );
+        assert!(later.desktop_alert && later.sound.is_some());
+    }
+
+    #[test]
+    fn nudges_and_calls_are_not_rate_limited() {
+        let engine = NotificationPolicyEngine::new();
+        let settings = AppSettings::default();
+        let now = Instant::now();
+
+        for kind in [NotificationEventKind::Nudge, NotificationEventKind::Call] {
+            for _ in 0..2 {
+                let decision =
+                    engine.decide(&event(kind, Some("alice")), BOTH, &ctx(&settings, now));
+                assert!(decision.sound.is_some(), "{:?}", kind);
+            }
+        }
+    }
+
+    #[test]
+    fn contact_overrides_replace_the_global_settings() {
+        let engine = NotificationPolicyEngine::new();
+        let mut settings = AppSettings::default();
+        settings.contact_overrides.insert(
+            "muted".to_string(),
+            ContactNotificationOverride {
+                enabled: Some(false),
+                ..ContactNotificationOverride::default()
+            },
+        );
+        settings.contact_overrides.insert(
+            "quiet".to_string(),
+            ContactNotificationOverride {
+                sound_volume: Some(25),
+                desktop_alerts: Some(false),
+                ..ContactNotificationOverride::default()
+            },
+        );
+        let now = Instant::now();
+        let message = |sender| event(NotificationEventKind::Message, Some(sender));
+
+        assert_eq!(
+            engine.decide(&message("muted"), BOTH, &ctx(&settings, now)),
+            NotificationDecision::default()
+        );
+
+        let decision = engine.decide(&message("quiet"), BOTH, &ctx(&settings, now));
+        assert!(!decision.desktop_alert);
+        assert_eq!(decision.sound, sound("new_mesage.mp3", 0.25));
+
+        // Others keep the global settings
+        let decision = engine.decide(&message("alice"), BOTH, &ctx(&settings, now));
+        assert!(decision.desktop_alert);
+        assert_eq!(decision.sound, sound("new_mesage.mp3", 0.8));
+    }
+
+    #[test]
+    fn a_contact_override_can_switch_notifications_back_on() {
+        let engine = NotificationPolicyEngine::new();
+        let mut settings = AppSettings::default();
+        settings.notifications.enabled = false;
+        settings.contact_overrides.insert(
+            "alice".to_string(),
+            ContactNotificationOverride {
+                enabled: Some(true),
+                ..ContactNotificationOverride::default()
+            },
+        );
+
+        let decision = engine.decide(
+            &event(NotificationEventKind::Message, Some("alice")),
+            BOTH,
+            &ctx(&settings, Instant::now()),
+        );
+        assert!(decision.desktop_alert && decision.sound.is_some());
+    }
+
+    #[test]
+    fn custom_sounds_replace_only_the_message_sound() {
+        let engine = NotificationPolicyEngine::new();
+        let mut settings = AppSettings::default();
+        settings.contact_overrides.insert(
+            "alice".to_string(),
+            ContactNotificationOverride {
+                sound_type: Some("nudge".to_string()),
+                ..ContactNotificationOverride::default()
+            },
+        );
+        let now = Instant::now();
+
+        let message = engine.decide(
+            &event(NotificationEventKind::Message, Some("alice")),
+            BOTH,
+            &ctx(&settings, now),
+        );
+        assert_eq!(message.sound, sound("nudge.mp3", 0.8));
+
+        let online = engine.decide(
+            &event(NotificationEventKind::ContactOnline, Some("alice")),
+            BOTH,
+            &ctx(&settings, now),
+        );
+        assert_eq!(online.sound, sound("contact_online.mp3", 0.8));
+    }
+
+    #[test]
+    fn a_focused_group_chat_counts_for_its_conversation() {
+        let mut message = event(NotificationEventKind::Message, Some("u1"));
+        assert_eq!(event_chat_labels(&message), vec!["chat-u1", "chat-bot-u1"]);
+
+        message.conversation_id = Some("c1".to_string());
+        assert_eq!(
+            event_chat_labels(&message),
+            vec!["chat-group-c1", "chat-u1", "chat-bot-u1"]
+        );
+        assert!(event_chat_labels(&event(NotificationEventKind::Message, None)).is_empty());
+    }
+}
@@ -0,0 +1,316 @@
+use crate::chat_attention;
+use crate::chat_windows;
+use crate::notification_policy::{
+    self, NotificationEvent, NotificationEventKind, RequestedOutputs,
+};
+use crate::settings::SettingsManager;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, PhysicalPosition, WebviewWindow};
+
+/// Minimum time between two nudges in the same direction with the same contact
+const NUDGE_COOLDOWN: Duration = Duration::from_secs(10);
+
+/// Offsets, in physical pixels, the window goes through while shaking
+const SHAKE_OFFSETS: [(i32, i32); 12] = [
+    (-10, 0),
+    (10, -4),
+    (-8, 4),
+    (8, -6),
+    (-10, 2),
+    (6, 6),
+    (-6, -6),
+    (10, 4),
+    (-4, -2),
+    (4, 2),
+    (-2, 0),
+    (2, 0),
+];
+
+/// Time each shake offset stays up
+const SHAKE_STEP: Duration = Duration::from_millis(40);
+
+/// Whether a nudge was sent or received
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum NudgeDirection {
+    Sent,
+    Received,
+}
+
+/// Limits how often nudges can be sent to, and received from, each contact
+/// Contacts are identified by their user ID, so a contact is limited the same way in every chat.
+pub struct NudgeLimiter {
+    last: Mutex<HashMap<(NudgeDirection, String), Instant>>,
+}
+
+impl NudgeLimiter {
+    /// Create a new NudgeLimiter with no recent nudges
+    pub fn new() -> Self {
+        Self {
+            last: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record a nudge if the contact's cooldown is over, otherwise return the time left
+    fn try_nudge(&self, direction: NudgeDirection, contact_id: &str) -> Result<(), Duration> {
+        self.try_nudge_all(direction, &[contact_id])
+    }
+
+    /// Record a nudge with every contact if all of their cooldowns are over, otherwise record
+    /// nothing and return the longest time left
+    fn try_nudge_all<S: AsRef<str>>(
+        &self,
+        direction: NudgeDirection,
+        contact_ids: &[S],
+    ) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut last = self.last.lock().unwrap();
+        last.retain(|_, at| now.duration_since(*at) < NUDGE_COOLDOWN);
+
+        let remaining = contact_ids
+            .iter()
+            .filter_map(|contact_id| last.get(&(direction, contact_id.as_ref().to_string())))
+            .map(|at| NUDGE_COOLDOWN - now.duration_since(*at))
+            .max();
+        if let Some(remaining) = remaining {
+            return Err(remaining);
+        }
+        for contact_id in contact_ids {
+            last.insert((direction, contact_id.as_ref().to_string()), now);
+        }
+        Ok(())
+    }
+
+    /// End the contacts' cooldowns early
+    fn release<S: AsRef<str>>(&self, direction: NudgeDirection, contact_ids: &[S]) {
+        let mut last = self.last.lock().unwrap();
+        for contact_id in contact_ids {
+            last.remove(&(direction, contact_id.as_ref().to_string()));
+        }
+    }
+}
+
+/// Chat windows shaking right now
+/// A shake that started mid-animation would take the displaced position as its origin and
+/// leave the window moved, so overlapping shakes of one window are ignored.
+pub struct ShakingWindows {
+    labels: Mutex<HashSet<String>>,
+}
+
+impl ShakingWindows {
+    /// Create a new ShakingWindows with no shaking windows
+    pub fn new() -> Self {
+        Self {
+            labels: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Mark `label` as shaking until the returned guard is dropped
+    /// Returns `None` when the window is already shaking.
+    fn start(&self, label: &str) -> Option<ShakeGuard<'_>> {
+        if !self.labels.lock().unwrap().insert(label.to_string()) {
+            return None;
+        }
+        Some(ShakeGuard {
+            windows: self,
+            label: label.to_string(),
+        })
+    }
+}
+
+/// Clears a window's shaking mark however the shake ends
+struct ShakeGuard<'a> {
+    windows: &'a ShakingWindows,
+    label: String,
+}
+
+impl Drop for ShakeGuard<'_> {
+    fn drop(&mut self) {
+        self.windows.labels.lock().unwrap().remove(&self.label);
+    }
+}
+
+/// Reserve the cooldown of every contact in `contact_ids` for a nudge about to be sent to them
+/// Fails with a message for the user when any of them was nudged too recently. Reserving before
+/// the send means two quick sends cannot both get through.
+#[tauri::command]
+pub fn reserve_sent_nudge(
+    limiter: tauri::State<NudgeLimiter>,
+    contact_ids: Vec<String>,
+) -> Result<(), String> {
+    if contact_ids.is_empty() {
+        return Err("There is no one to nudge in this chat".to_string());
+    }
+
+    limiter
+        .try_nudge_all(NudgeDirection::Sent, &contact_ids)
+        .map_err(|remaining| {
+            format!(
+                "You cannot send nudges too often. Please wait {} seconds.",
+                remaining.as_secs().max(1)
+            )
+        })
+}
+
+/// Give back the cooldowns reserved for a nudge that could not be sent
+#[tauri::command]
+pub fn release_sent_nudge(limiter: tauri::State<NudgeLimiter>, contact_ids: Vec<String>) {
+    limiter.release(NudgeDirection::Sent, &contact_ids);
+}
+
+/// Handle a nudge received in `conversation_id` from `sender_id`: play its sound through the
+/// notification policy and shake the chat window, unless the sender nudged too recently
+/// Returns whether the nudge counted.
+#[tauri::command]
+pub async fn receive_nudge(
+    app: AppHandle,
+    limiter: tauri::State<'_, NudgeLimiter>,
+    conversation_id: String,
+    sender_id: String,
+) -> Result<bool, String> {
+    if limiter
+        .try_nudge(NudgeDirection::Received, &sender_id)
+        .is_err()
+    {
+        return Ok(false);
+    }
+
+    let event = NotificationEvent {
+        kind: NotificationEventKind::Nudge,
+        sender_id: Some(sender_id.clone()),
+        conversation_id: Some(conversation_id.clone()),
+        title: None,
+        body: None,
+    };
+    let requested = RequestedOutputs {
+        alert: false,
+        sound: true,
+    };
+    notification_policy::dispatch(&app, &event, requested)?;
+
+    let window = chat_attention::chat_labels(&sender_id, Some(conversation_id))
+        .iter()
+        .find_map(|label| app.get_webview_window(label));
+    if let Some(window) = window {
+        shake(&app, &window).await?;
+    }
+    Ok(true)
+}
+
+/// Shake the chat window `label`, then put it back where it was
+/// Goes through the same settings check and shake guard as received nudges.
+#[tauri::command]
+pub async fn shake_window(app: AppHandle, label: String) -> Result<bool, String> {
+    if !chat_windows::is_chat_window(&label) {
+        return Err(format!("Not a chat window: {}", label));
+    }
+    let window = app
+        .get_webview_window(&label)
+        .ok_or_else(|| format!("Chat window not found: {}", label))?;
+
+    shake(&app, &window).await
+}
+
+/// Shake a chat window for a received nudge, then put it back where it was
+/// Returns whether the window shook; it does not when shaking is turned off in the settings,
+/// or when the window is already shaking.
+async fn shake(app: &AppHandle, window: &WebviewWindow) -> Result<bool, String> {
+    if !app
+        .state::<SettingsManager>()
+        .get_settings()
+        .notifications
+        .shake_on_nudge
+    {
+        return Ok(false);
+    }
+
+    let shaking = app.state::<ShakingWindows>();
+    let Some(_guard) = shaking.start(window.label()) else {
+        return Ok(false);
+    };
+
+    let origin = window
+        .outer_position()
+        .map_err(|e| format!("Failed to get window position: {}", e))?;
+
+    for (x, y) in SHAKE_OFFSETS {
+        if window
+            .set_position(PhysicalPosition::new(origin.x + x, origin.y + y))
+            .is_err()
+        {
+            // Most likely closed mid-shake; put it back in case it is still open
+            let _ = window.set_position(origin);
+            return Ok(true);
+        }
+        tokio::time::sleep(SHAKE_STEP).await;
+    }
+
+    window
+        .set_position(origin)
+        .map_err(|e| format!("Failed to restore window position: {}", e))?;
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_reserved_nudge_blocks_the_next_one() {
+        let limiter = NudgeLimiter::new();
+        assert_eq!(limiter.try_nudge(NudgeDirection::Sent, "c1"), Ok(()));
+
+        let remaining = limiter.try_nudge(NudgeDirection::Sent, "c1").unwrap_err();
+        assert!(remaining <= NUDGE_COOLDOWN && remaining > Duration::ZERO);
+    }
+
+    #[test]
+    fn releasing_a_nudge_frees_the_contact() {
+        let limiter = NudgeLimiter::new();
+        limiter.try_nudge(NudgeDirection::Sent, "c1").unwrap();
+        limiter.release(NudgeDirection::Sent, &["c1"]);
+
+        assert_eq!(limiter.try_nudge(NudgeDirection::Sent, "c1"), Ok(()));
+    }
+
+    #[test]
+    fn a_window_shakes_once_at_a_time() {
+        let shaking = ShakingWindows::new();
+        let guard = shaking.start("chat-1").unwrap();
+        assert!(shaking.start("chat-1").is_none());
+        assert!(shaking.start("chat-2").is_some());
+
+        drop(guard);
+        assert!(shaking.start("chat-1").is_some());
+    }
+
+    #[test]
+    fn contacts_and_directions_are_limited_separately() {
+        let limiter = NudgeLimiter::new();
+        limiter.try_nudge(NudgeDirection::Sent, "c1").unwrap();
+
+        assert_eq!(limiter.try_nudge(NudgeDirection::Sent, "c2"), Ok(()));
+        assert_eq!(limiter.try_nudge(NudgeDirection::Received, "c1"), Ok(()));
+        assert!(limiter.try_nudge(NudgeDirection::Received, "c1").is_err());
+    }
+
+    #[test]
+    fn a_nudge_to_several_contacts_is_all_or_nothing() {
+        let limiter = NudgeLimiter::new();
+        limiter.try_nudge(NudgeDirection::Sent, "c2").unwrap();
+
+        assert!(limiter
+            .try_nudge_all(NudgeDirection::Sent, &["c1", "c2", "c3"])
+            .is_err());
+        assert_eq!(limiter.try_nudge(NudgeDirection::Sent, "c1"), Ok(()));
+        assert_eq!(limiter.try_nudge(NudgeDirection::Sent, "c3"), Ok(()));
+
+        limiter.release(NudgeDirection::Sent, &["c1", "c2", "c3"]);
+        assert_eq!(
+            limiter.try_nudge_all(NudgeDirection::Sent, &["c1", "c2", "c3"]),
+            Ok(())
+        );
+        assert!(limiter.try_nudge(NudgeDirection::Sent, "c2").is_err());
+    }
+}
@@ -0,0 +1,516 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Filesystem operations used by `JsonFile`.
+/// Abstracted so that write failures (full disk, crash mid-write) can be injected.
+pub trait Storage: Send + Sync {
+    /// Create `path`, write `contents` and fsync before returning
+    fn write_synced(&self, path: &Path, contents: &[u8], mode: Option<u32>) -> io::Result<()>;
+
+    /// Atomically replace `to` with `from`
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+
+    /// Read a file into a string
+    fn read_to_string(&self, path: &Path) -> io::Result<String>;
+
+    /// Remove a file
+    fn remove(&self, path: &Path) -> io::Result<()>;
+
+    /// Create a directory and all of its parents
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+
+    /// Flush a directory entry so a completed rename survives a crash
+    fn sync_dir(&self, path: &Path) -> io::Result<()>;
+}
+
+/// `Storage` backed by the real filesystem
+pub struct OsStorage;
+
+impl Storage for OsStorage {
+    fn write_synced(&self, path: &Path, contents: &[u8], mode: Option<u32>) -> io::Result<()> {
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+
+        #[cfg(unix)]
+        if let Some(mode) = mode {
+            use std::os::unix::fs::OpenOptionsExt;
+            options.mode(mode);
+        }
+
+        let mut file = options.open(path)?;
+
+        // A leftover temp file keeps its old permissions, so apply the mode explicitly too
+        #[cfg(unix)]
+        if let Some(mode) = mode {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(fs::Permissions::from_mode(mode))?;
+        }
+        #[cfg(not(unix))]
+        let _ = mode;
+
+        file.write_all(contents)?;
+        file.sync_all()
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn read_to_string(&self, path: &Path) -> io::Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+
+    fn sync_dir(&self, path: &Path) -> io::Result<()> {
+        // Directories can only be opened (and fsynced) this way on Unix
+        #[cfg(unix)]
+        File::open(path)?.sync_all()?;
+        #[cfg(not(unix))]
+        let _ = path;
+        Ok(())
+    }
+}
+
+/// A JSON file written crash-safely.
+///
+/// Saves go to `<file>.tmp`, are fsynced and then renamed over the live file, so readers
+/// never observe a half-written document. The previous good version is rotated into
+/// `<file>.bak`, and loading falls back to it when the live file is missing or corrupt.
+pub struct JsonFile {
+    path: PathBuf,
+    mode: Option<u32>,
+    storage: Box<dyn Storage>,
+    /// Set when the live file failed to load, so it is not rotated over the backup
+    live_rejected: AtomicBool,
+}
+
+impl JsonFile {
+    /// Create a JsonFile at the given path using the real filesystem
+    pub fn new(path: PathBuf) -> Self {
+        Self::with_storage(path, Box::new(OsStorage))
+    }
+
+    /// Create a JsonFile at the given path using a custom storage backend
+    pub fn with_storage(path: PathBuf, storage: Box<dyn Storage>) -> Self {
+        Self {
+            path,
+            mode: None,
+            storage,
+            live_rejected: AtomicBool::new(false),
+        }
+    }
+
+    /// Restrict the Unix permissions of written files (e.g. `0o600` for secrets)
+    pub fn with_mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Path of the live file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Path of the rotating backup
+    pub fn backup_path(&self) -> PathBuf {
+        sibling_path(&self.path, "bak")
+    }
+
+    fn temp_path(&self) -> PathBuf {
+        sibling_path(&self.path, "tmp")
+    }
+
+    /// Load and parse the file, falling back to the backup if the live file is unusable.
+    /// Returns `Ok(None)` when neither file exists.
+    pub fn load<T: DeserializeOwned>(&self) -> Result<Option<T>, String> {
+        self.load_with(|value| serde_json::from_value(value).map_err(|e| e.to_string()))
+    }
+
+    /// Like `load`, but the parsed JSON is turned into a `T` by `convert` (e.g. after migrating
+    /// it). A file that `convert` rejects is as unusable as a corrupt one.
+    pub fn load_with<T>(
+        &self,
+        convert: impl Fn(Value) -> Result<T, String>,
+    ) -> Result<Option<T>, String> {
+        let primary_error = match self.read_json(&self.path, &convert) {
+            Ok(Some(value)) => return Ok(Some(value)),
+            Ok(None) => None,
+            Err(e) => {
+                self.live_rejected.store(true, Ordering::SeqCst);
+                Some(e)
+            }
+        };
+
+        let backup_path = self.backup_path();
+        match self.read_json(&backup_path, &convert) {
+            Ok(Some(value)) => {
+                eprintln!(
+                    "Recovered {:?} from backup {:?} ({})",
+                    self.path,
+                    backup_path,
+                    primary_error.as_deref().unwrap_or("file missing")
+                );
+                Ok(Some(value))
+            }
+            Ok(None) => primary_error.map_or(Ok(None), Err),
+            Err(backup_error) => Err(primary_error.unwrap_or(backup_error)),
+        }
+    }
+
+    /// Serialize `value` and atomically replace the file with it
+    pub fn save<T: Serialize>(&self, value: &T) -> Result<(), String> {
+        let json = serde_json::to_string_pretty(value)
+            .map_err(|e| format!("Failed to serialize {:?}: {}", self.path, e))?;
+
+        // Ensure parent directory exists
+        if let Some(parent) = self.path.parent() {
+            self.storage
+                .create_dir_all(parent)
+                .map_err(|e| format!("Failed to create storage directory: {}", e))?;
+        }
+
+        let temp_path = self.temp_path();
+        if let Err(e) = self
+            .storage
+            .write_synced(&temp_path, json.as_bytes(), self.mode)
+        {
+            let _ = self.storage.remove(&temp_path);
+            return Err(format!("Failed to write {:?}: {}", temp_path, e));
+        }
+
+        if !self.live_rejected.load(Ordering::SeqCst) {
+            self.rotate_backup();
+        }
+
+        if let Err(e) = self.storage.rename(&temp_path, &self.path) {
+            let _ = self.storage.remove(&temp_path);
+            return Err(format!("Failed to replace {:?}: {}", self.path, e));
+        }
+        self.live_rejected.store(false, Ordering::SeqCst);
+
+        if let Some(parent) = self.path.parent() {
+            if let Err(e) = self.storage.sync_dir(parent) {
+                eprintln!("Failed to sync directory {:?}: {}", parent, e);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replace the backup with the live file, the same crash-safe way the live file is written
+    /// Only a readable live file is rotated, otherwise a corrupt file would overwrite the last
+    /// good backup. `save` also skips this while the live file is one that failed to load.
+    fn rotate_backup(&self) {
+        let Ok(contents) = self.storage.read_to_string(&self.path) else {
+            return;
+        };
+        if serde_json::from_str::<serde_json::Value>(&contents).is_err() {
+            return;
+        }
+
+        let backup_path = self.backup_path();
+        let temp_path = sibling_path(&backup_path, "tmp");
+        let result = self
+            .storage
+            .write_synced(&temp_path, contents.as_bytes(), self.mode)
+            .and_then(|()| self.storage.rename(&temp_path, &backup_path));
+        if let Err(e) = result {
+            let _ = self.storage.remove(&temp_path);
+            eprintln!("Failed to rotate backup for {:?}: {}", self.path, e);
+        }
+    }
+
+    /// Read, parse and convert one file, treating "not found" as `None`
+    fn read_json<T>(
+        &self,
+        path: &Path,
+        convert: &impl Fn(Value) -> Result<T, String>,
+    ) -> Result<Option<T>, String> {
+        let contents = match self.storage.read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read {:?}: {}", path, e)),
+        };
+
+        serde_json::from_str(&contents)
+            .map_err(|e| e.to_string())
+            .and_then(convert)
+            .map(Some)
+            .map_err(|e| format!("Failed to parse {:?}: {}", path, e))
+    }
+}
+
+/// `settings.json` -> `settings.json.<suffix>`
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    /// In-memory `Storage` that fails writes or renames to chosen paths
+    #[derive(Clone, Default)]
+    struct FakeStorage {
+        files: Arc<Mutex<HashMap<PathBuf, Vec<u8>>>>,
+        fail_writes_to: Arc<Mutex<Option<PathBuf>>>,
+        fail_renames_to: Arc<Mutex<Option<PathBuf>>>,
+    }
+
+    impl FakeStorage {
+        fn put(&self, path: &Path, contents: &str) {
+            self.files
+                .lock()
+                .unwrap()
+                .insert(path.to_path_buf(), contents.as_bytes().to_vec());
+        }
+
+        fn get(&self, path: &Path) -> Option<String> {
+            self.files
+                .lock()
+                .unwrap()
+                .get(path)
+                .map(|contents| String::from_utf8(contents.clone()).unwrap())
+        }
+
+        fn get_json(&self, path: &Path) -> Option<Value> {
+            self.get(path)
+                .map(|contents| serde_json::from_str(&contents).unwrap())
+        }
+
+        fn paths(&self) -> Vec<PathBuf> {
+            let mut paths: Vec<_> = self.files.lock().unwrap().keys().cloned().collect();
+            paths.sort();
+            paths
+        }
+
+        fn fail_writes_to(&self, path: Option<PathBuf>) {
+            *self.fail_writes_to.lock().unwrap() = path;
+        }
+
+        fn fail_renames_to(&self, path: Option<PathBuf>) {
+            *self.fail_renames_to.lock().unwrap() = path;
+        }
+    }
+
+    fn injected() -> io::Error {
+        io::Error::other("injected failure")
+    }
+
+    impl Storage for FakeStorage {
+        fn write_synced(&self, path: &Path, contents: &[u8], _mode: Option<u32>) -> io::Result<()> {
+            if self.fail_writes_to.lock().unwrap().as_deref() == Some(path) {
+                // Fail halfway through, like a full disk
+                self.files
+                    .lock()
+                    .unwrap()
+                    .insert(path.to_path_buf(), contents[..contents.len() / 2].to_vec());
+                return Err(injected());
+            }
+            self.files
+                .lock()
+                .unwrap()
+                .insert(path.to_path_buf(), contents.to_vec());
+            Ok(())
+        }
+
+        fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+            if self.fail_renames_to.lock().unwrap().as_deref() == Some(to) {
+                return Err(injected());
+            }
+            let mut files = self.files.lock().unwrap();
+            let contents = files
+                .remove(from)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))?;
+            files.insert(to.to_path_buf(), contents);
+            Ok(())
+        }
+
+        fn read_to_string(&self, path: &Path) -> io::Result<String> {
+            self.get(path)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        }
+
+        fn remove(&self, path: &Path) -> io::Result<()> {
+            self.files
+                .lock()
+                .unwrap()
+                .remove(path)
+                .map(|_| ())
+                .ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+        }
+
+        fn create_dir_all(&self, _path: &Path) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn sync_dir(&self, _path: &Path) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn json_file() -> (JsonFile, FakeStorage) {
+        let storage = FakeStorage::default();
+        let file = JsonFile::with_storage(
+            PathBuf::from("/data/settings.json"),
+            Box::new(storage.clone()),
+        );
+        (file, storage)
+    }
+
+    #[test]
+    fn saves_and_rotates_the_previous_version_into_the_backup() {
+        let (file, storage) = json_file();
+        file.save(&json!({ "version": 1 })).unwrap();
+        file.save(&json!({ "version": 2 })).unwrap();
+
+        assert_eq!(storage.get_json(file.path()), Some(json!({ "version": 2 })));
+        assert_eq!(
+            storage.get_json(&file.backup_path()),
+            Some(json!({ "version": 1 }))
+        );
+        assert_eq!(
+            storage.paths(),
+            vec![file.path().to_path_buf(), file.backup_path()]
+        );
+        assert_eq!(file.load::<Value>(), Ok(Some(json!({ "version": 2 }))));
+    }
+
+    #[test]
+    fn failed_write_leaves_the_live_file_untouched() {
+        let (file, storage) = json_file();
+        file.save(&json!({ "version": 1 })).unwrap();
+
+        storage.fail_writes_to(Some(file.temp_path()));
+        assert!(file.save(&json!({ "version": 2 })).is_err());
+
+        assert_eq!(storage.get_json(file.path()), Some(json!({ "version": 1 })));
+        assert_eq!(storage.get(&file.temp_path()), None);
+        assert_eq!(file.load::<Value>(), Ok(Some(json!({ "version": 1 }))));
+    }
+
+    #[test]
+    fn failed_rename_leaves_the_live_file_untouched() {
+        let (file, storage) = json_file();
+        file.save(&json!({ "version": 1 })).unwrap();
+
+        storage.fail_renames_to(Some(file.path().to_path_buf()));
+        assert!(file.save(&json!({ "version": 2 })).is_err());
+
+        assert_eq!(storage.get_json(file.path()), Some(json!({ "version": 1 })));
+        assert_eq!(storage.get(&file.temp_path()), None);
+        assert_eq!(file.load::<Value>(), Ok(Some(json!({ "version": 1 }))));
+    }
+
+    #[test]
+    fn failed_backup_rotation_keeps_the_old_backup_and_still_saves() {
+        let (file, storage) = json_file();
+        file.save(&json!({ "version": 1 })).unwrap();
+        file.save(&json!({ "version": 2 })).unwrap();
+
+        let backup_temp = sibling_path(&file.backup_path(), "tmp");
+        storage.fail_writes_to(Some(backup_temp.clone()));
+        file.save(&json!({ "version": 3 })).unwrap();
+        assert_eq!(
+            storage.get_json(&file.backup_path()),
+            Some(json!({ "version": 1 }))
+        );
+        assert_eq!(storage.get(&backup_temp), None);
+
+        storage.fail_writes_to(None);
+        storage.fail_renames_to(Some(file.backup_path()));
+        file.save(&json!({ "version": 4 })).unwrap();
+        assert_eq!(
+            storage.get_json(&file.backup_path()),
+            Some(json!({ "version": 1 }))
+        );
+        assert_eq!(storage.get_json(file.path()), Some(json!({ "version": 4 })));
+    }
+
+    #[test]
+    fn corrupt_live_file_falls_back_to_the_backup() {
+        let (file, storage) = json_file();
+        storage.put(file.path(), "{ \"version\": 2");
+        storage.put(&file.backup_path(), "{ \"version\": 1 }");
+
+        assert_eq!(file.load::<Value>(), Ok(Some(json!({ "version": 1 }))));
+    }
+
+    #[test]
+    fn missing_live_file_falls_back_to_the_backup() {
+        let (file, storage) = json_file();
+        storage.put(&file.backup_path(), "{ \"version\": 1 }");
+
+        assert_eq!(file.load::<Value>(), Ok(Some(json!({ "version": 1 }))));
+    }
+
+    #[test]
+    fn corrupt_live_file_does_not_overwrite_a_good_backup() {
+        let (file, storage) = json_file();
+        storage.put(file.path(), "not json");
+        storage.put(&file.backup_path(), "{ \"version\": 1 }");
+
+        file.save(&json!({ "version": 3 })).unwrap();
+
+        assert_eq!(
+            storage.get_json(&file.backup_path()),
+            Some(json!({ "version": 1 }))
+        );
+        assert_eq!(storage.get_json(file.path()), Some(json!({ "version": 3 })));
+    }
+
+    #[test]
+    fn a_live_file_the_caller_rejects_falls_back_to_the_backup() {
+        let (file, storage) = json_file();
+        storage.put(file.path(), "{ \"version\": \"two\" }");
+        storage.put(&file.backup_path(), "{ \"version\": 1 }");
+
+        let version = |value: Value| {
+            value["version"]
+                .as_u64()
+                .ok_or_else(|| "version is not a number".to_string())
+        };
+        assert_eq!(file.load_with(version), Ok(Some(1)));
+
+        // The rejected file must not replace the backup it was recovered from
+        file.save(&json!({ "version": 3 })).unwrap();
+        assert_eq!(
+            storage.get_json(&file.backup_path()),
+            Some(json!({ "version": 1 }))
+        );
+
+        // Once replaced, saves rotate again
+        file.save(&json!({ "version": 4 })).unwrap();
+        assert_eq!(
+            storage.get_json(&file.backup_path()),
+            Some(json!({ "version": 3 }))
+        );
+    }
+
+    #[test]
+    fn reports_corruption_without_a_backup() {
+        let (file, storage) = json_file();
+        assert_eq!(file.load::<Value>(), Ok(None));
+
+        storage.put(file.path(), "not json");
+        assert!(file.load::<Value>().is_err());
+    }
+}
@@ -0,0 +1,427 @@
+use crate::cli;
+use crate::single_instance;
+use crate::AppState;
+use serde::Serialize;
+use std::fs::{self, TryLockError};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tauri::{AppHandle, Manager, WebviewWindowBuilder};
+
+/// Label of the profile picker window shown on startup
+pub const PICKER_WINDOW_LABEL: &str = "profile-picker";
+
+/// A profile as shown to the user
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileInfo {
+    pub name: Option<String>, // None for the default profile
+    pub is_active: bool,
+}
+
+/// Directory holding all named profiles
+pub fn profiles_dir(data_root: &Path) -> PathBuf {
+    data_root.join("profiles")
+}
+
+/// Data directory of a profile; the default profile lives directly in the data root
+pub fn profile_data_dir(data_root: &Path, profile: Option<&str>) -> PathBuf {
+    match profile {
+        Some(name) => profiles_dir(data_root).join(name),
+        None => data_root.to_path_buf(),
+    }
+}
+
+/// Names of all named profiles, sorted
+pub fn list_named_profiles(data_root: &Path) -> Result<Vec<String>, String> {
+    let dir = profiles_dir(data_root);
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let entries =
+        fs::read_dir(&dir).map_err(|e| format!("Failed to read profiles directory: {}", e))?;
+
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| cli::validate_profile_name(name).is_ok())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// Lock file serializing changes to the profile `name` with instances starting in it
+/// It lives outside the profile directory, which cannot be renamed or deleted on Windows while a
+/// file in it is open, and outside `profiles` so it cannot be mistaken for a profile.
+fn change_lock_path(data_root: &Path, name: &str) -> PathBuf {
+    data_root.join("profile-locks").join(name)
+}
+
+/// Take the change lock of the profile `name`
+/// With `wait` set, blocks until no one else holds it; otherwise returns `None` when someone does.
+/// Filesystems without locking are not guarded, like the instance lock.
+pub fn lock_changes(data_root: &Path, name: &str, wait: bool) -> Result<Option<fs::File>, String> {
+    let path = change_lock_path(data_root, name);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create profile lock directory: {}", e))?;
+    }
+    let file = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&path)
+        .map_err(|e| format!("Failed to open profile lock: {}", e))?;
+
+    let locked = if wait {
+        file.lock().map_err(TryLockError::Error)
+    } else {
+        file.try_lock()
+    };
+    match locked {
+        Ok(()) | Err(TryLockError::Error(_)) => Ok(Some(file)),
+        Err(TryLockError::WouldBlock) => Ok(None),
+    }
+}
+
+/// Make sure no instance is running in, or starting in, the profile `name` at `dir`
+/// No instance can start in the profile until the returned lock is dropped.
+fn lock_unused(data_root: &Path, dir: &Path, name: &str) -> Result<fs::File, String> {
+    let in_use = || {
+        format!(
+            "Profile '{}' is in use by another instance of the app",
+            name
+        )
+    };
+
+    let lock = lock_changes(data_root, name, false)?.ok_or_else(in_use)?;
+    if single_instance::is_locked(dir)? {
+        return Err(in_use());
+    }
+    Ok(lock)
+}
+
+/// Rename the profile `name` in `data_root` to `new_name`, unless it is `active` or running
+pub fn rename(
+    data_root: &Path,
+    active: Option<&str>,
+    name: &str,
+    new_name: &str,
+) -> Result<(), String> {
+    cli::validate_profile_name(name)?;
+    cli::validate_profile_name(new_name)?;
+
+    if active == Some(name) {
+        return Err("Cannot rename the profile that is currently in use".to_string());
+    }
+
+    let from = profile_data_dir(data_root, Some(name));
+    let to = profile_data_dir(data_root, Some(new_name));
+    if !from.is_dir() {
+        return Err(format!("Profile '{}' does not exist", name));
+    }
+    if to.exists() {
+        return Err(format!("Profile '{}' already exists", new_name));
+    }
+
+    let _lock = lock_unused(data_root, &from, name)?;
+    // Nothing may start in the new name while the directory moves there either
+    let _new_lock = lock_unused(data_root, &to, new_name)?;
+    fs::rename(&from, &to).map_err(|e| format!("Failed to rename profile: {}", e))
+}
+
+/// Delete the profile `name` in `data_root` and all of its data, unless it is `active` or running
+pub fn delete(data_root: &Path, active: Option<&str>, name: &str) -> Result<(), String> {
+    cli::validate_profile_name(name)?;
+
+    if active == Some(name) {
+        return Err("Cannot delete the profile that is currently in use".to_string());
+    }
+
+    let dir = profile_data_dir(data_root, Some(name));
+    if !dir.is_dir() {
+        return Err(format!("Profile '{}' does not exist", name));
+    }
+
+    let _lock = lock_unused(data_root, &dir, name)?;
+    fs::remove_dir_all(&dir).map_err(|e| format!("Failed to delete profile: {}", e))
+}
+
+/// Start a new instance of the app in the given profile and exit this one
+fn relaunch(app: &AppHandle, state: &AppState, profile: Option<&str>) -> Result<(), String> {
+    let exe = tauri::process::current_binary(&app.env())
+        .map_err(|e| format!("Failed to locate the application binary: {}", e))?;
+
+    let mut command = Command::new(exe);
+    // The new instance must not fall back to the profile this one was started with
+    command.env_remove("TAURI_PROFILE");
+    if let Some(data_dir) = &state.launch_args().data_dir {
+        command.arg("--data-dir").arg(data_dir);
+    }
+    if let Some(name) = profile {
+        command.arg("--profile").arg(name);
+    }
+
+    command
+        .spawn()
+        .map_err(|e| format!("Failed to relaunch into profile: {}", e))?;
+
+    app.exit(0);
+    Ok(())
+}
+
+/// Show the profile picker window
+pub fn open_profile_picker(app: &AppHandle) -> Result<(), String> {
+    if let Some(window) = app.get_webview_window(PICKER_WINDOW_LABEL) {
+        return window
+            .set_focus()
+            .map_err(|e| format!("Failed to focus profile picker: {}", e));
+    }
+
+    WebviewWindowBuilder::new(
+        app,
+        PICKER_WINDOW_LABEL,
+        tauri::WebviewUrl::App("profile-picker.html".into()),
+    )
+    .title("Choose a profile")
+    .decorations(false)
+    .transparent(true)
+    .resizable(false)
+    .inner_size(320.0, 360.0)
+    .center()
+    .build()
+    .map_err(|e| format!("Failed to open profile picker: {}", e))?;
+
+    Ok(())
+}
+
+/// List all profiles, starting with the default one
+#[tauri::command]
+pub fn list_profiles(state: tauri::State<AppState>) -> Result<Vec<ProfileInfo>, String> {
+    let active = state.get_profile();
+
+    let mut profiles = vec![ProfileInfo {
+        name: None,
+        is_active: active.is_none(),
+    }];
+    profiles.extend(
+        list_named_profiles(state.data_root())?
+            .into_iter()
+            .map(|name| ProfileInfo {
+                is_active: active.as_deref() == Some(name.as_str()),
+                name: Some(name),
+            }),
+    );
+
+    Ok(profiles)
+}
+
+/// Create a new, empty profile
+#[tauri::command]
+pub fn create_profile(state: tauri::State<AppState>, name: String) -> Result<ProfileInfo, String> {
+    cli::validate_profile_name(&name)?;
+
+    let dir = profile_data_dir(state.data_root(), Some(&name));
+    if dir.exists() {
+        return Err(format!("Profile '{}' already exists", name));
+    }
+
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create profile: {}", e))?;
+
+    Ok(ProfileInfo {
+        name: Some(name),
+        is_active: false,
+    })
+}
+
+/// Rename a profile that is not currently in use
+#[tauri::command]
+pub fn rename_profile(
+    state: tauri::State<AppState>,
+    name: String,
+    new_name: String,
+) -> Result<(), String> {
+    rename(
+        state.data_root(),
+        state.get_profile().as_deref(),
+        &name,
+        &new_name,
+    )
+}
+
+/// Delete a profile and all of its data
+#[tauri::command]
+pub fn delete_profile(state: tauri::State<AppState>, name: String) -> Result<(), String> {
+    delete(state.data_root(), state.get_profile().as_deref(), &name)
+}
+
+/// Switch to another profile by relaunching the app into it
+/// Choosing the active profile just continues to the main window.
+#[tauri::command]
+pub fn switch_profile(
+    app: AppHandle,
+    state: tauri::State<AppState>,
+    name: Option<String>,
+) -> Result<(), String> {
+    if let Some(name) = &name {
+        cli::validate_profile_name(name)?;
+        if !profile_data_dir(state.data_root(), Some(name)).is_dir() {
+            return Err(format!("Profile '{}' does not exist", name));
+        }
+    }
+
+    if name == state.get_profile() {
+        if let Some(picker) = app.get_webview_window(PICKER_WINDOW_LABEL) {
+            let _ = picker.close();
+        }
+        if let Some(window) = app.get_webview_window("main") {
+            let _ = window.show();
+            let _ = window.set_focus();
+        }
+        return Ok(());
+    }
+
+    relaunch(&app, &state, name.as_deref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::single_instance::InstanceStatus;
+
+    fn data_root(profiles: &[&str]) -> tempfile::TempDir {
+        let root = tempfile::tempdir().unwrap();
+        for name in profiles {
+            fs::create_dir_all(profile_data_dir(root.path(), Some(name))).unwrap();
+        }
+        root
+    }
+
+    #[test]
+    fn lists_named_profile_directories_sorted() {
+        let root = data_root(&["work", "Home", "alt"]);
+        fs::write(profiles_dir(root.path()).join("notes.txt"), "").unwrap();
+        fs::create_dir(profiles_dir(root.path()).join("-bad")).unwrap();
+        fs::create_dir(profiles_dir(root.path()).join("CON")).unwrap();
+
+        assert_eq!(
+            list_named_profiles(root.path()).unwrap(),
+            vec!["Home", "alt", "work"]
+        );
+    }
+
+    #[test]
+    fn lists_no_profiles_without_a_profiles_directory() {
+        let root = tempfile::tempdir().unwrap();
+        assert_eq!(
+            list_named_profiles(root.path()).unwrap(),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn renames_a_profile_with_its_data() {
+        let root = data_root(&["work"]);
+        let settings = profile_data_dir(root.path(), Some("work")).join("settings.json");
+        fs::write(&settings, "{}").unwrap();
+
+        rename(root.path(), None, "work", "office").unwrap();
+        assert_eq!(list_named_profiles(root.path()).unwrap(), vec!["office"]);
+        assert!(profile_data_dir(root.path(), Some("office"))
+            .join("settings.json")
+            .is_file());
+    }
+
+    #[test]
+    fn refuses_invalid_renames() {
+        let root = data_root(&["work", "home"]);
+        assert!(rename(root.path(), None, "missing", "other").is_err());
+        assert!(rename(root.path(), None, "work", "home").is_err());
+        assert!(rename(root.path(), None, "work", "../escape").is_err());
+        assert!(rename(root.path(), None, "work", "").is_err());
+        assert!(rename(root.path(), None, "../work", "other").is_err());
+        assert!(rename(root.path(), Some("work"), "work", "other").is_err());
+
+        assert_eq!(
+            list_named_profiles(root.path()).unwrap(),
+            vec!["home", "work"]
+        );
+    }
+
+    #[test]
+    fn deletes_a_profile_with_its_data() {
+        let root = data_root(&["work", "home"]);
+        fs::write(
+            profile_data_dir(root.path(), Some("work")).join("settings.json"),
+            "{}",
+        )
+        .unwrap();
+
+        delete(root.path(), Some("home"), "work").unwrap();
+        assert_eq!(list_named_profiles(root.path()).unwrap(), vec!["home"]);
+    }
+
+    #[test]
+    fn refuses_invalid_deletes() {
+        let root = data_root(&["work"]);
+        assert!(delete(root.path(), None, "missing").is_err());
+        assert!(delete(root.path(), None, "..").is_err());
+        assert!(delete(root.path(), None, "").is_err());
+        assert!(delete(root.path(), Some("work"), "work").is_err());
+
+        assert_eq!(list_named_profiles(root.path()).unwrap(), vec!["work"]);
+    }
+
+    #[test]
+    fn refuses_to_change_a_running_profile() {
+        let root = data_root(&["work"]);
+        let dir = profile_data_dir(root.path(), Some("work"));
+        let Ok(InstanceStatus::Primary(instance)) = single_instance::acquire(&dir, Vec::new())
+        else {
+            panic!("Failed to start an instance in the profile");
+        };
+
+        assert!(rename(root.path(), None, "work", "office").is_err());
+        assert!(delete(root.path(), None, "work").is_err());
+        assert!(dir.is_dir());
+
+        drop(instance);
+        rename(root.path(), None, "work", "office").unwrap();
+    }
+
+    #[test]
+    fn refuses_to_change_a_profile_another_process_is_changing_or_starting() {
+        let root = data_root(&["work", "home"]);
+
+        let lock = lock_changes(root.path(), "work", false).unwrap().unwrap();
+        assert!(lock_changes(root.path(), "work", false).unwrap().is_none());
+        assert!(rename(root.path(), None, "work", "office").is_err());
+        assert!(delete(root.path(), None, "work").is_err());
+        // Nor may another profile move into a name that is starting
+        assert!(rename(root.path(), None, "home", "work2").is_ok());
+        let _starting = lock_changes(root.path(), "office", false).unwrap().unwrap();
+        assert!(rename(root.path(), None, "work2", "office").is_err());
+
+        drop(lock);
+        delete(root.path(), None, "work").unwrap();
+        assert_eq!(list_named_profiles(root.path()).unwrap(), vec!["work2"]);
+    }
+
+    #[test]
+    fn keeps_no_files_open_in_a_profile_while_changing_it() {
+        // Windows cannot rename or delete a directory with an open file in it
+        let root = data_root(&["work"]);
+        let dir = profile_data_dir(root.path(), Some("work"));
+
+        let lock = lock_unused(root.path(), &dir, "work").unwrap();
+        assert!(!change_lock_path(root.path(), "work").starts_with(&dir));
+        assert!(!change_lock_path(root.path(), "work").starts_with(profiles_dir(root.path())));
+        assert!(!dir.join("instance.lock").exists());
+        fs::rename(&dir, profile_data_dir(root.path(), Some("office"))).unwrap();
+        drop(lock);
+
+        assert_eq!(list_named_profiles(root.path()).unwrap(), vec!["office"]);
+    }
+}
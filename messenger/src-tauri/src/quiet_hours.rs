@@ -0,0 +1,397 @@
+use crate::settings::{QuietHours, QuietRange, SettingsManager};
+use chrono::{Datelike, Duration, Local, NaiveDateTime};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use tauri::{AppHandle, Manager};
+
+/// How often the digest task re-checks the schedule while nothing is queued,
+/// so settings changes are picked up without a restart
+const IDLE_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// Number of suppressed alerts listed individually in the digest body
+const DIGEST_PREVIEW_COUNT: usize = 3;
+
+/// Source of the current local time, injectable so the schedule can be tested
+pub trait Clock: Send + Sync {
+    fn now(&self) -> NaiveDateTime;
+}
+
+/// `Clock` reading the system's local time
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        Local::now().naive_local()
+    }
+}
+
+impl QuietRange {
+    /// End of this range's occurrence covering `at`, if any.
+    /// Occurrences starting today and yesterday are checked to cover ranges past midnight.
+    fn active_until(&self, at: NaiveDateTime) -> Option<NaiveDateTime> {
+        [0, 1].into_iter().find_map(|days_back| {
+            let day = at.date() - Duration::days(days_back);
+            if !self.days.contains(&day.weekday()) {
+                return None;
+            }
+
+            let start = day.and_time(self.start);
+            let end = if self.end > self.start {
+                day.and_time(self.end)
+            } else {
+                (day + Duration::days(1)).and_time(self.end)
+            };
+
+            (start <= at && at < end).then_some(end)
+        })
+    }
+}
+
+impl QuietHours {
+    /// When the quiet period covering `at` ends, or `None` if `at` is not quiet.
+    /// Back-to-back ranges are merged into a single period.
+    pub fn active_until(&self, at: NaiveDateTime) -> Option<NaiveDateTime> {
+        if !self.enabled {
+            return None;
+        }
+
+        let mut end = self
+            .ranges
+            .iter()
+            .filter_map(|r| r.active_until(at))
+            .max()?;
+
+        // Follow ranges that pick up exactly where the previous one stops (bounded to a week)
+        for _ in 0..7 {
+            match self.ranges.iter().filter_map(|r| r.active_until(end)).max() {
+                Some(next) if next > end => end = next,
+                _ => break,
+            }
+        }
+
+        Some(end)
+    }
+}
+
+/// A desktop notification held back during quiet hours
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuppressedAlert {
+    pub title: String,
+    pub body: String,
+    pub sender_id: Option<String>,
+}
+
+/// Summary notification for the alerts suppressed during a quiet period
+#[derive(Debug, Clone, PartialEq)]
+pub struct Digest {
+    pub title: String,
+    pub body: String,
+}
+
+impl Digest {
+    fn from_alerts(alerts: &[SuppressedAlert]) -> Self {
+        let title = if alerts.len() == 1 {
+            "1 notification during quiet hours".to_string()
+        } else {
+            format!("{} notifications during quiet hours", alerts.len())
+        };
+
+        let mut lines: Vec<String> = alerts
+            .iter()
+            .take(DIGEST_PREVIEW_COUNT)
+            .map(|alert| alert.title.clone())
+            .collect();
+        if alerts.len() > DIGEST_PREVIEW_COUNT {
+            lines.push(format!("and {} more", alerts.len() - DIGEST_PREVIEW_COUNT));
+        }
+
+        Self {
+            title,
+            body: lines.join("\n"),
+        }
+    }
+}
+
+/// Decides when alerts are suppressed and queues them for the end-of-quiet-hours digest
+pub struct QuietHoursScheduler {
+    clock: Arc<dyn Clock>,
+    suppressed: Mutex<Vec<SuppressedAlert>>,
+}
+
+impl QuietHoursScheduler {
+    /// Create a new QuietHoursScheduler reading time from the given clock
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            clock,
+            suppressed: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Whether an alert should be suppressed right now
+    pub fn should_suppress(&self, quiet_hours: &QuietHours, is_call: bool) -> bool {
+        if is_call && quiet_hours.allow_calls {
+            return false;
+        }
+        quiet_hours.active_until(self.clock.now()).is_some()
+    }
+
+    /// Queue a suppressed notification for the digest
+    pub fn queue(&self, alert: SuppressedAlert) {
+        self.suppressed.lock().unwrap().push(alert);
+    }
+
+    /// Drain the queued alerts into a digest once the quiet period is over
+    pub fn take_digest(&self, quiet_hours: &QuietHours) -> Option<Digest> {
+        if quiet_hours.active_until(self.clock.now()).is_some() {
+            return None;
+        }
+
+        let mut suppressed = self.suppressed.lock().unwrap();
+        if suppressed.is_empty() {
+            return None;
+        }
+
+        let digest = Digest::from_alerts(&suppressed);
+        suppressed.clear();
+        Some(digest)
+    }
+
+    /// How long the digest task should sleep before checking again
+    pub fn next_check_in(&self, quiet_hours: &QuietHours) -> std::time::Duration {
+        let now = self.clock.now();
+        quiet_hours
+            .active_until(now)
+            .and_then(|end| (end - now).to_std().ok())
+            .map_or(IDLE_POLL_INTERVAL, |until_end| {
+                until_end.min(IDLE_POLL_INTERVAL)
+            })
+    }
+}
+
+/// Deliver the digest whenever a quiet period ends
+/// Runs for the lifetime of the app on the Tauri async runtime
+pub fn spawn_digest_task(app: AppHandle) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            let delay = {
+                let scheduler = app.state::<QuietHoursScheduler>();
+                let settings_manager = app.state::<SettingsManager>();
+                scheduler.next_check_in(&settings_manager.get_settings().quiet_hours)
+            };
+            tokio::time::sleep(delay).await;
+
+            let digest = {
+                let scheduler = app.state::<QuietHoursScheduler>();
+                let settings_manager = app.state::<SettingsManager>();
+                scheduler.take_digest(&settings_manager.get_settings().quiet_hours)
+            };
+
+            if let Some(digest) = digest {
+                let policy = app.state::<SettingsManager>().notification_policy_for(None);
+                if !policy.shows_desktop_alert() {
+                    continue;
+                }
+
+                if let Err(e) =
+                    crate::send_desktop_notification(&app, digest.title, digest.body, false)
+                {
+                    eprintln!("Failed to deliver quiet hours digest: {}", e);
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, NaiveTime, Weekday};
+
+    /// `Clock` that stays where the test puts it
+    struct FixedClock(Mutex<NaiveDateTime>);
+
+    impl Clock for FixedClock {
+        fn now(&self) -> NaiveDateTime {
+            *self.0.lock().unwrap()
+        }
+    }
+
+    /// 2024-01-01 is a Monday, so `day` 0..7 runs Monday to Sunday
+    fn at(day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, 1 + day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    fn time(hour: u32, minute: u32) -> NaiveTime {
+        NaiveTime::from_hms_opt(hour, minute, 0).unwrap()
+    }
+
+    fn range(days: &[Weekday], start: NaiveTime, end: NaiveTime) -> QuietRange {
+        QuietRange {
+            days: days.to_vec(),
+            start,
+            end,
+        }
+    }
+
+    fn quiet_hours(ranges: Vec<QuietRange>) -> QuietHours {
+        QuietHours {
+            enabled: true,
+            allow_calls: true,
+            ranges,
+        }
+    }
+
+    fn scheduler(now: NaiveDateTime) -> (QuietHoursScheduler, Arc<FixedClock>) {
+        let clock = Arc::new(FixedClock(Mutex::new(now)));
+        (QuietHoursScheduler::new(clock.clone()), clock)
+    }
+
+    fn alert(title: &str) -> SuppressedAlert {
+        SuppressedAlert {
+            title: title.to_string(),
+            body: String::new(),
+            sender_id: None,
+        }
+    }
+
+    #[test]
+    fn same_day_range_covers_only_its_weekdays() {
+        let hours = quiet_hours(vec![range(
+            &[Weekday::Mon, Weekday::Wed],
+            time(9, 0),
+            time(17, 0),
+        )]);
+
+        assert_eq!(hours.active_until(at(0, 9, 0)), Some(at(0, 17, 0)));
+        assert_eq!(hours.active_until(at(0, 16, 59)), Some(at(0, 17, 0)));
+        assert_eq!(hours.active_until(at(0, 8, 59)), None);
+        assert_eq!(hours.active_until(at(0, 17, 0)), None);
+        assert_eq!(hours.active_until(at(1, 12, 0)), None);
+        assert_eq!(hours.active_until(at(2, 12, 0)), Some(at(2, 17, 0)));
+    }
+
+    #[test]
+    fn cross_midnight_range_belongs_to_the_day_it_starts() {
+        let hours = quiet_hours(vec![range(&[Weekday::Fri], time(22, 0), time(7, 0))]);
+
+        assert_eq!(hours.active_until(at(4, 21, 59)), None);
+        assert_eq!(hours.active_until(at(4, 22, 0)), Some(at(5, 7, 0)));
+        assert_eq!(hours.active_until(at(5, 3, 0)), Some(at(5, 7, 0)));
+        assert_eq!(hours.active_until(at(5, 7, 0)), None);
+        // Saturday night is not quiet, and neither is Friday morning
+        assert_eq!(hours.active_until(at(5, 23, 0)), None);
+        assert_eq!(hours.active_until(at(4, 3, 0)), None);
+    }
+
+    #[test]
+    fn range_ending_at_its_start_lasts_a_full_day() {
+        let hours = quiet_hours(vec![range(&[Weekday::Sun], time(0, 0), time(0, 0))]);
+
+        assert_eq!(hours.active_until(at(6, 0, 0)), Some(at(7, 0, 0)));
+        assert_eq!(hours.active_until(at(6, 23, 59)), Some(at(7, 0, 0)));
+        assert_eq!(hours.active_until(at(7, 0, 0)), None);
+    }
+
+    #[test]
+    fn back_to_back_ranges_merge() {
+        let hours = quiet_hours(vec![
+            range(&[Weekday::Mon], time(22, 0), time(0, 0)),
+            range(&[Weekday::Tue], time(0, 0), time(7, 0)),
+        ]);
+
+        assert_eq!(hours.active_until(at(0, 23, 0)), Some(at(1, 7, 0)));
+    }
+
+    #[test]
+    fn disabled_quiet_hours_are_never_active() {
+        let mut hours = quiet_hours(vec![range(&[Weekday::Mon], time(0, 0), time(0, 0))]);
+        hours.enabled = false;
+
+        assert_eq!(hours.active_until(at(0, 12, 0)), None);
+    }
+
+    #[test]
+    fn calls_get_through_only_when_allowed() {
+        let mut hours = quiet_hours(vec![range(&[Weekday::Mon], time(9, 0), time(17, 0))]);
+        let (scheduler, _) = scheduler(at(0, 12, 0));
+
+        assert!(scheduler.should_suppress(&hours, false));
+        assert!(!scheduler.should_suppress(&hours, true));
+
+        hours.allow_calls = false;
+        assert!(scheduler.should_suppress(&hours, true));
+    }
+
+    #[test]
+    fn digest_waits_for_the_end_of_the_quiet_period() {
+        let hours = quiet_hours(vec![range(&[Weekday::Mon], time(22, 0), time(7, 0))]);
+        let (scheduler, clock) = scheduler(at(0, 23, 0));
+
+        for title in [
+            "Alice says:",
+            "Bob says:",
+            "Carol says:",
+            "Dave says:",
+            "Eve says:",
+        ] {
+            scheduler.queue(alert(title));
+        }
+        assert_eq!(scheduler.take_digest(&hours), None);
+
+        *clock.0.lock().unwrap() = at(1, 6, 59);
+        assert_eq!(scheduler.take_digest(&hours), None);
+
+        *clock.0.lock().unwrap() = at(1, 7, 0);
+        assert_eq!(
+            scheduler.take_digest(&hours),
+            Some(Digest {
+                title: "5 notifications during quiet hours".to_string(),
+                body: "Alice says:\nBob says:\nCarol says:\nand 2 more".to_string(),
+            })
+        );
+        assert_eq!(scheduler.take_digest(&hours), None);
+    }
+
+    #[test]
+    fn digest_of_one_alert() {
+        let hours = quiet_hours(Vec::new());
+        let (scheduler, _) = scheduler(at(0, 12, 0));
+
+        assert_eq!(scheduler.take_digest(&hours), None);
+        scheduler.queue(alert("Alice says:"));
+        assert_eq!(
+            scheduler.take_digest(&hours),
+            Some(Digest {
+                title: "1 notification during quiet hours".to_string(),
+                body: "Alice says:".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn checks_again_when_the_quiet_period_ends() {
+        let hours = quiet_hours(vec![range(&[Weekday::Mon], time(9, 0), time(17, 0))]);
+        let (scheduler, clock) = scheduler(at(0, 16, 59));
+
+        assert_eq!(
+            scheduler.next_check_in(&hours),
+            std::time::Duration::from_secs(60)
+        );
+
+        *clock.0.lock().unwrap() = at(0, 16, 59) + Duration::seconds(30);
+        assert_eq!(
+            scheduler.next_check_in(&hours),
+            std::time::Duration::from_secs(30)
+        );
+
+        *clock.0.lock().unwrap() = at(0, 12, 0);
+        assert_eq!(scheduler.next_check_in(&hours), IDLE_POLL_INTERVAL);
+
+        *clock.0.lock().unwrap() = at(0, 18, 0);
+        assert_eq!(scheduler.next_check_in(&hours), IDLE_POLL_INTERVAL);
+    }
+}
@@ -0,0 +1,199 @@
+use crate::chat_target::ChatTarget;
+use crate::persistence::JsonFile;
+use crate::tray::TrayManager;
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Manager};
+
+/// Most contacts listed under "Recent Conversations" in the tray
+pub const MAX_RECENT_CHATS: usize = 8;
+
+/// A contact the user recently chatted with
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecentChat {
+    pub user_id: String,
+    pub display_name: String,
+}
+
+impl RecentChat {
+    /// Chat to open for this contact
+    pub fn target(&self) -> ChatTarget {
+        ChatTarget::Contact {
+            user_id: self.user_id.clone(),
+            display_name: Some(self.display_name.clone()),
+        }
+    }
+}
+
+/// Contacts with the most recent chat activity, most recent first, saved with the profile
+pub struct RecentChats {
+    chats: Mutex<Vec<RecentChat>>,
+    file: JsonFile,
+}
+
+impl RecentChats {
+    /// Create a new RecentChats, loading the saved list from `storage_path`
+    pub fn new(storage_path: PathBuf) -> Self {
+        let file = JsonFile::new(storage_path);
+        let mut chats: Vec<RecentChat> = file
+            .load()
+            .unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                None
+            })
+            .unwrap_or_default();
+        chats.truncate(MAX_RECENT_CHATS);
+
+        Self {
+            chats: Mutex::new(chats),
+            file,
+        }
+    }
+
+    /// Move a contact to the top of the list
+    /// Contacts are only added once their name is known, so the tray never shows a bare id;
+    /// without a name, only a contact already in the list is moved. Returns whether the list
+    /// changed.
+    pub fn record(&self, user_id: &str, display_name: Option<&str>) -> bool {
+        let display_name = display_name.map(str::trim).filter(|name| !name.is_empty());
+
+        let mut chats = self.chats.lock().unwrap();
+        let chat = match chats.iter().position(|chat| chat.user_id == user_id) {
+            Some(index) => {
+                let renamed = display_name.is_some_and(|name| name != chats[index].display_name);
+                if index == 0 && !renamed {
+                    return false;
+                }
+
+                let mut chat = chats.remove(index);
+                if let Some(name) = display_name {
+                    chat.display_name = name.to_string();
+                }
+                chat
+            }
+            None => match display_name {
+                Some(name) => RecentChat {
+                    user_id: user_id.to_string(),
+                    display_name: name.to_string(),
+                },
+                None => return false,
+            },
+        };
+
+        chats.insert(0, chat);
+        chats.truncate(MAX_RECENT_CHATS);
+        if let Err(e) = self.file.save(&*chats) {
+            eprintln!("Failed to save recent chats: {}", e);
+        }
+        true
+    }
+
+    /// Recent contacts, most recent first
+    pub fn list(&self) -> Vec<RecentChat> {
+        self.chats.lock().unwrap().clone()
+    }
+}
+
+/// Note chat activity with a contact and update the tray menu if the list changed
+pub fn record_activity(app: &AppHandle, user_id: &str, display_name: Option<&str>) {
+    if app
+        .state::<AppState>()
+        .recent_chats()
+        .record(user_id, display_name)
+    {
+        if let Some(tray) = app.try_state::<TrayManager>() {
+            tray.refresh(app);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recent_chats(dir: &tempfile::TempDir) -> RecentChats {
+        RecentChats::new(dir.path().join("recent_chats.json"))
+    }
+
+    fn user_ids(chats: &RecentChats) -> Vec<String> {
+        chats.list().into_iter().map(|chat| chat.user_id).collect()
+    }
+
+    #[test]
+    fn records_new_contacts_at_the_top() {
+        let dir = tempfile::tempdir().unwrap();
+        let chats = recent_chats(&dir);
+
+        assert!(chats.record("a", Some("Alice")));
+        assert!(chats.record("b", Some("Bob")));
+        assert_eq!(user_ids(&chats), vec!["b", "a"]);
+    }
+
+    #[test]
+    fn moves_existing_contacts_to_the_top_without_duplicating() {
+        let dir = tempfile::tempdir().unwrap();
+        let chats = recent_chats(&dir);
+        chats.record("a", Some("Alice"));
+        chats.record("b", Some("Bob"));
+        chats.record("c", Some("Carol"));
+
+        assert!(chats.record("a", None));
+        assert_eq!(user_ids(&chats), vec!["a", "c", "b"]);
+        assert!(!chats.record("a", Some("Alice")));
+        assert_eq!(user_ids(&chats), vec!["a", "c", "b"]);
+    }
+
+    #[test]
+    fn keeps_the_latest_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let chats = recent_chats(&dir);
+        chats.record("a", Some("Alice"));
+
+        assert!(chats.record("a", Some(" Alice Smith ")));
+        assert!(!chats.record("a", Some("   ")));
+        assert_eq!(
+            chats.list(),
+            vec![RecentChat {
+                user_id: "a".to_string(),
+                display_name: "Alice Smith".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn skips_new_contacts_without_a_name() {
+        let dir = tempfile::tempdir().unwrap();
+        let chats = recent_chats(&dir);
+
+        assert!(!chats.record("a", None));
+        assert!(!chats.record("a", Some("")));
+        assert!(chats.list().is_empty());
+    }
+
+    #[test]
+    fn drops_the_oldest_contact_past_the_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let chats = recent_chats(&dir);
+        for i in 0..=MAX_RECENT_CHATS {
+            chats.record(&i.to_string(), Some("Someone"));
+        }
+
+        let ids = user_ids(&chats);
+        assert_eq!(ids.len(), MAX_RECENT_CHATS);
+        assert_eq!(ids[0], MAX_RECENT_CHATS.to_string());
+        assert!(!ids.contains(&"0".to_string()));
+    }
+
+    #[test]
+    fn reloads_the_saved_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let chats = recent_chats(&dir);
+        chats.record("a", Some("Alice"));
+        chats.record("b", Some("Bob"));
+
+        assert_eq!(recent_chats(&dir).list(), chats.list());
+    }
+}
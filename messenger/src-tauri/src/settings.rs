@@ -1,76 +1,274 @@
+use crate::persistence::JsonFile;
+use chrono::{NaiveTime, Weekday};
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::path::PathBuf;
+use serde_json::Value;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::sync::Mutex;
 use tauri::{AppHandle, Emitter};
 
+/// Current version of the on-disk settings schema.
+/// Bump this and append a step to `MIGRATIONS` when existing fields are renamed or reshaped;
+/// newly added fields only need a serde default.
+pub const CURRENT_SCHEMA_VERSION: u32 = 1;
+
+/// A single forward migration, upgrading raw settings JSON by one version
+type Migration = fn(&mut Value) -> Result<(), String>;
+
+/// Ordered migration chain: `MIGRATIONS[n]` upgrades a version `n` file to `n + 1`
+const MIGRATIONS: &[Migration] = &[migrate_v0_to_v1];
+
 /// Application settings interface
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct AppSettings {
+    pub schema_version: u32,
     pub notifications: NotificationSettings,
     pub startup: StartupSettings,
     pub files: FileSettings,
+    pub contact_overrides: HashMap<String, ContactNotificationOverride>, // Keyed by contact user ID
+    pub quiet_hours: QuietHours,
 }
 
 /// Notification settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", default)]
 pub struct NotificationSettings {
     pub enabled: bool,
     pub sound_enabled: bool,
     pub sound_volume: u8, // 0-100
     pub desktop_alerts: bool,
+    pub shake_on_nudge: bool, // Shake the chat window when a nudge is received
+    pub events: NotificationEventToggles,
 }
 
-/// Startup settings
+/// Per-event notification toggles
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NotificationEventToggles {
+    pub message: bool,
+    pub contact_online: bool,
+    pub contact_offline: bool,
+    pub contact_request: bool,
+    pub nudge: bool,
+    pub call: bool,
+    pub file: bool,
+}
+
+/// Per-contact notification overrides; `None` fields inherit the global setting
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct ContactNotificationOverride {
+    pub enabled: Option<bool>,
+    pub sound_enabled: Option<bool>,
+    pub sound_volume: Option<u8>, // 0-100
+    pub desktop_alerts: Option<bool>,
+    pub sound_type: Option<String>, // Replaces the message sound for this contact
+}
+
+/// Notification policy after applying a contact's overrides to the global settings
+#[derive(Debug, Clone, PartialEq)]
+pub struct EffectiveNotificationPolicy {
+    pub enabled: bool,
+    pub sound_enabled: bool,
+    pub sound_volume: u8, // 0-100
+    pub desktop_alerts: bool,
+    pub sound_type: Option<String>,
+}
+
+impl EffectiveNotificationPolicy {
+    /// Whether a desktop notification should be shown
+    pub fn shows_desktop_alert(&self) -> bool {
+        self.enabled && self.desktop_alerts
+    }
+
+    /// Playback volume on a 0.0-1.0 scale
+    pub fn volume(&self) -> f32 {
+        f32::from(self.sound_volume.min(100)) / 100.0
+    }
+}
+
+impl NotificationSettings {
+    /// Resolve the effective policy for a contact, falling back to these global values
+    pub fn resolve(
+        &self,
+        contact_override: Option<&ContactNotificationOverride>,
+    ) -> EffectiveNotificationPolicy {
+        let o = contact_override.cloned().unwrap_or_default();
+        EffectiveNotificationPolicy {
+            enabled: o.enabled.unwrap_or(self.enabled),
+            sound_enabled: o.sound_enabled.unwrap_or(self.sound_enabled),
+            sound_volume: o.sound_volume.unwrap_or(self.sound_volume),
+            desktop_alerts: o.desktop_alerts.unwrap_or(self.desktop_alerts),
+            sound_type: o.sound_type,
+        }
+    }
+}
+
+/// Quiet hours (Do Not Disturb) schedule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct QuietHours {
+    pub enabled: bool,
+    pub allow_calls: bool, // Let incoming calls through while quiet
+    pub ranges: Vec<QuietRange>,
+}
+
+/// A recurring quiet period on selected weekdays
+/// A range whose end is at or before its start runs past midnight into the next day
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct QuietRange {
+    pub days: Vec<Weekday>, // Days on which the range starts
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+/// Startup settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct StartupSettings {
     pub auto_launch: bool,
     pub start_minimized: bool,
+    pub close_behavior: CloseBehavior,
 }
 
-/// File settings
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// What closing the main window does
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub enum CloseBehavior {
+    /// Hide the window and keep running in the tray
+    #[default]
+    MinimizeToTray,
+    /// Quit the app
+    Quit,
+    /// Ask the user, who can have the answer remembered
+    Ask,
+}
+
+/// File settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
 pub struct FileSettings {
     pub download_location: String,
     pub auto_accept_from: Vec<String>, // User IDs to auto-accept files from
+    pub per_contact_folders: bool,     // Save each contact's files in a folder named after them
 }
 
 /// Default settings values
-fn default_settings() -> AppSettings {
-    AppSettings {
-        notifications: NotificationSettings {
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            schema_version: CURRENT_SCHEMA_VERSION,
+            notifications: NotificationSettings::default(),
+            startup: StartupSettings::default(),
+            files: FileSettings::default(),
+            contact_overrides: HashMap::new(),
+            quiet_hours: QuietHours::default(),
+        }
+    }
+}
+
+impl Default for QuietHours {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            allow_calls: true,
+            ranges: Vec::new(),
+        }
+    }
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
             enabled: true,
             sound_enabled: true,
             sound_volume: 80,
             desktop_alerts: true,
-        },
-        startup: StartupSettings {
-            auto_launch: false,
-            start_minimized: false,
-        },
-        files: FileSettings {
-            download_location: String::new(),
-            auto_accept_from: Vec::new(),
-        },
+            shake_on_nudge: true,
+            events: NotificationEventToggles::default(),
+        }
     }
 }
 
+impl Default for NotificationEventToggles {
+    fn default() -> Self {
+        Self {
+            message: true,
+            contact_online: true,
+            contact_offline: true,
+            contact_request: true,
+            nudge: true,
+            call: true,
+            file: true,
+        }
+    }
+}
+
+/// Read the schema version of raw settings JSON.
+/// Files written before versioning was introduced have no version and count as 0.
+fn schema_version_of(value: &Value) -> u32 {
+    value
+        .get("schemaVersion")
+        .and_then(Value::as_u64)
+        // Too large for a u32 can only mean a (much) newer build
+        .map(|v| u32::try_from(v).unwrap_or(u32::MAX))
+        .unwrap_or(0)
+}
+
+/// Run every migration needed to bring raw settings JSON up to `CURRENT_SCHEMA_VERSION`
+fn migrate(value: &mut Value) -> Result<(), String> {
+    if !value.is_object() {
+        return Err("Settings file is not a JSON object".to_string());
+    }
+
+    let from = schema_version_of(value);
+    for (version, step) in MIGRATIONS.iter().enumerate().skip(from as usize) {
+        step(value).map_err(|e| {
+            format!(
+                "Failed to migrate settings from v{} to v{}: {}",
+                version,
+                version + 1,
+                e
+            )
+        })?;
+        value["schemaVersion"] = Value::from(version as u32 + 1);
+    }
+
+    Ok(())
+}
+
+/// v0 -> v1: introduce `schemaVersion`.
+/// Unversioned files already match the v1 layout; missing fields are filled by serde defaults.
+fn migrate_v0_to_v1(_value: &mut Value) -> Result<(), String> {
+    Ok(())
+}
+
+/// Path of the backup kept when a settings file is migrated from `version`
+fn migration_backup_path(storage_path: &Path, version: u32) -> PathBuf {
+    let file_name = storage_path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| "settings.json".to_string());
+    storage_path.with_file_name(format!("{}.v{}.bak", file_name, version))
+}
+
 /// Manages application settings and persistence
 pub struct SettingsManager {
     settings: Mutex<AppSettings>,
-    storage_path: PathBuf,
+    file: JsonFile,
+    read_only: bool, // Set when the file was written by a newer build
 }
 
 impl SettingsManager {
     /// Create a new SettingsManager with storage at the given path
     pub fn new(storage_path: PathBuf) -> Self {
         let mut manager = Self {
-            settings: Mutex::new(default_settings()),
-            storage_path,
+            settings: Mutex::new(AppSettings::default()),
+            file: JsonFile::new(storage_path),
+            read_only: false,
         };
 
         // Load settings from disk on initialization
@@ -88,6 +286,7 @@ impl SettingsManager {
 
     /// Update notification settings
     pub fn update_notification_settings(&self, updates: NotificationSettings) -> Result<(), String> {
+        self.ensure_writable()?;
         let mut settings = self.settings.lock().unwrap();
         settings.notifications = updates;
         drop(settings);
@@ -97,6 +296,7 @@ impl SettingsManager {
 
     /// Update startup settings
     pub fn update_startup_settings(&self, updates: StartupSettings) -> Result<(), String> {
+        self.ensure_writable()?;
         let mut settings = self.settings.lock().unwrap();
         settings.startup = updates;
         drop(settings);
@@ -106,6 +306,7 @@ impl SettingsManager {
 
     /// Update file settings
     pub fn update_file_settings(&self, updates: FileSettings) -> Result<(), String> {
+        self.ensure_writable()?;
         let mut settings = self.settings.lock().unwrap();
         settings.files = updates;
         drop(settings);
@@ -113,47 +314,156 @@ impl SettingsManager {
         Ok(())
     }
 
+    /// Update quiet hours
+    pub fn update_quiet_hours(&self, updates: QuietHours) -> Result<(), String> {
+        self.ensure_writable()?;
+        let mut settings = self.settings.lock().unwrap();
+        settings.quiet_hours = updates;
+        drop(settings);
+        self.save_to_disk()?;
+        Ok(())
+    }
+
+    /// Get the notification override for a contact, if any
+    pub fn get_contact_override(&self, user_id: &str) -> Option<ContactNotificationOverride> {
+        self.settings
+            .lock()
+            .unwrap()
+            .contact_overrides
+            .get(user_id)
+            .cloned()
+    }
+
+    /// Set the notification override for a contact
+    pub fn set_contact_override(
+        &self,
+        user_id: String,
+        contact_override: ContactNotificationOverride,
+    ) -> Result<(), String> {
+        if let Some(sound_type) = &contact_override.sound_type {
+            if crate::sound_file_for(sound_type).is_none() {
+                return Err(format!("Unknown sound type: {}", sound_type));
+            }
+        }
+
+        self.ensure_writable()?;
+        let mut settings = self.settings.lock().unwrap();
+        settings.contact_overrides.insert(user_id, contact_override);
+        drop(settings);
+        self.save_to_disk()?;
+        Ok(())
+    }
+
+    /// Clear the notification override for a contact
+    pub fn clear_contact_override(&self, user_id: &str) -> Result<(), String> {
+        let mut settings = self.settings.lock().unwrap();
+        if !settings.contact_overrides.contains_key(user_id) {
+            return Ok(());
+        }
+        self.ensure_writable()?;
+        settings.contact_overrides.remove(user_id);
+        drop(settings);
+        self.save_to_disk()?;
+        Ok(())
+    }
+
+    /// Resolve the notification policy for a sender, or the global policy when unknown
+    pub fn notification_policy_for(&self, sender_id: Option<&str>) -> EffectiveNotificationPolicy {
+        let settings = self.settings.lock().unwrap();
+        let contact_override = sender_id.and_then(|id| settings.contact_overrides.get(id));
+        settings.notifications.resolve(contact_override)
+    }
+
     /// Reset all settings to defaults
     pub fn reset_settings(&self) -> Result<(), String> {
-        *self.settings.lock().unwrap() = default_settings();
+        self.ensure_writable()?;
+        *self.settings.lock().unwrap() = AppSettings::default();
         self.save_to_disk()?;
         Ok(())
     }
 
     /// Load settings from disk
+    /// A file that cannot be migrated or read as `AppSettings` is as unusable as a corrupt one,
+    /// so the backup is tried in that case too.
     fn load_from_disk(&mut self) -> Result<(), String> {
-        if !self.storage_path.exists() {
+        // Newest schema seen in either file; a broken file from a newer build still must not be
+        // overwritten with the backup's contents
+        let newer_version = Cell::new(None);
+        let loaded = self.file.load_with(|mut value| {
+            let from_version = schema_version_of(&value);
+            let original = value.clone();
+            if from_version > CURRENT_SCHEMA_VERSION {
+                newer_version.set(newer_version.get().max(Some(from_version)));
+            } else {
+                migrate(&mut value)?;
+            }
+            let settings: AppSettings = serde_json::from_value(value).map_err(|e| e.to_string())?;
+            Ok((settings, from_version, original))
+        })?;
+        let Some((settings, from_version, original)) = loaded else {
+            return Ok(());
+        };
+
+        *self.settings.lock().unwrap() = settings;
+
+        if let Some(version) = newer_version.get() {
+            // Written by a newer build: read what we understand but never overwrite it
+            eprintln!(
+                "Settings file has schema v{}, newer than supported v{}",
+                version, CURRENT_SCHEMA_VERSION
+            );
+            self.read_only = true;
             return Ok(());
         }
 
-        let contents = fs::read_to_string(&self.storage_path)
-            .map_err(|e| format!("Failed to read settings file: {}", e))?;
+        if from_version < CURRENT_SCHEMA_VERSION {
+            // Keep the pre-migration file around before upgrading it in place
+            let backup_path = migration_backup_path(self.file.path(), from_version);
+            JsonFile::new(backup_path)
+                .save(&original)
+                .map_err(|e| format!("Failed to back up settings before migration: {}", e))?;
+
+            self.save_to_disk()?;
+            println!(
+                "Migrated settings from schema v{} to v{}",
+                from_version, CURRENT_SCHEMA_VERSION
+            );
+        }
 
-        let settings: AppSettings = serde_json::from_str(&contents)
-            .map_err(|e| format!("Failed to parse settings: {}", e))?;
+        Ok(())
+    }
 
-        *self.settings.lock().unwrap() = settings;
+    /// Write the current settings to disk, e.g. before the app exits
+    /// Does nothing when the settings file belongs to a newer build.
+    pub fn flush(&self) -> Result<(), String> {
+        if self.read_only {
+            return Ok(());
+        }
+        self.save_to_disk()
+    }
 
+    /// Fail when the settings file belongs to a newer build
+    /// Checked before changing anything, so the running app keeps matching the file.
+    fn ensure_writable(&self) -> Result<(), String> {
+        if self.read_only {
+            return Err(
+                "Settings were saved by a newer version of the app; changes will not be saved"
+                    .to_string(),
+            );
+        }
         Ok(())
     }
 
     /// Save settings to disk
+    /// Refused when the settings file belongs to a newer build, so it is never downgraded.
     fn save_to_disk(&self) -> Result<(), String> {
-        let settings = self.settings.lock().unwrap();
-
-        let json = serde_json::to_string_pretty(&*settings)
-            .map_err(|e| format!("Failed to serialize settings: {}", e))?;
-
-        // Ensure parent directory exists
-        if let Some(parent) = self.storage_path.parent() {
-            fs::create_dir_all(parent)
-                .map_err(|e| format!("Failed to create storage directory: {}", e))?;
-        }
+        self.ensure_writable()?;
 
-        fs::write(&self.storage_path, json)
-            .map_err(|e| format!("Failed to write settings file: {}", e))?;
+        let settings = self.settings.lock().unwrap();
 
-        Ok(())
+        self.file
+            .save(&*settings)
+            .map_err(|e| format!("Failed to write settings file: {}", e))
     }
 }
 
@@ -208,6 +518,67 @@ pub fn update_file_settings(
     Ok(())
 }
 
+/// Update quiet hours
+#[tauri::command]
+pub fn update_quiet_hours(
+    app: AppHandle,
+    settings_manager: tauri::State<SettingsManager>,
+    quiet_hours: QuietHours,
+) -> Result<(), String> {
+    settings_manager.update_quiet_hours(quiet_hours.clone())?;
+
+    // Emit event to all windows
+    let _ = app.emit("settings-changed", quiet_hours);
+
+    Ok(())
+}
+
+/// Get the notification override for a contact
+#[tauri::command]
+pub fn get_contact_notification_override(
+    settings_manager: tauri::State<SettingsManager>,
+    user_id: String,
+) -> Option<ContactNotificationOverride> {
+    settings_manager.get_contact_override(&user_id)
+}
+
+/// Set the notification override for a contact
+#[tauri::command]
+pub fn set_contact_notification_override(
+    app: AppHandle,
+    settings_manager: tauri::State<SettingsManager>,
+    user_id: String,
+    contact_override: ContactNotificationOverride,
+) -> Result<(), String> {
+    settings_manager.set_contact_override(user_id.clone(), contact_override.clone())?;
+
+    // Emit event to all windows
+    let _ = app.emit(
+        "contact-override-changed",
+        serde_json::json!({ "userId": user_id, "override": contact_override }),
+    );
+
+    Ok(())
+}
+
+/// Clear the notification override for a contact
+#[tauri::command]
+pub fn clear_contact_notification_override(
+    app: AppHandle,
+    settings_manager: tauri::State<SettingsManager>,
+    user_id: String,
+) -> Result<(), String> {
+    settings_manager.clear_contact_override(&user_id)?;
+
+    // Emit event to all windows
+    let _ = app.emit(
+        "contact-override-changed",
+        serde_json::json!({ "userId": user_id, "override": null }),
+    );
+
+    Ok(())
+}
+
 /// Reset settings to defaults
 #[tauri::command]
 pub fn reset_settings(
@@ -223,3 +594,143 @@ pub fn reset_settings(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn reads_schema_versions() {
+        assert_eq!(schema_version_of(&json!({})), 0);
+        assert_eq!(schema_version_of(&json!({ "schemaVersion": 1 })), 1);
+        assert_eq!(schema_version_of(&json!({ "schemaVersion": "1" })), 0);
+        assert_eq!(schema_version_of(&json!({ "schemaVersion": -1 })), 0);
+        assert_eq!(
+            schema_version_of(&json!({ "schemaVersion": u64::from(u32::MAX) + 1 })),
+            u32::MAX
+        );
+    }
+
+    #[test]
+    fn migrates_unversioned_settings() {
+        let mut value = json!({ "notifications": { "soundVolume": 30 } });
+        migrate(&mut value).unwrap();
+        assert_eq!(schema_version_of(&value), CURRENT_SCHEMA_VERSION);
+        assert_eq!(value["notifications"]["soundVolume"], 30);
+    }
+
+    #[test]
+    fn leaves_current_and_newer_settings_alone() {
+        for version in [CURRENT_SCHEMA_VERSION, CURRENT_SCHEMA_VERSION + 1, u32::MAX] {
+            let original = json!({ "schemaVersion": version, "future": true });
+            let mut value = original.clone();
+            migrate(&mut value).unwrap();
+            assert_eq!(value, original);
+        }
+    }
+
+    #[test]
+    fn rejects_non_object_settings() {
+        for mut value in [json!([]), json!(null), json!("settings")] {
+            assert!(migrate(&mut value).is_err());
+        }
+    }
+
+    #[test]
+    fn upgrades_old_files_on_disk_and_keeps_a_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let original = r#"{ "notifications": { "soundVolume": 30 } }"#;
+        fs::write(&path, original).unwrap();
+
+        let manager = SettingsManager::new(path.clone());
+        assert_eq!(manager.get_settings().notifications.sound_volume, 30);
+
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(schema_version_of(&saved), CURRENT_SCHEMA_VERSION);
+        let backup = fs::read_to_string(migration_backup_path(&path, 0)).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Value>(&backup).unwrap(),
+            serde_json::from_str::<Value>(original).unwrap()
+        );
+    }
+
+    #[test]
+    fn falls_back_to_the_backup_when_settings_do_not_deserialize() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let broken = r#"{ "schemaVersion": 1, "notifications": { "soundVolume": "loud" } }"#;
+        fs::write(&path, broken).unwrap();
+        fs::write(
+            dir.path().join("settings.json.bak"),
+            r#"{ "schemaVersion": 1, "notifications": { "soundVolume": 30 } }"#,
+        )
+        .unwrap();
+
+        let manager = SettingsManager::new(path.clone());
+        assert_eq!(manager.get_settings().notifications.sound_volume, 30);
+
+        // Saving replaces the broken file but keeps the backup it was recovered from
+        manager.flush().unwrap();
+        let saved: Value = serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(saved["notifications"]["soundVolume"], 30);
+        let backup = fs::read_to_string(dir.path().join("settings.json.bak")).unwrap();
+        assert!(backup.contains("30"));
+    }
+
+    #[test]
+    fn stays_read_only_when_a_newer_file_falls_back_to_the_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let newer = r#"{ "schemaVersion": 99, "notifications": "moved elsewhere" }"#;
+        fs::write(&path, newer).unwrap();
+        fs::write(
+            dir.path().join("settings.json.bak"),
+            r#"{ "schemaVersion": 1, "notifications": { "soundVolume": 30 } }"#,
+        )
+        .unwrap();
+
+        let manager = SettingsManager::new(path.clone());
+        assert_eq!(manager.get_settings().notifications.sound_volume, 30);
+        assert!(manager.reset_settings().is_err());
+        assert!(manager.flush().is_ok());
+        assert_eq!(fs::read_to_string(&path).unwrap(), newer);
+    }
+
+    #[test]
+    fn never_overwrites_settings_from_a_newer_build() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("settings.json");
+        let original =
+            r#"{ "schemaVersion": 99, "notifications": { "soundVolume": 30 }, "future": {} }"#;
+        fs::write(&path, original).unwrap();
+
+        let manager = SettingsManager::new(path.clone());
+        assert_eq!(manager.get_settings().notifications.sound_volume, 30);
+        let loaded = serde_json::to_value(manager.get_settings()).unwrap();
+
+        assert!(manager
+            .update_quiet_hours(QuietHours {
+                enabled: true,
+                ..QuietHours::default()
+            })
+            .is_err());
+        assert!(manager
+            .set_contact_override("someone".to_string(), Default::default())
+            .is_err());
+        assert!(manager.clear_contact_override("nobody").is_ok());
+        assert!(manager.reset_settings().is_err());
+        assert!(manager.flush().is_ok());
+
+        // The running app keeps the settings it loaded
+        assert_eq!(
+            serde_json::to_value(manager.get_settings()).unwrap(),
+            loaded
+        );
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), original);
+        assert!(!dir.path().join("settings.json.bak").exists());
+    }
+}
@@ -0,0 +1,355 @@
+use crate::auth_preferences::AuthPreferencesManager;
+use crate::settings::SettingsManager;
+use crate::AppState;
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::Notify;
+
+/// Event broadcast to every window when the app starts shutting down
+pub const SHUTDOWN_EVENT: &str = "app-shutting-down";
+
+/// Longest time to wait for shutdown hooks before exiting anyway
+const HOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Runs the shutdown steps once, however the app was asked to quit:
+/// 1. broadcast `app-shutting-down` to all windows
+/// 2. wait for the registered shutdown hooks, up to `HOOK_TIMEOUT`
+/// 3. flush the managers to disk
+/// 4. exit
+///
+/// Windows register a named hook, do their cleanup when the event arrives (e.g. going offline)
+/// and then complete it. Work in Rust that must not be cut short holds a `ShutdownGuard`.
+pub struct ShutdownCoordinator {
+    started: AtomicBool,
+    finished: AtomicBool,
+    hooks: Mutex<HashSet<(String, String)>>, // (window label, hook name)
+    tasks: AtomicUsize,
+    changed: Notify,
+}
+
+/// Keeps shutdown waiting while it is alive
+pub struct ShutdownGuard<'a> {
+    coordinator: &'a ShutdownCoordinator,
+}
+
+impl Drop for ShutdownGuard<'_> {
+    fn drop(&mut self) {
+        self.coordinator.tasks.fetch_sub(1, Ordering::SeqCst);
+        self.coordinator.changed.notify_waiters();
+    }
+}
+
+impl ShutdownCoordinator {
+    /// Create a new ShutdownCoordinator with no hooks
+    pub fn new() -> Self {
+        Self {
+            started: AtomicBool::new(false),
+            finished: AtomicBool::new(false),
+            hooks: Mutex::new(HashSet::new()),
+            tasks: AtomicUsize::new(0),
+            changed: Notify::new(),
+        }
+    }
+
+    /// Whether the shutdown steps have run and the app may exit
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::SeqCst)
+    }
+
+    /// Make shutdown wait for the work done while the guard is alive
+    pub fn guard(&self) -> ShutdownGuard<'_> {
+        self.tasks.fetch_add(1, Ordering::SeqCst);
+        ShutdownGuard { coordinator: self }
+    }
+
+    /// Register a hook that shutdown waits for until the window completes it
+    pub fn register_hook(&self, window: &str, name: &str) {
+        self.hooks
+            .lock()
+            .unwrap()
+            .insert((window.to_string(), name.to_string()));
+    }
+
+    /// Mark a hook as done
+    pub fn complete_hook(&self, window: &str, name: &str) {
+        self.hooks
+            .lock()
+            .unwrap()
+            .remove(&(window.to_string(), name.to_string()));
+        self.changed.notify_waiters();
+    }
+
+    /// Drop the hooks of a window that was closed; it can no longer complete them
+    pub fn forget_window(&self, window: &str) {
+        self.hooks
+            .lock()
+            .unwrap()
+            .retain(|(label, _)| label != window);
+        self.changed.notify_waiters();
+    }
+
+    fn pending_hooks(&self) -> Vec<String> {
+        self.hooks
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(window, name)| format!("{}/{}", window, name))
+            .collect()
+    }
+
+    /// Wait until every hook is completed and every guard dropped
+    async fn wait_for_hooks(&self) {
+        loop {
+            // Created before checking, so a change in between still wakes us up
+            let changed = self.changed.notified();
+            if self.hooks.lock().unwrap().is_empty() && self.tasks.load(Ordering::SeqCst) == 0 {
+                return;
+            }
+            changed.await;
+        }
+    }
+
+    /// Run the shutdown steps: `broadcast`, wait up to `timeout` for the hooks and guards, then
+    /// `flush`
+    /// Returns whether the hooks and guards finished in time; the managers are flushed either way.
+    async fn run_steps(
+        &self,
+        timeout: Duration,
+        broadcast: impl FnOnce(),
+        flush: impl FnOnce(),
+    ) -> bool {
+        broadcast();
+
+        let finished = tokio::time::timeout(timeout, self.wait_for_hooks())
+            .await
+            .is_ok();
+        if !finished {
+            eprintln!(
+                "Shutdown hooks did not finish within {:?}, exiting anyway (pending: {:?}, tasks: {})",
+                timeout,
+                self.pending_hooks(),
+                self.tasks.load(Ordering::SeqCst)
+            );
+        }
+
+        flush();
+        finished
+    }
+}
+
+/// Start shutting down in the background; later requests while it runs are ignored
+pub fn request(app: &AppHandle, code: i32) {
+    let coordinator = app.state::<ShutdownCoordinator>();
+    if coordinator.started.swap(true, Ordering::SeqCst) {
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        run(&app).await;
+        app.state::<ShutdownCoordinator>()
+            .finished
+            .store(true, Ordering::SeqCst);
+        app.exit(code);
+    });
+}
+
+async fn run(app: &AppHandle) {
+    println!("Shutting down");
+    app.state::<ShutdownCoordinator>()
+        .run_steps(
+            HOOK_TIMEOUT,
+            || {
+                if let Err(e) = app.emit(SHUTDOWN_EVENT, ()) {
+                    eprintln!("Failed to emit {} event: {}", SHUTDOWN_EVENT, e);
+                }
+            },
+            || flush_managers(app),
+        )
+        .await;
+}
+
+/// Write everything the managers hold in memory to disk
+fn flush_managers(app: &AppHandle) {
+    if let Err(e) = app.state::<SettingsManager>().flush() {
+        eprintln!("Failed to flush settings: {}", e);
+    }
+    if let Err(e) = app.state::<AuthPreferencesManager>().flush() {
+        eprintln!("Failed to flush auth preferences: {}", e);
+    }
+    if let Err(e) = app.state::<AppState>().chat_windows().flush() {
+        eprintln!("Failed to flush chat window geometry: {}", e);
+    }
+}
+
+/// Shut down gracefully when the process receives SIGTERM, e.g. from the session manager
+#[cfg(unix)]
+pub fn listen_for_sigterm(app: AppHandle) {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    tauri::async_runtime::spawn(async move {
+        let mut sigterm = match signal(SignalKind::terminate()) {
+            Ok(sigterm) => sigterm,
+            Err(e) => {
+                eprintln!("Failed to listen for SIGTERM: {}", e);
+                return;
+            }
+        };
+        if sigterm.recv().await.is_some() {
+            println!("Received SIGTERM");
+            request(&app, 0);
+        }
+    });
+}
+
+/// Register a hook that shutdown waits for until the calling window completes it
+#[tauri::command]
+pub fn register_shutdown_hook(
+    coordinator: tauri::State<ShutdownCoordinator>,
+    window: tauri::WebviewWindow,
+    name: String,
+) {
+    coordinator.register_hook(window.label(), &name);
+}
+
+/// Mark one of the calling window's shutdown hooks as done
+#[tauri::command]
+pub fn complete_shutdown_hook(
+    coordinator: tauri::State<ShutdownCoordinator>,
+    window: tauri::WebviewWindow,
+    name: String,
+) {
+    coordinator.complete_hook(window.label(), &name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::time::{sleep, Instant};
+
+    /// Run the shutdown steps next to `work`, recording each step in `log`
+    async fn shut_down(
+        coordinator: &ShutdownCoordinator,
+        log: &Mutex<Vec<&'static str>>,
+        work: impl std::future::Future<Output = ()>,
+    ) -> bool {
+        let steps = coordinator.run_steps(
+            HOOK_TIMEOUT,
+            || log.lock().unwrap().push("broadcast"),
+            || log.lock().unwrap().push("flush"),
+        );
+        tokio::join!(steps, work).0
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn runs_straight_through_without_hooks() {
+        let coordinator = ShutdownCoordinator::new();
+        let log = Mutex::new(Vec::new());
+        let start = Instant::now();
+
+        assert!(shut_down(&coordinator, &log, async {}).await);
+        assert_eq!(*log.lock().unwrap(), vec!["broadcast", "flush"]);
+        assert_eq!(start.elapsed(), Duration::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn flushes_after_the_hooks_complete() {
+        let coordinator = ShutdownCoordinator::new();
+        coordinator.register_hook("main", "go-offline");
+        coordinator.register_hook("chat-1", "save-draft");
+        let log = Mutex::new(Vec::new());
+
+        let work = async {
+            sleep(Duration::from_secs(1)).await;
+            coordinator.complete_hook("main", "go-offline");
+            log.lock().unwrap().push("go-offline");
+            sleep(Duration::from_secs(1)).await;
+            coordinator.complete_hook("chat-1", "save-draft");
+            log.lock().unwrap().push("save-draft");
+        };
+
+        assert!(shut_down(&coordinator, &log, work).await);
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["broadcast", "go-offline", "save-draft", "flush"]
+        );
+    }
+
+    #[test]
+    fn hooks_belong_to_their_window() {
+        let coordinator = ShutdownCoordinator::new();
+        coordinator.register_hook("main", "go-offline");
+        coordinator.complete_hook("chat-1", "go-offline");
+        coordinator.complete_hook("main", "other");
+
+        assert_eq!(coordinator.pending_hooks(), vec!["main/go-offline"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_on_hooks_after_the_timeout() {
+        let coordinator = ShutdownCoordinator::new();
+        coordinator.register_hook("main", "go-offline");
+        let log = Mutex::new(Vec::new());
+        let start = Instant::now();
+
+        assert!(!shut_down(&coordinator, &log, async {}).await);
+        assert_eq!(start.elapsed(), HOOK_TIMEOUT);
+        assert_eq!(*log.lock().unwrap(), vec!["broadcast", "flush"]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn stops_waiting_for_a_closed_window() {
+        let coordinator = ShutdownCoordinator::new();
+        coordinator.register_hook("chat-1", "save-draft");
+        coordinator.register_hook("chat-1", "leave-call");
+        let log = Mutex::new(Vec::new());
+        let start = Instant::now();
+
+        let work = async {
+            sleep(Duration::from_secs(1)).await;
+            coordinator.forget_window("chat-1");
+        };
+
+        assert!(shut_down(&coordinator, &log, work).await);
+        assert_eq!(start.elapsed(), Duration::from_secs(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn waits_for_every_guard() {
+        let coordinator = ShutdownCoordinator::new();
+        let first = coordinator.guard();
+        let second = coordinator.guard();
+        let log = Mutex::new(Vec::new());
+        let start = Instant::now();
+
+        let work = async {
+            sleep(Duration::from_secs(1)).await;
+            drop(first);
+            log.lock().unwrap().push("first");
+            sleep(Duration::from_secs(2)).await;
+            drop(second);
+            log.lock().unwrap().push("second");
+        };
+
+        assert!(shut_down(&coordinator, &log, work).await);
+        assert_eq!(start.elapsed(), Duration::from_secs(3));
+        assert_eq!(
+            *log.lock().unwrap(),
+            vec!["broadcast", "first", "second", "flush"]
+        );
+        assert_eq!(coordinator.tasks.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn a_guard_outliving_the_timeout_is_abandoned() {
+        let coordinator = ShutdownCoordinator::new();
+        let _guard = coordinator.guard();
+        let log = Mutex::new(Vec::new());
+
+        assert!(!shut_down(&coordinator, &log, async {}).await);
+        assert_eq!(*log.lock().unwrap(), vec!["broadcast", "flush"]);
+    }
+}
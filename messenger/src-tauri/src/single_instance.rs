@@ -0,0 +1,395 @@
+use crate::deep_link::{self, DeepLink};
+use crate::persistence::{OsStorage, Storage};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// How long a second instance waits for the running one to publish its endpoint
+const ENDPOINT_WAIT: Duration = Duration::from_secs(3);
+
+/// Socket timeout for forwarding arguments
+const FORWARD_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Upper bound on a forwarded message, to keep a misbehaving client from exhausting memory
+const MAX_MESSAGE_SIZE: u64 = 64 * 1024;
+
+/// Where the running instance listens for forwarded launches.
+/// Kept separate from the lock file, which cannot be read while locked on Windows.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Endpoint {
+    pid: u32, // For diagnostics only
+
+    port: u16,
+    token: String,
+}
+
+/// Arguments forwarded from a second launch of the same profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ForwardedLaunch {
+    token: String,
+    pub args: Vec<String>,
+    pub cwd: Option<PathBuf>,
+}
+
+/// Result of trying to become the single instance for a profile
+pub enum InstanceStatus {
+    /// This process owns the profile; keep the lock alive for the process lifetime
+    Primary(InstanceLock),
+    /// Another instance owns the profile and has received our arguments
+    Forwarded,
+}
+
+/// Exclusive per-profile lock, released when dropped or when the process dies
+pub struct InstanceLock {
+    _file: File,
+    endpoint_path: PathBuf,
+    listener: Option<TcpListener>,
+    token: String,
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.endpoint_path);
+    }
+}
+
+/// Acquire the profile lock in `data_dir`, or forward `args` to the instance holding it.
+///
+/// The lock is an OS file lock, so a lock file left behind by a crashed instance is stale by
+/// definition and is simply taken over.
+pub fn acquire(data_dir: &Path, args: Vec<String>) -> Result<InstanceStatus, String> {
+    fs::create_dir_all(data_dir).map_err(|e| format!("Failed to create data directory: {}", e))?;
+
+    let lock_path = data_dir.join("instance.lock");
+    let endpoint_path = data_dir.join("instance.json");
+
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(&lock_path)
+        .map_err(|e| format!("Failed to open instance lock: {}", e))?;
+
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => {
+            forward(&endpoint_path, args)?;
+            return Ok(InstanceStatus::Forwarded);
+        }
+        Err(TryLockError::Error(e)) => {
+            // Some filesystems (e.g. network shares) do not support locking; run unguarded
+            eprintln!(
+                "Failed to lock {:?}, running without single-instance guard: {}",
+                lock_path, e
+            );
+        }
+    }
+
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)))
+        .map_err(|e| format!("Failed to open instance socket: {}", e))?;
+    let port = listener
+        .local_addr()
+        .map_err(|e| format!("Failed to read instance socket address: {}", e))?
+        .port();
+
+    let token: String = {
+        let bytes: [u8; 16] = rand::thread_rng().gen();
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    };
+
+    let endpoint = Endpoint {
+        pid: std::process::id(),
+        port,
+        token: token.clone(),
+    };
+    write_endpoint(&endpoint_path, &endpoint)?;
+
+    Ok(InstanceStatus::Primary(InstanceLock {
+        _file: file,
+        endpoint_path,
+        listener: Some(listener),
+        token,
+    }))
+}
+
+/// Whether a running instance holds the profile lock in `data_dir`
+/// The lock is only probed and its file closed again, so the directory can be renamed or deleted
+/// afterwards; callers that need the answer to stay true hold the profile's change lock.
+pub fn is_locked(data_dir: &Path) -> Result<bool, String> {
+    let file = match File::open(data_dir.join("instance.lock")) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(format!("Failed to open instance lock: {}", e)),
+    };
+
+    match file.try_lock() {
+        Ok(()) => Ok(false),
+        Err(TryLockError::WouldBlock) => Ok(true),
+        // Filesystems without locking run unguarded in `acquire` too
+        Err(TryLockError::Error(_)) => Ok(false),
+    }
+}
+
+/// Write the endpoint file readable only by the current user
+/// It is written to a temp file and renamed into place, so a second launch reading it never sees
+/// it half-written.
+fn write_endpoint(path: &Path, endpoint: &Endpoint) -> Result<(), String> {
+    let json = serde_json::to_string(endpoint)
+        .map_err(|e| format!("Failed to serialize instance endpoint: {}", e))?;
+
+    let temp_path = path.with_extension("json.tmp");
+    OsStorage
+        .write_synced(&temp_path, json.as_bytes(), Some(0o600))
+        .and_then(|()| OsStorage.rename(&temp_path, path))
+        .map_err(|e| {
+            let _ = fs::remove_file(&temp_path);
+            format!("Failed to write instance endpoint: {}", e)
+        })
+}
+
+/// Send our arguments to the running instance
+fn forward(endpoint_path: &Path, args: Vec<String>) -> Result<(), String> {
+    // The running instance may have just taken the lock and not yet published its endpoint,
+    // in which case the file is missing or still describes a previous, dead instance
+    let mut waited = Duration::ZERO;
+    let (endpoint, mut stream) = loop {
+        let attempt = fs::read_to_string(endpoint_path)
+            .map_err(|e| format!("Failed to read instance endpoint: {}", e))
+            .and_then(|contents| {
+                serde_json::from_str::<Endpoint>(&contents)
+                    .map_err(|e| format!("Failed to parse instance endpoint: {}", e))
+            })
+            .and_then(|endpoint| {
+                let address = SocketAddr::from((Ipv4Addr::LOCALHOST, endpoint.port));
+                TcpStream::connect_timeout(&address, FORWARD_TIMEOUT)
+                    .map(|stream| (endpoint, stream))
+                    .map_err(|e| format!("Failed to reach the running instance: {}", e))
+            });
+
+        match attempt {
+            Ok(connected) => break connected,
+            Err(_) if waited < ENDPOINT_WAIT => {
+                thread::sleep(Duration::from_millis(100));
+                waited += Duration::from_millis(100);
+            }
+            Err(e) => return Err(e),
+        }
+    };
+
+    let message = ForwardedLaunch {
+        token: endpoint.token,
+        args,
+        cwd: std::env::current_dir().ok(),
+    };
+    let mut json = serde_json::to_string(&message)
+        .map_err(|e| format!("Failed to serialize forwarded arguments: {}", e))?;
+    json.push('\n');
+
+    stream
+        .set_write_timeout(Some(FORWARD_TIMEOUT))
+        .and_then(|_| stream.write_all(json.as_bytes()))
+        .map_err(|e| format!("Failed to forward arguments: {}", e))
+}
+
+/// Accept forwarded launches in the background for the lifetime of the app
+pub fn listen(app: AppHandle, lock: &mut InstanceLock) {
+    let Some(listener) = lock.listener.take() else {
+        return;
+    };
+    let token = lock.token.clone();
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            match read_forwarded(stream, &token) {
+                Ok(launch) => handle_forwarded(&app, launch),
+                Err(e) => eprintln!("Ignoring forwarded launch: {}", e),
+            }
+        }
+    });
+}
+
+/// Read and authenticate one forwarded launch
+fn read_forwarded(stream: TcpStream, token: &str) -> Result<ForwardedLaunch, String> {
+    stream
+        .set_read_timeout(Some(FORWARD_TIMEOUT))
+        .map_err(|e| format!("Failed to configure socket: {}", e))?;
+
+    let mut line = String::new();
+    BufReader::new(stream.take(MAX_MESSAGE_SIZE))
+        .read_line(&mut line)
+        .map_err(|e| format!("Failed to read forwarded launch: {}", e))?;
+
+    let launch: ForwardedLaunch = serde_json::from_str(&line)
+        .map_err(|e| format!("Failed to parse forwarded launch: {}", e))?;
+
+    if launch.token != token {
+        return Err("Invalid token".to_string());
+    }
+    Ok(launch)
+}
+
+/// Open the links of the new launch, or bring the running instance to the front,
+/// and tell the frontend about the launch
+fn handle_forwarded(app: &AppHandle, launch: ForwardedLaunch) {
+    let links = DeepLink::from_args(&launch.args);
+    if !links.is_empty() {
+        deep_link::dispatch_all(app, &links);
+    } else if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+
+    let _ = app.emit(
+        "second-instance",
+        serde_json::json!({ "args": launch.args, "cwd": launch.cwd }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn primary(status: InstanceStatus) -> InstanceLock {
+        match status {
+            InstanceStatus::Primary(lock) => lock,
+            InstanceStatus::Forwarded => panic!("expected to become the primary instance"),
+        }
+    }
+
+    fn read_endpoint(data_dir: &Path) -> Endpoint {
+        serde_json::from_str(&fs::read_to_string(data_dir.join("instance.json")).unwrap()).unwrap()
+    }
+
+    /// Send `message` to the listener of `lock` and return the connection it accepted
+    fn send(lock: &InstanceLock, message: Vec<u8>) -> TcpStream {
+        let listener = lock.listener.as_ref().unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut client = TcpStream::connect(address).unwrap();
+            // The reader may stop early on oversized messages
+            let _ = client.write_all(&message);
+        });
+        listener.accept().unwrap().0
+    }
+
+    fn launch(token: &str, args: &[&str]) -> Vec<u8> {
+        let mut json = serde_json::to_vec(&ForwardedLaunch {
+            token: token.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            cwd: None,
+        })
+        .unwrap();
+        json.push(b'\n');
+        json
+    }
+
+    #[test]
+    fn takes_over_the_lock_of_an_instance_that_is_gone() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = primary(acquire(dir.path(), Vec::new()).unwrap());
+        let first_port = first
+            .listener
+            .as_ref()
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        drop(first);
+
+        // A crashed instance leaves both files behind
+        fs::write(dir.path().join("instance.lock"), "").unwrap();
+        write_endpoint(
+            &dir.path().join("instance.json"),
+            &Endpoint {
+                pid: 0,
+                port: first_port,
+                token: "stale".to_string(),
+            },
+        )
+        .unwrap();
+
+        let second = primary(acquire(dir.path(), Vec::new()).unwrap());
+
+        let endpoint = read_endpoint(dir.path());
+        assert_eq!(endpoint.token, second.token);
+        assert_eq!(
+            endpoint.port,
+            second
+                .listener
+                .as_ref()
+                .unwrap()
+                .local_addr()
+                .unwrap()
+                .port()
+        );
+        assert!(!dir.path().join("instance.json.tmp").exists());
+    }
+
+    #[test]
+    fn forwards_arguments_to_the_running_instance() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = primary(acquire(dir.path(), Vec::new()).unwrap());
+
+        let args = vec!["--minimized".to_string(), "spirit://chat/u1".to_string()];
+        let status = acquire(dir.path(), args.clone()).unwrap();
+        assert!(matches!(status, InstanceStatus::Forwarded));
+
+        let (stream, _) = lock.listener.as_ref().unwrap().accept().unwrap();
+        let forwarded = read_forwarded(stream, &lock.token).unwrap();
+        assert_eq!(forwarded.args, args);
+        assert_eq!(forwarded.cwd, std::env::current_dir().ok());
+    }
+
+    #[test]
+    fn the_endpoint_is_removed_with_the_lock() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = primary(acquire(dir.path(), Vec::new()).unwrap());
+        assert!(is_locked(dir.path()).unwrap());
+
+        drop(lock);
+
+        assert!(!dir.path().join("instance.json").exists());
+        assert!(!is_locked(dir.path()).unwrap());
+    }
+
+    #[test]
+    fn rejects_a_wrong_token() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = primary(acquire(dir.path(), Vec::new()).unwrap());
+
+        let stream = send(&lock, launch("not-the-token", &["--minimized"]));
+
+        assert_eq!(
+            read_forwarded(stream, &lock.token).unwrap_err(),
+            "Invalid token"
+        );
+    }
+
+    #[test]
+    fn rejects_an_oversized_message() {
+        let dir = tempfile::tempdir().unwrap();
+        let lock = primary(acquire(dir.path(), Vec::new()).unwrap());
+
+        let huge = "x".repeat(MAX_MESSAGE_SIZE as usize);
+        let stream = send(&lock, launch(&lock.token, &[&huge]));
+
+        let error = read_forwarded(stream, &lock.token).unwrap_err();
+        assert!(
+            error.starts_with("Failed to parse forwarded launch"),
+            "{}",
+            error
+        );
+    }
+}
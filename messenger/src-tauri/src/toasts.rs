@@ -0,0 +1,729 @@
+use crate::chat_target::ChatTarget;
+use crate::chat_windows;
+use crate::notification_policy::{
+    self, NotificationEvent, NotificationEventKind, RequestedOutputs,
+};
+use crate::recent_chats;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager, Monitor, WebviewWindowBuilder, WindowEvent};
+
+/// Prefix of every toast window label
+pub const TOAST_WINDOW_PREFIX: &str = "notification-";
+
+/// Most toasts shown at once, across all monitors
+const MAX_VISIBLE: usize = 3;
+
+/// Size of a toast window
+const TOAST_SIZE: (f64, f64) = (300.0, 120.0);
+
+/// Space between stacked toasts
+const TOAST_GAP: f64 = 8.0;
+
+/// Space between the toasts and the edge of the work area
+const SCREEN_MARGIN: f64 = 12.0;
+
+/// How long a toast stays up
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+
+/// Time left on a toast after the pointer leaves it, at least
+const MIN_DURATION_AFTER_HOVER: Duration = Duration::from_millis(1500);
+
+/// A toast to show
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Toast {
+    pub message: String,
+    pub description: String,
+    /// Chat to open when the toast is clicked
+    pub chat: Option<ChatTarget>,
+    /// Sender of the message, when the chat alone does not say, e.g. in a group chat
+    #[serde(default)]
+    pub sender_id: Option<String>,
+    /// Conversation the message arrived in, when known
+    #[serde(default)]
+    pub conversation_id: Option<String>,
+}
+
+/// A toast on screen
+struct VisibleToast {
+    label: String,
+    toast: Toast,
+    monitor: String,
+    slot: usize,
+    remaining: Duration,
+    running_since: Option<Instant>, // None while paused
+    timer: u64,                     // Bumped to invalidate pending expiry tasks
+}
+
+/// Visible toasts and the ones waiting for room, kept apart from the windows so the
+/// bookkeeping can be reasoned about on its own
+#[derive(Default)]
+struct ToastQueue {
+    visible: Vec<VisibleToast>,
+    pending: VecDeque<Toast>,
+    next_id: u64,
+}
+
+/// Where a toast offered to the queue ended up
+#[derive(Debug, PartialEq)]
+enum Admission {
+    /// Shown under `label` in `slot`
+    Visible { label: String, slot: usize },
+    /// Waiting for room
+    Queued,
+    /// Not kept, since there is no monitor to show it on and no visible toast whose closing
+    /// would retry the queue
+    Dropped,
+}
+
+impl ToastQueue {
+    /// Show `toast` in a free slot of `monitor`, given as its key and slot count, or queue it
+    /// Toasts keep their order: nothing jumps ahead of ones already waiting, even with room.
+    fn admit(&mut self, toast: &Toast, monitor: Option<(&str, usize)>, now: Instant) -> Admission {
+        if self.pending.is_empty() {
+            let reserved = monitor.and_then(|(key, slots)| self.reserve(toast, key, slots, now));
+            if let Some((label, slot)) = reserved {
+                return Admission::Visible { label, slot };
+            }
+            // Only a closing toast retries the queue, so with none visible it would wait forever
+            if self.visible.is_empty() {
+                return Admission::Dropped;
+            }
+        }
+
+        self.pending.push_back(toast.clone());
+        Admission::Queued
+    }
+
+    /// Take the oldest waiting toast if there is room to show it
+    fn next_pending(&mut self) -> Option<Toast> {
+        if self.visible.len() >= MAX_VISIBLE {
+            return None;
+        }
+        self.pending.pop_front()
+    }
+
+    /// Mark `toast` visible in the first of `slots` free on `monitor`, with its timer running
+    /// from `now`
+    /// Returns the new toast's label and slot, or `None` when every slot is taken or
+    /// `MAX_VISIBLE` toasts are already shown.
+    fn reserve(
+        &mut self,
+        toast: &Toast,
+        monitor: &str,
+        slots: usize,
+        now: Instant,
+    ) -> Option<(String, usize)> {
+        if self.visible.len() >= MAX_VISIBLE {
+            return None;
+        }
+        let slot = (0..slots).find(|slot| {
+            !self
+                .visible
+                .iter()
+                .any(|visible| visible.monitor == monitor && visible.slot == *slot)
+        })?;
+
+        self.next_id += 1;
+        let label = format!("{}{}", TOAST_WINDOW_PREFIX, self.next_id);
+        self.visible.push(VisibleToast {
+            label: label.clone(),
+            toast: toast.clone(),
+            monitor: monitor.to_string(),
+            slot,
+            remaining: TOAST_DURATION,
+            running_since: Some(now),
+            timer: 0,
+        });
+        Some((label, slot))
+    }
+
+    /// Forget a toast whose window is gone
+    fn remove(&mut self, label: &str) {
+        self.visible.retain(|visible| visible.label != label);
+    }
+
+    /// Stop the timer of a toast, keeping the time it has left
+    fn pause(&mut self, label: &str, now: Instant) {
+        if let Some(visible) = self.visible.iter_mut().find(|v| v.label == label) {
+            if let Some(since) = visible.running_since.take() {
+                visible.remaining = visible
+                    .remaining
+                    .saturating_sub(now.saturating_duration_since(since));
+                visible.timer += 1;
+            }
+        }
+    }
+
+    /// Restart the timer of a paused toast from `now`
+    /// Returns the new timer and how long it runs, or `None` if the toast is not paused.
+    fn resume(&mut self, label: &str, now: Instant) -> Option<(u64, Duration)> {
+        let visible = self.visible.iter_mut().find(|v| v.label == label)?;
+        if visible.running_since.is_some() {
+            return None;
+        }
+
+        visible.remaining = visible.remaining.max(MIN_DURATION_AFTER_HOVER);
+        visible.running_since = Some(now);
+        visible.timer += 1;
+        Some((visible.timer, visible.remaining))
+    }
+
+    /// Whether the toast's timer `timer` is still the one that should close it
+    fn is_current_timer(&self, label: &str, timer: u64) -> bool {
+        self.visible
+            .iter()
+            .any(|v| v.label == label && v.timer == timer && v.running_since.is_some())
+    }
+
+    /// Chat to open for a toast
+    fn chat_for(&self, label: &str) -> Option<ChatTarget> {
+        self.visible
+            .iter()
+            .find(|v| v.label == label)
+            .and_then(|v| v.toast.chat.clone())
+    }
+}
+
+/// Where a new toast goes
+struct Placement {
+    label: String,
+    position: (f64, f64),
+}
+
+/// Owns the toast windows: stacks them per monitor, limits how many are visible, queues the
+/// rest in order and closes each one when its timer runs out
+pub struct ToastManager {
+    queue: Mutex<ToastQueue>,
+}
+
+impl ToastManager {
+    /// Create a new ToastManager with no toasts
+    pub fn new() -> Self {
+        Self {
+            queue: Mutex::new(ToastQueue::default()),
+        }
+    }
+
+    /// Show a toast now, or queue it if the maximum number is already visible
+    /// The toast is a message alert, so the notification policy may drop it or hold it for the
+    /// quiet hours digest instead.
+    pub fn show(&self, app: &AppHandle, toast: Toast) -> Result<(), String> {
+        // Toasts announce incoming messages, which bring the sender up in the recent chats
+        if let Some(ChatTarget::Contact {
+            user_id,
+            display_name,
+        }) = &toast.chat
+        {
+            recent_chats::record_activity(app, user_id, display_name.as_deref());
+        }
+
+        let sender_id = match &toast.chat {
+            Some(chat @ (ChatTarget::Contact { .. } | ChatTarget::Bot { .. })) => {
+                Some(chat.id().to_string())
+            }
+            _ => toast.sender_id.clone(),
+        };
+        let conversation_id = match &toast.chat {
+            Some(ChatTarget::Conversation {
+                conversation_id, ..
+            }) => Some(conversation_id.clone()),
+            _ => toast.conversation_id.clone(),
+        };
+        let event = NotificationEvent {
+            kind: NotificationEventKind::Message,
+            sender_id,
+            conversation_id,
+            title: Some(toast.message.clone()),
+            body: Some(toast.description.clone()),
+        };
+        let requested = RequestedOutputs {
+            alert: true,
+            sound: false,
+        };
+        if !notification_policy::evaluate(app, &event, requested).desktop_alert {
+            return Ok(());
+        }
+
+        let monitor = current_monitor(app).map(|monitor| {
+            let area = chat_windows::logical_work_area(&monitor);
+            (monitor_key(&monitor), area)
+        });
+        let admission = self.queue.lock().unwrap().admit(
+            &toast,
+            monitor
+                .as_ref()
+                .map(|(key, area)| (key.as_str(), slot_count(area.3))),
+            Instant::now(),
+        );
+
+        match (admission, monitor) {
+            (Admission::Visible { label, slot }, Some((_, area))) => {
+                let position = slot_position(area, slot);
+                self.open(app, toast, Placement { label, position })
+            }
+            (Admission::Dropped, _) | (Admission::Visible { .. }, None) => {
+                Err("Failed to show toast: no monitor to show it on".to_string())
+            }
+            (Admission::Queued, _) => Ok(()),
+        }
+    }
+
+    /// Show queued toasts while there is room for them
+    fn show_pending(&self, app: &AppHandle) {
+        loop {
+            let Some(toast) = self.queue.lock().unwrap().next_pending() else {
+                return;
+            };
+
+            let Some(placement) = self.place(app, &toast) else {
+                let mut queue = self.queue.lock().unwrap();
+                if queue.visible.is_empty() {
+                    eprintln!("Dropping toast: no monitor to show it on");
+                    continue;
+                }
+                queue.pending.push_front(toast);
+                return;
+            };
+            if let Err(e) = self.open(app, toast, placement) {
+                eprintln!("{}", e);
+            }
+        }
+    }
+
+    /// Reserve the first free slot on the monitor the user is looking at
+    fn place(&self, app: &AppHandle, toast: &Toast) -> Option<Placement> {
+        let monitor = current_monitor(app)?;
+        let area = chat_windows::logical_work_area(&monitor);
+
+        let (label, slot) = self.queue.lock().unwrap().reserve(
+            toast,
+            &monitor_key(&monitor),
+            slot_count(area.3),
+            Instant::now(),
+        )?;
+        Some(Placement {
+            label,
+            position: slot_position(area, slot),
+        })
+    }
+
+    /// Create the window for a placed toast and start its timer
+    fn open(&self, app: &AppHandle, toast: Toast, placement: Placement) -> Result<(), String> {
+        let url = format!(
+            "notification.html?message={}&description={}",
+            urlencoding::encode(&toast.message),
+            urlencoding::encode(&toast.description)
+        );
+
+        let window =
+            WebviewWindowBuilder::new(app, &placement.label, tauri::WebviewUrl::App(url.into()))
+                .title("Notification")
+                .inner_size(TOAST_SIZE.0, TOAST_SIZE.1)
+                .position(placement.position.0, placement.position.1)
+                .decorations(false)
+                .transparent(true)
+                .always_on_top(true)
+                .skip_taskbar(true)
+                .resizable(false)
+                .focused(false)
+                .build();
+
+        let window = match window {
+            Ok(window) => window,
+            Err(e) => {
+                self.remove(&placement.label);
+                return Err(format!("Failed to show toast: {}", e));
+            }
+        };
+
+        let handle = app.clone();
+        let label = placement.label.clone();
+        window.on_window_event(move |event| {
+            if let WindowEvent::Destroyed = event {
+                let app = handle.clone();
+                let label = label.clone();
+                // Opening the next toast creates a window, which must not happen inside this handler
+                tauri::async_runtime::spawn(async move {
+                    let manager = app.state::<ToastManager>();
+                    manager.remove(&label);
+                    manager.show_pending(&app);
+                });
+            }
+        });
+
+        spawn_expiry(app.clone(), placement.label, 0, TOAST_DURATION);
+        Ok(())
+    }
+
+    /// Forget a toast whose window is gone
+    fn remove(&self, label: &str) {
+        self.queue.lock().unwrap().remove(label);
+    }
+
+    /// Stop the timer of a toast, e.g. while the pointer is over it
+    pub fn pause(&self, label: &str) {
+        self.queue.lock().unwrap().pause(label, Instant::now());
+    }
+
+    /// Restart the timer of a paused toast
+    pub fn resume(&self, app: &AppHandle, label: &str) {
+        let resumed = self.queue.lock().unwrap().resume(label, Instant::now());
+        if let Some((timer, delay)) = resumed {
+            spawn_expiry(app.clone(), label.to_string(), timer, delay);
+        }
+    }
+
+    /// Whether the toast's timer `timer` is still the one that should close it
+    fn is_current_timer(&self, label: &str, timer: u64) -> bool {
+        self.queue.lock().unwrap().is_current_timer(label, timer)
+    }
+
+    /// Chat to open for a toast
+    fn chat_for(&self, label: &str) -> Option<ChatTarget> {
+        self.queue.lock().unwrap().chat_for(label)
+    }
+}
+
+/// Close a toast after `delay`, unless its timer was paused or restarted in the meantime
+fn spawn_expiry(app: AppHandle, label: String, timer: u64, delay: Duration) {
+    tauri::async_runtime::spawn(async move {
+        tokio::time::sleep(delay).await;
+        if app.state::<ToastManager>().is_current_timer(&label, timer) {
+            if let Some(window) = app.get_webview_window(&label) {
+                let _ = window.close();
+            }
+        }
+    });
+}
+
+/// Monitor under the pointer, falling back to the primary monitor
+fn current_monitor(app: &AppHandle) -> Option<Monitor> {
+    app.cursor_position()
+        .ok()
+        .and_then(|cursor| app.monitor_from_point(cursor.x, cursor.y).ok().flatten())
+        .or_else(|| app.primary_monitor().ok().flatten())
+        .or_else(|| app.available_monitors().ok()?.into_iter().next())
+}
+
+/// Identifies a monitor for stacking
+fn monitor_key(monitor: &Monitor) -> String {
+    match monitor.name() {
+        Some(name) => name.clone(),
+        None => format!("{},{}", monitor.position().x, monitor.position().y),
+    }
+}
+
+/// Number of toasts that fit on top of each other in a work area `area_height` high, at least one
+fn slot_count(area_height: f64) -> usize {
+    ((area_height - 2.0 * SCREEN_MARGIN + TOAST_GAP) / (TOAST_SIZE.1 + TOAST_GAP))
+        .floor()
+        .max(1.0) as usize
+}
+
+/// Logical position of the toast in `slot` of a work area
+/// Toasts stack down from the top right on macOS, like native notifications, and up from the
+/// bottom right elsewhere.
+fn slot_position((x, y, width, height): (f64, f64, f64, f64), slot: usize) -> (f64, f64) {
+    let (toast_width, toast_height) = TOAST_SIZE;
+    let offset = slot as f64 * (toast_height + TOAST_GAP);
+
+    let left = x + width - toast_width - SCREEN_MARGIN;
+    if cfg!(target_os = "macos") {
+        (left, y + SCREEN_MARGIN + offset)
+    } else {
+        (left, y + height - toast_height - SCREEN_MARGIN - offset)
+    }
+}
+
+/// Show a toast notification, if the notification policy allows it
+/// Toasts beyond the maximum visible count wait in order until earlier ones close.
+#[tauri::command]
+pub async fn show_toast(
+    app: AppHandle,
+    manager: tauri::State<'_, ToastManager>,
+    toast: Toast,
+) -> Result<(), String> {
+    manager.show(&app, toast)
+}
+
+/// Pause the calling toast's timer while the pointer is over it
+#[tauri::command]
+pub fn pause_toast(manager: tauri::State<ToastManager>, window: tauri::WebviewWindow) {
+    manager.pause(window.label());
+}
+
+/// Resume the calling toast's timer when the pointer leaves it
+#[tauri::command]
+pub fn resume_toast(
+    app: AppHandle,
+    manager: tauri::State<ToastManager>,
+    window: tauri::WebviewWindow,
+) {
+    manager.resume(&app, window.label());
+}
+
+/// Close the calling toast and open the chat it is about
+#[tauri::command]
+pub async fn toast_clicked(
+    app: AppHandle,
+    manager: tauri::State<'_, ToastManager>,
+    window: tauri::WebviewWindow,
+) -> Result<(), String> {
+    let chat = manager.chat_for(window.label());
+    let _ = window.close();
+
+    if let Some(chat) = chat {
+        crate::show_chat_window(&app, &chat)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn toast(message: &str) -> Toast {
+        Toast {
+            message: message.to_string(),
+            description: String::new(),
+            chat: None,
+            sender_id: None,
+            conversation_id: None,
+        }
+    }
+
+    /// Offer a toast to the queue, returning its label if it is visible
+    fn show(queue: &mut ToastQueue, message: &str, monitor: &str) -> Option<String> {
+        match queue.admit(&toast(message), Some((monitor, 10)), Instant::now()) {
+            Admission::Visible { label, .. } => Some(label),
+            _ => None,
+        }
+    }
+
+    fn slots(queue: &ToastQueue) -> Vec<(&str, usize)> {
+        queue
+            .visible
+            .iter()
+            .map(|v| (v.monitor.as_str(), v.slot))
+            .collect()
+    }
+
+    #[test]
+    fn fills_the_lowest_free_slot_per_monitor() {
+        let mut queue = ToastQueue::default();
+        let now = Instant::now();
+        let first = queue.reserve(&toast("1"), "a", 5, now).unwrap();
+        queue.reserve(&toast("2"), "a", 5, now).unwrap();
+        queue.reserve(&toast("3"), "b", 5, now).unwrap();
+        assert_eq!(slots(&queue), vec![("a", 0), ("a", 1), ("b", 0)]);
+
+        queue.remove(&first.0);
+        assert_eq!(queue.reserve(&toast("4"), "a", 5, now).unwrap().1, 0);
+        assert_eq!(queue.reserve(&toast("5"), "a", 5, now).unwrap().1, 2);
+    }
+
+    #[test]
+    fn gives_every_toast_its_own_label() {
+        let mut queue = ToastQueue::default();
+        let now = Instant::now();
+        let (first, _) = queue.reserve(&toast("1"), "a", 5, now).unwrap();
+        queue.remove(&first);
+        let (second, _) = queue.reserve(&toast("2"), "a", 5, now).unwrap();
+
+        assert_ne!(first, second);
+        assert!(first.starts_with(TOAST_WINDOW_PREFIX));
+    }
+
+    #[test]
+    fn finds_no_slot_on_a_full_monitor() {
+        let mut queue = ToastQueue::default();
+        let now = Instant::now();
+        queue.reserve(&toast("1"), "a", 1, now).unwrap();
+
+        assert!(queue.reserve(&toast("2"), "a", 1, now).is_none());
+        assert_eq!(queue.visible.len(), 1);
+    }
+
+    #[test]
+    fn queues_toasts_past_the_visible_limit_in_order() {
+        let mut queue = ToastQueue::default();
+        let labels: Vec<Option<String>> = (0..MAX_VISIBLE + 2)
+            .map(|i| show(&mut queue, &i.to_string(), "a"))
+            .collect();
+        assert!(labels[..MAX_VISIBLE].iter().all(Option::is_some));
+        assert!(labels[MAX_VISIBLE..].iter().all(Option::is_none));
+
+        // No room yet
+        assert!(queue.next_pending().is_none());
+
+        queue.remove(labels[0].as_ref().unwrap());
+        assert_eq!(
+            queue.next_pending().unwrap().message,
+            MAX_VISIBLE.to_string()
+        );
+    }
+
+    #[test]
+    fn new_toasts_wait_behind_queued_ones() {
+        let mut queue = ToastQueue::default();
+        let labels: Vec<Option<String>> = (0..=MAX_VISIBLE)
+            .map(|i| show(&mut queue, &i.to_string(), "a"))
+            .collect();
+
+        // There is room again, but a toast is still waiting
+        queue.remove(labels[0].as_ref().unwrap());
+        assert!(show(&mut queue, "late", "a").is_none());
+
+        let next = queue.next_pending().unwrap();
+        queue.reserve(&next, "a", 10, Instant::now()).unwrap();
+        assert_eq!(next.message, MAX_VISIBLE.to_string());
+        assert!(queue.next_pending().is_none());
+        assert_eq!(queue.pending.front().unwrap().message, "late");
+    }
+
+    #[test]
+    fn never_reserves_past_the_visible_limit() {
+        let mut queue = ToastQueue::default();
+        let now = Instant::now();
+        for i in 0..MAX_VISIBLE {
+            queue.reserve(&toast(&i.to_string()), "a", 10, now).unwrap();
+        }
+
+        assert!(queue.reserve(&toast("over"), "b", 10, now).is_none());
+        assert_eq!(queue.visible.len(), MAX_VISIBLE);
+    }
+
+    #[test]
+    fn queues_toasts_when_the_monitor_is_full() {
+        let mut queue = ToastQueue::default();
+        let now = Instant::now();
+        assert!(matches!(
+            queue.admit(&toast("1"), Some(("a", 1)), now),
+            Admission::Visible { slot: 0, .. }
+        ));
+
+        assert_eq!(
+            queue.admit(&toast("2"), Some(("a", 1)), now),
+            Admission::Queued
+        );
+        assert_eq!(queue.pending.len(), 1);
+    }
+
+    #[test]
+    fn drops_toasts_with_nowhere_to_show_them() {
+        let mut queue = ToastQueue::default();
+        let now = Instant::now();
+        assert_eq!(queue.admit(&toast("1"), None, now), Admission::Dropped);
+        assert!(queue.pending.is_empty());
+
+        // With a toast visible, its closing shows the waiting one
+        queue.admit(&toast("2"), Some(("a", 5)), now);
+        assert_eq!(queue.admit(&toast("3"), None, now), Admission::Queued);
+    }
+
+    #[test]
+    fn pausing_keeps_the_time_left() {
+        let mut queue = ToastQueue::default();
+        let start = Instant::now();
+        let (label, _) = queue.reserve(&toast("1"), "a", 5, start).unwrap();
+        assert!(queue.is_current_timer(&label, 0));
+
+        queue.pause(&label, start + Duration::from_secs(2));
+        assert!(!queue.is_current_timer(&label, 0));
+        assert_eq!(
+            queue.visible[0].remaining,
+            TOAST_DURATION - Duration::from_secs(2)
+        );
+
+        // Pausing again does not count the paused time
+        queue.pause(&label, start + Duration::from_secs(4));
+        assert_eq!(
+            queue.visible[0].remaining,
+            TOAST_DURATION - Duration::from_secs(2)
+        );
+
+        let (timer, delay) = queue
+            .resume(&label, start + Duration::from_secs(10))
+            .unwrap();
+        assert_eq!(delay, TOAST_DURATION - Duration::from_secs(2));
+        assert!(queue.is_current_timer(&label, timer));
+        assert!(!queue.is_current_timer(&label, 0));
+    }
+
+    #[test]
+    fn resuming_leaves_a_minimum_time() {
+        let mut queue = ToastQueue::default();
+        let start = Instant::now();
+        let (label, _) = queue.reserve(&toast("1"), "a", 5, start).unwrap();
+
+        queue.pause(&label, start + TOAST_DURATION);
+        assert_eq!(queue.visible[0].remaining, Duration::ZERO);
+        let (_, delay) = queue.resume(&label, start + TOAST_DURATION).unwrap();
+        assert_eq!(delay, MIN_DURATION_AFTER_HOVER);
+    }
+
+    #[test]
+    fn resuming_a_running_or_closed_toast_does_nothing() {
+        let mut queue = ToastQueue::default();
+        let start = Instant::now();
+        let (label, _) = queue.reserve(&toast("1"), "a", 5, start).unwrap();
+
+        assert!(queue.resume(&label, start).is_none());
+        assert!(queue.is_current_timer(&label, 0));
+
+        queue.pause(&label, start);
+        queue.remove(&label);
+        assert!(queue.resume(&label, start).is_none());
+        assert!(!queue.is_current_timer(&label, 1));
+    }
+
+    #[test]
+    fn finds_the_chat_of_a_visible_toast() {
+        let mut queue = ToastQueue::default();
+        let chat = ChatTarget::Contact {
+            user_id: "user".to_string(),
+            display_name: None,
+        };
+        let mut with_chat = toast("1");
+        with_chat.chat = Some(chat.clone());
+        let (label, _) = queue.reserve(&with_chat, "a", 5, Instant::now()).unwrap();
+
+        assert_eq!(queue.chat_for(&label), Some(chat));
+        queue.remove(&label);
+        assert_eq!(queue.chat_for(&label), None);
+    }
+
+    #[test]
+    fn stacks_as_many_toasts_as_fit_the_work_area() {
+        assert_eq!(slot_count(2.0 * SCREEN_MARGIN + TOAST_SIZE.1), 1);
+        assert_eq!(
+            slot_count(2.0 * SCREEN_MARGIN + 3.0 * TOAST_SIZE.1 + 2.0 * TOAST_GAP),
+            3
+        );
+        assert_eq!(
+            slot_count(2.0 * SCREEN_MARGIN + 3.0 * TOAST_SIZE.1 + TOAST_GAP),
+            2
+        );
+        assert_eq!(slot_count(0.0), 1);
+    }
+
+    #[test]
+    fn stacks_toasts_from_the_corner() {
+        let area = (100.0, 50.0, 1000.0, 800.0);
+        let (x, first_y) = slot_position(area, 0);
+        let (_, second_y) = slot_position(area, 1);
+
+        assert_eq!(x, 100.0 + 1000.0 - TOAST_SIZE.0 - SCREEN_MARGIN);
+        if cfg!(target_os = "macos") {
+            assert_eq!(first_y, 50.0 + SCREEN_MARGIN);
+            assert_eq!(second_y, first_y + TOAST_SIZE.1 + TOAST_GAP);
+        } else {
+            assert_eq!(first_y, 50.0 + 800.0 - TOAST_SIZE.1 - SCREEN_MARGIN);
+            assert_eq!(second_y, first_y - TOAST_SIZE.1 - TOAST_GAP);
+        }
+    }
+}
@@ -0,0 +1,434 @@
+use crate::tray_icon::{self, PresenceBadge};
+use crate::AppState;
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use tauri::{
+    menu::{CheckMenuItem, Menu, MenuEvent, MenuItem, PredefinedMenuItem, Submenu},
+    tray::{MouseButton, MouseButtonState, TrayIconBuilder, TrayIconEvent},
+    AppHandle, Emitter, Manager,
+};
+
+/// Id of the app's tray icon
+pub const TRAY_ID: &str = "main";
+
+/// Shown in the tooltip when signed out
+const APP_NAME: &str = "Spirit Messenger";
+
+/// Prefix of the status menu item ids, followed by the status
+const STATUS_ITEM_PREFIX: &str = "status:";
+
+/// Prefix of the recent conversation menu item ids, followed by the contact's user id
+const RECENT_CHAT_ITEM_PREFIX: &str = "recent:";
+
+/// Presence status of the signed in user, matching `PresenceStatus` in the frontend
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresenceStatus {
+    Online,
+    Away,
+    Busy,
+    BeRightBack,
+    OnThePhone,
+    OutToLunch,
+    AppearOffline,
+    Offline,
+}
+
+impl PresenceStatus {
+    /// Statuses offered in the tray, in menu order
+    const TRAY_CHOICES: [Self; 5] = [
+        Self::Online,
+        Self::Busy,
+        Self::BeRightBack,
+        Self::Away,
+        Self::AppearOffline,
+    ];
+
+    /// Name used in menu ids and events
+    fn id(self) -> &'static str {
+        match self {
+            Self::Online => "online",
+            Self::Away => "away",
+            Self::Busy => "busy",
+            Self::BeRightBack => "be_right_back",
+            Self::OnThePhone => "on_the_phone",
+            Self::OutToLunch => "out_to_lunch",
+            Self::AppearOffline => "appear_offline",
+            Self::Offline => "offline",
+        }
+    }
+
+    /// Text shown to the user
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Online => "Online",
+            Self::Away => "Away",
+            Self::Busy => "Busy",
+            Self::BeRightBack => "Be Right Back",
+            Self::OnThePhone => "On The Phone",
+            Self::OutToLunch => "Out To Lunch",
+            Self::AppearOffline => "Appear Offline",
+            Self::Offline => "Offline",
+        }
+    }
+
+    fn from_id(id: &str) -> Option<Self> {
+        Self::TRAY_CHOICES
+            .into_iter()
+            .find(|status| status.id() == id)
+    }
+}
+
+/// Something the user picked in the tray menu, sent to the main window as `tray-action`
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum TrayAction {
+    /// `previous` is what the tray showed before, to go back to if the change fails
+    SetPresence {
+        status: PresenceStatus,
+        previous: Option<PresenceStatus>,
+    },
+    SignOut,
+    OpenOptions,
+}
+
+/// What the tray shows about the signed in user
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TrayState {
+    pub presence: Option<PresenceStatus>, // None while signed out
+    pub display_name: Option<String>,
+    pub personal_message: Option<String>,
+    pub unread_count: u32,
+}
+
+impl TrayState {
+    /// Tooltip with the user's name, status and personal message
+    fn tooltip(&self) -> String {
+        let Some(presence) = self.presence else {
+            return APP_NAME.to_string();
+        };
+
+        let mut lines = vec![match self.display_name.as_deref().map(str::trim) {
+            Some(name) if !name.is_empty() => format!("{} ({})", name, presence.label()),
+            _ => format!("{} ({})", APP_NAME, presence.label()),
+        }];
+        if let Some(message) = self.personal_message.as_deref().map(str::trim) {
+            if !message.is_empty() {
+                lines.push(message.to_string());
+            }
+        }
+        match self.unread_count {
+            0 => {}
+            1 => lines.push("1 unread message".to_string()),
+            count => lines.push(format!("{} unread messages", count)),
+        }
+        lines.join("\n")
+    }
+}
+
+/// Owns the tray icon and menu, and updates them whenever the state they show changes
+pub struct TrayManager {
+    state: Mutex<TrayState>,
+    icon: Mutex<Option<(PresenceBadge, bool)>>, // What the tray icon currently shows
+}
+
+impl TrayManager {
+    /// Create a new TrayManager for a signed out user
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(TrayState::default()),
+            icon: Mutex::new(None),
+        }
+    }
+
+    /// Replace everything the tray shows
+    pub fn set_state(&self, app: &AppHandle, state: TrayState) {
+        *self.state.lock().unwrap() = state;
+        self.refresh(app);
+    }
+
+    /// Update only the presence shown in the tray; returns the presence it showed before
+    pub fn set_presence(
+        &self,
+        app: &AppHandle,
+        presence: PresenceStatus,
+    ) -> Option<PresenceStatus> {
+        let previous = self.state.lock().unwrap().presence.replace(presence);
+        self.refresh(app);
+        previous
+    }
+
+    /// Update the tray menu, icon and tooltip from the current state
+    pub fn refresh(&self, app: &AppHandle) {
+        let Some(tray) = app.tray_by_id(TRAY_ID) else {
+            return;
+        };
+        let state = self.state.lock().unwrap().clone();
+
+        match self.build_menu(app) {
+            Ok(menu) => {
+                if let Err(e) = tray.set_menu(Some(menu)) {
+                    eprintln!("Failed to update tray menu: {}", e);
+                }
+            }
+            Err(e) => eprintln!("Failed to build tray menu: {}", e),
+        }
+
+        if let Err(e) = tray.set_tooltip(Some(state.tooltip())) {
+            eprintln!("Failed to update tray tooltip: {}", e);
+        }
+
+        // Only re-render the icon when what it shows has changed
+        let icon = (
+            PresenceBadge::for_presence(state.presence),
+            state.unread_count > 0,
+        );
+        let mut current = self.icon.lock().unwrap();
+        if *current != Some(icon) {
+            match tray_icon::render(icon.0, icon.1) {
+                Ok(image) => match tray.set_icon(Some(image)) {
+                    Ok(()) => *current = Some(icon),
+                    Err(e) => eprintln!("Failed to update tray icon: {}", e),
+                },
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+    }
+
+    fn build_menu(&self, app: &AppHandle) -> tauri::Result<Menu<tauri::Wry>> {
+        let presence = self.state.lock().unwrap().presence;
+        let signed_in = presence.is_some();
+
+        let status_items = PresenceStatus::TRAY_CHOICES
+            .into_iter()
+            .map(|status| {
+                CheckMenuItem::with_id(
+                    app,
+                    format!("{}{}", STATUS_ITEM_PREFIX, status.id()),
+                    status.label(),
+                    signed_in,
+                    presence == Some(status),
+                    None::<&str>,
+                )
+            })
+            .collect::<tauri::Result<Vec<_>>>()?;
+        let status_refs: Vec<&dyn tauri::menu::IsMenuItem<tauri::Wry>> = status_items
+            .iter()
+            .map(|item| item as &dyn tauri::menu::IsMenuItem<tauri::Wry>)
+            .collect();
+        let status = Submenu::with_items(app, "My Status", signed_in, &status_refs)?;
+
+        // Recent conversations, only while signed in since they belong to the signed in user
+        let recent_chats = if signed_in {
+            app.state::<AppState>().recent_chats().list()
+        } else {
+            Vec::new()
+        };
+        let recent_items = recent_chats
+            .iter()
+            .map(|chat| {
+                MenuItem::with_id(
+                    app,
+                    format!("{}{}", RECENT_CHAT_ITEM_PREFIX, chat.user_id),
+                    &chat.display_name,
+                    true,
+                    None::<&str>,
+                )
+            })
+            .collect::<tauri::Result<Vec<_>>>()?;
+        let recent_header =
+            MenuItem::with_id(app, "recent", "Recent Conversations", false, None::<&str>)?;
+        let recent_separator = PredefinedMenuItem::separator(app)?;
+
+        let options = MenuItem::with_id(app, "options", "Open Options", signed_in, None::<&str>)?;
+        let sign_out = MenuItem::with_id(app, "sign-out", "Sign Out", signed_in, None::<&str>)?;
+        let show = MenuItem::with_id(app, "show", "Show Window", true, None::<&str>)?;
+        let quit = MenuItem::with_id(app, "quit", "Quit", true, None::<&str>)?;
+
+        let mut items: Vec<&dyn tauri::menu::IsMenuItem<tauri::Wry>> = Vec::new();
+        if !recent_items.is_empty() {
+            items.push(&recent_header);
+            items.extend(
+                recent_items
+                    .iter()
+                    .map(|item| item as &dyn tauri::menu::IsMenuItem<tauri::Wry>),
+            );
+            items.push(&recent_separator);
+        }
+        let separators = [
+            PredefinedMenuItem::separator(app)?,
+            PredefinedMenuItem::separator(app)?,
+        ];
+        items.extend([
+            &status as &dyn tauri::menu::IsMenuItem<tauri::Wry>,
+            &separators[0],
+            &options,
+            &sign_out,
+            &separators[1],
+            &show,
+            &quit,
+        ]);
+
+        Menu::with_items(app, &items)
+    }
+}
+
+/// Create the tray icon and its menu
+pub fn build(app: &AppHandle) -> tauri::Result<()> {
+    let manager = app.state::<TrayManager>();
+    let menu = manager.build_menu(app)?;
+    let icon = tray_icon::render(PresenceBadge::Offline, false).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        app.default_window_icon().unwrap().clone()
+    });
+
+    TrayIconBuilder::with_id(TRAY_ID)
+        .icon(icon)
+        .tooltip(APP_NAME)
+        .menu(&menu)
+        .show_menu_on_left_click(false)
+        .on_menu_event(handle_menu_event)
+        .on_tray_icon_event(|tray, event| {
+            if let TrayIconEvent::Click {
+                button: MouseButton::Left,
+                button_state: MouseButtonState::Up,
+                ..
+            } = event
+            {
+                show_main_window(tray.app_handle());
+            }
+        })
+        .build(app)?;
+
+    *manager.icon.lock().unwrap() = Some((PresenceBadge::Offline, false));
+    Ok(())
+}
+
+fn handle_menu_event(app: &AppHandle, event: MenuEvent) {
+    let id = event.id.as_ref();
+    if let Some(user_id) = id.strip_prefix(RECENT_CHAT_ITEM_PREFIX) {
+        let chat = app
+            .state::<AppState>()
+            .recent_chats()
+            .list()
+            .into_iter()
+            .find(|chat| chat.user_id == user_id);
+        if let Some(chat) = chat {
+            if let Err(e) = crate::show_chat_window(app, &chat.target()) {
+                eprintln!("Failed to open recent chat: {}", e);
+            }
+        }
+        return;
+    }
+
+    let action = if let Some(status) = id
+        .strip_prefix(STATUS_ITEM_PREFIX)
+        .and_then(PresenceStatus::from_id)
+    {
+        // Show the new status right away; the frontend puts back the previous one if saving fails
+        let previous = app.state::<TrayManager>().set_presence(app, status);
+        Some(TrayAction::SetPresence { status, previous })
+    } else {
+        match id {
+            "options" => Some(TrayAction::OpenOptions),
+            "sign-out" => {
+                show_main_window(app);
+                Some(TrayAction::SignOut)
+            }
+            "show" => {
+                show_main_window(app);
+                None
+            }
+            "quit" => {
+                crate::shutdown::request(app, 0);
+                None
+            }
+            _ => None,
+        }
+    };
+
+    if let Some(action) = action {
+        if let Err(e) = app.emit_to("main", "tray-action", &action) {
+            eprintln!("Failed to emit tray-action event: {}", e);
+        }
+    }
+}
+
+fn show_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+/// Show the user's presence, name, personal message and unread count in the tray
+/// Pass no presence when signed out.
+#[tauri::command]
+pub fn set_tray_state(app: AppHandle, manager: tauri::State<TrayManager>, state: TrayState) {
+    manager.set_state(&app, state);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signed_in(presence: PresenceStatus) -> TrayState {
+        TrayState {
+            presence: Some(presence),
+            display_name: Some("Alice".to_string()),
+            ..TrayState::default()
+        }
+    }
+
+    #[test]
+    fn tooltip_shows_only_the_app_name_when_signed_out() {
+        let state = TrayState {
+            presence: None,
+            display_name: Some("Alice".to_string()),
+            unread_count: 3,
+            ..TrayState::default()
+        };
+        assert_eq!(state.tooltip(), APP_NAME);
+    }
+
+    #[test]
+    fn tooltip_shows_name_status_message_and_unread_count() {
+        let mut state = signed_in(PresenceStatus::BeRightBack);
+        assert_eq!(state.tooltip(), "Alice (Be Right Back)");
+
+        state.personal_message = Some("  back soon ".to_string());
+        state.unread_count = 1;
+        assert_eq!(
+            state.tooltip(),
+            "Alice (Be Right Back)\nback soon\n1 unread message"
+        );
+
+        state.unread_count = 4;
+        assert_eq!(
+            state.tooltip(),
+            "Alice (Be Right Back)\nback soon\n4 unread messages"
+        );
+    }
+
+    #[test]
+    fn tooltip_falls_back_to_the_app_name_and_skips_blank_messages() {
+        let state = TrayState {
+            display_name: Some("  ".to_string()),
+            personal_message: Some(" ".to_string()),
+            ..signed_in(PresenceStatus::Busy)
+        };
+        assert_eq!(state.tooltip(), format!("{} (Busy)", APP_NAME));
+    }
+
+    #[test]
+    fn only_tray_choices_are_parsed_from_menu_ids() {
+        for status in PresenceStatus::TRAY_CHOICES {
+            assert_eq!(PresenceStatus::from_id(status.id()), Some(status));
+        }
+        for id in ["on_the_phone", "out_to_lunch", "offline", "Online", ""] {
+            assert_eq!(PresenceStatus::from_id(id), None, "{:?}", id);
+        }
+    }
+}
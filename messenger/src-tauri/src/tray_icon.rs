@@ -0,0 +1,184 @@
+use crate::tray::PresenceStatus;
+use image::{imageops, RgbaImage};
+use tauri::image::Image;
+
+/// App icon the badges are drawn on
+const BASE_ICON: &[u8] = include_bytes!("../icons/32x32.png");
+
+const ONLINE_BADGE: &[u8] = include_bytes!("../icons/tray/online.png");
+const AWAY_BADGE: &[u8] = include_bytes!("../icons/tray/away.png");
+const BUSY_BADGE: &[u8] = include_bytes!("../icons/tray/busy.png");
+const OFFLINE_BADGE: &[u8] = include_bytes!("../icons/tray/offline.png");
+const UNREAD_OVERLAY: &[u8] = include_bytes!("../icons/tray/unread.png");
+
+/// Presence badge drawn in the bottom right corner of the tray icon
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PresenceBadge {
+    Online,
+    Away,
+    Busy,
+    Offline,
+}
+
+impl PresenceBadge {
+    /// Badge for a presence; signed out shows as offline
+    pub fn for_presence(presence: Option<PresenceStatus>) -> Self {
+        match presence {
+            Some(PresenceStatus::Online) => Self::Online,
+            Some(
+                PresenceStatus::Away
+                | PresenceStatus::BeRightBack
+                | PresenceStatus::OnThePhone
+                | PresenceStatus::OutToLunch,
+            ) => Self::Away,
+            Some(PresenceStatus::Busy) => Self::Busy,
+            Some(PresenceStatus::AppearOffline | PresenceStatus::Offline) | None => Self::Offline,
+        }
+    }
+
+    fn png(self) -> &'static [u8] {
+        match self {
+            Self::Online => ONLINE_BADGE,
+            Self::Away => AWAY_BADGE,
+            Self::Busy => BUSY_BADGE,
+            Self::Offline => OFFLINE_BADGE,
+        }
+    }
+}
+
+fn decode(png: &[u8]) -> Result<RgbaImage, String> {
+    image::load_from_memory_with_format(png, image::ImageFormat::Png)
+        .map(|image| image.to_rgba8())
+        .map_err(|e| format!("Failed to decode tray icon: {}", e))
+}
+
+/// Compose the tray icon from the app icon, a presence badge and the unread overlay
+pub fn render(badge: PresenceBadge, unread: bool) -> Result<Image<'static>, String> {
+    let mut icon = decode(BASE_ICON)?;
+    let (width, height) = icon.dimensions();
+
+    let badge = decode(badge.png())?;
+    imageops::overlay(
+        &mut icon,
+        &badge,
+        i64::from(width.saturating_sub(badge.width())),
+        i64::from(height.saturating_sub(badge.height())),
+    );
+
+    if unread {
+        let overlay = decode(UNREAD_OVERLAY)?;
+        imageops::overlay(
+            &mut icon,
+            &overlay,
+            i64::from(width.saturating_sub(overlay.width())),
+            0,
+        );
+    }
+
+    Ok(Image::new_owned(icon.into_raw(), width, height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BADGES: [PresenceBadge; 4] = [
+        PresenceBadge::Online,
+        PresenceBadge::Away,
+        PresenceBadge::Busy,
+        PresenceBadge::Offline,
+    ];
+
+    /// Pixels of an icon that differ from `other`, as (x, y)
+    fn changed_pixels(icon: &Image<'_>, other: &[u8]) -> Vec<(u32, u32)> {
+        icon.rgba()
+            .chunks(4)
+            .zip(other.chunks(4))
+            .enumerate()
+            .filter(|(_, (a, b))| a != b)
+            .map(|(i, _)| (i as u32 % icon.width(), i as u32 / icon.width()))
+            .collect()
+    }
+
+    #[test]
+    fn maps_presence_to_badges() {
+        assert_eq!(
+            PresenceBadge::for_presence(Some(PresenceStatus::Online)),
+            PresenceBadge::Online
+        );
+        for presence in [
+            PresenceStatus::Away,
+            PresenceStatus::BeRightBack,
+            PresenceStatus::OnThePhone,
+            PresenceStatus::OutToLunch,
+        ] {
+            assert_eq!(
+                PresenceBadge::for_presence(Some(presence)),
+                PresenceBadge::Away
+            );
+        }
+        assert_eq!(
+            PresenceBadge::for_presence(Some(PresenceStatus::Busy)),
+            PresenceBadge::Busy
+        );
+        assert_eq!(
+            PresenceBadge::for_presence(Some(PresenceStatus::AppearOffline)),
+            PresenceBadge::Offline
+        );
+        assert_eq!(PresenceBadge::for_presence(None), PresenceBadge::Offline);
+    }
+
+    #[test]
+    fn keeps_the_app_icon_size() {
+        let base = decode(BASE_ICON).unwrap();
+        for badge in BADGES {
+            for unread in [false, true] {
+                let icon = render(badge, unread).unwrap();
+                assert_eq!((icon.width(), icon.height()), base.dimensions());
+            }
+        }
+    }
+
+    #[test]
+    fn draws_the_badge_in_the_bottom_right_corner() {
+        let base = decode(BASE_ICON).unwrap();
+        for badge in BADGES {
+            let icon = render(badge, false).unwrap();
+            let changed = changed_pixels(&icon, base.as_raw());
+            assert!(!changed.is_empty(), "{:?}", badge);
+            assert!(
+                changed.iter().all(|&(x, y)| x >= 32 - 14 && y >= 32 - 14),
+                "{:?}",
+                badge
+            );
+        }
+    }
+
+    #[test]
+    fn each_presence_has_its_own_badge() {
+        let icons: Vec<Vec<u8>> = BADGES
+            .iter()
+            .map(|badge| render(*badge, false).unwrap().rgba().to_vec())
+            .collect();
+        for (i, a) in icons.iter().enumerate() {
+            for b in &icons[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn draws_the_unread_overlay_in_the_top_right_corner() {
+        for badge in BADGES {
+            let read = render(badge, false).unwrap();
+            let unread = render(badge, true).unwrap();
+            let changed = changed_pixels(&unread, read.rgba());
+            assert!(!changed.is_empty(), "{:?}", badge);
+            assert!(
+                changed.iter().all(|&(x, y)| x >= 32 - 12 && y < 12),
+                "{:?}",
+                badge
+            );
+        }
+    }
+}
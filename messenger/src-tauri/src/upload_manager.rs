@@ -0,0 +1,789 @@
+use crate::backend;
+use crate::file_access::FileAccessBroker;
+use futures_util::StreamExt;
+use reqwest::header::AUTHORIZATION;
+use reqwest::multipart::{Form, Part};
+use reqwest::{Body, Client, StatusCode};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter};
+use tokio::sync::watch;
+use tokio_util::io::ReaderStream;
+
+/// Event emitted with the percentage of a file sent so far
+pub const PROGRESS_EVENT: &str = "file-upload-progress";
+
+/// Event emitted once an upload succeeded, failed or was cancelled
+pub const COMPLETE_EVENT: &str = "file-upload-complete";
+
+/// Largest file that can be sent
+pub const MAX_UPLOAD_BYTES: u64 = 100 * 1024 * 1024;
+
+/// Attempts made at an upload before giving up on transient failures
+const MAX_ATTEMPTS: u32 = 4;
+
+/// Wait before the first retry, doubled before each next one
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Time between two progress events for the same upload
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Transfer requests expire a day after they are offered, so their files are not sent later
+const QUEUED_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The backend stores the whole file before answering, so allow it some time
+const READ_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Why a file could not be sent
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum UploadError {
+    /// No file was queued for the transfer
+    NotQueued { transfer_id: String },
+    /// The same transfer is already uploading
+    AlreadyUploading { transfer_id: String },
+    /// The upload was cancelled
+    Cancelled,
+    /// The file could not be read
+    Read { path: String, message: String },
+    /// The file is larger than `MAX_UPLOAD_BYTES`
+    TooLarge { limit: u64 },
+    /// The server answered with an error status
+    Http { status: u16, message: String },
+    /// The server could not be reached or the connection failed
+    Network { message: String },
+}
+
+impl UploadError {
+    /// Whether trying again later may succeed
+    fn is_transient(&self) -> bool {
+        match self {
+            Self::Network { .. } => true,
+            Self::Http { status, .. } => {
+                *status >= 500
+                    || *status == StatusCode::REQUEST_TIMEOUT.as_u16()
+                    || *status == StatusCode::TOO_MANY_REQUESTS.as_u16()
+            }
+            _ => false,
+        }
+    }
+}
+
+impl fmt::Display for UploadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotQueued { transfer_id } => {
+                write!(f, "No file queued for transfer {}", transfer_id)
+            }
+            Self::AlreadyUploading { transfer_id } => {
+                write!(f, "Transfer {} is already uploading", transfer_id)
+            }
+            Self::Cancelled => write!(f, "Upload cancelled"),
+            Self::Read { path, message } => write!(f, "Failed to read {}: {}", path, message),
+            Self::TooLarge { limit } => {
+                write!(
+                    f,
+                    "File is larger than the {} MB limit",
+                    limit / 1024 / 1024
+                )
+            }
+            Self::Http { status, message } => {
+                write!(f, "Upload failed with status {}: {}", status, message)
+            }
+            Self::Network { message } => write!(f, "Upload failed: {}", message),
+        }
+    }
+}
+
+impl From<UploadError> for String {
+    fn from(error: UploadError) -> Self {
+        error.to_string()
+    }
+}
+
+/// A file to send, described the way the transfer request needs it
+#[derive(Debug, Clone)]
+pub struct SelectedFile {
+    pub path: String,
+    pub name: String,
+    pub size: u64,
+    pub mime_type: String,
+}
+
+impl SelectedFile {
+    /// Describe the file at `path`
+    /// The name and type are derived from the path alone, so describing the same file again
+    /// when uploading gives what the transfer request was created with.
+    pub fn describe(path: &Path) -> Result<Self, UploadError> {
+        let metadata = fs::metadata(path).map_err(|e| read_error(path, &e))?;
+        if !metadata.is_file() {
+            return Err(UploadError::Read {
+                path: path.to_string_lossy().to_string(),
+                message: "Not a file".to_string(),
+            });
+        }
+
+        Ok(Self {
+            path: path.to_string_lossy().to_string(),
+            name: path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            size: metadata.len(),
+            mime_type: mime_guess::from_path(path)
+                .first_or_octet_stream()
+                .essence_str()
+                .to_string(),
+        })
+    }
+}
+
+/// Payload of the `file-upload-progress` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadProgress<'a> {
+    transfer_id: &'a str,
+    /// Percentage sent, 0 to 100
+    progress: u32,
+}
+
+/// Payload of the `file-upload-complete` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct UploadComplete<'a> {
+    transfer_id: &'a str,
+    success: bool,
+    error: Option<String>,
+}
+
+/// A file waiting for its transfer to be accepted
+struct QueuedFile {
+    file: SelectedFile,
+    queued_at: Instant,
+}
+
+/// Sends files straight from disk to the backend
+/// Files are queued for a transfer when it is offered and uploaded once it is accepted. A file
+/// stays queued after a failed upload so it can be retried, until the upload succeeds, is
+/// cancelled, the transfer is forgotten or it expires. Each running upload can be cancelled by
+/// its transfer ID.
+pub struct UploadManager {
+    client: Client,
+    queued: Mutex<HashMap<String, QueuedFile>>,
+    active: Mutex<HashMap<String, watch::Sender<bool>>>,
+}
+
+impl UploadManager {
+    /// Create a new UploadManager with no running uploads
+    pub fn new() -> Self {
+        let client = Client::builder()
+            .connect_timeout(CONNECT_TIMEOUT)
+            .read_timeout(READ_TIMEOUT)
+            .build()
+            .unwrap_or_else(|e| {
+                eprintln!("Failed to configure upload client: {}", e);
+                Client::new()
+            });
+
+        Self {
+            client,
+            queued: Mutex::new(HashMap::new()),
+            active: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Queue `file` to be sent for `transfer_id`, dropping files whose transfer expired
+    fn queue(&self, transfer_id: String, file: SelectedFile, now: Instant) {
+        let mut queued = self.queued.lock().unwrap();
+        queued.retain(|_, entry| now.duration_since(entry.queued_at) < QUEUED_TTL);
+        queued.insert(
+            transfer_id,
+            QueuedFile {
+                file,
+                queued_at: now,
+            },
+        );
+    }
+
+    /// The file queued for `transfer_id`
+    fn queued(&self, transfer_id: &str) -> Result<SelectedFile, UploadError> {
+        self.queued
+            .lock()
+            .unwrap()
+            .get(transfer_id)
+            .map(|entry| entry.file.clone())
+            .ok_or_else(|| UploadError::NotQueued {
+                transfer_id: transfer_id.to_string(),
+            })
+    }
+
+    /// Stop sending a file for `transfer_id`; returns whether one was queued
+    /// Used when the transfer is declined, expires or is cancelled before it was accepted.
+    pub fn forget(&self, transfer_id: &str) -> bool {
+        self.queued.lock().unwrap().remove(transfer_id).is_some()
+    }
+
+    /// Mark `transfer_id` as uploading and return the receiver its cancellation arrives on
+    fn begin(&self, transfer_id: &str) -> Result<watch::Receiver<bool>, UploadError> {
+        let mut active = self.active.lock().unwrap();
+        if active.contains_key(transfer_id) {
+            return Err(UploadError::AlreadyUploading {
+                transfer_id: transfer_id.to_string(),
+            });
+        }
+
+        let (sender, receiver) = watch::channel(false);
+        active.insert(transfer_id.to_string(), sender);
+        Ok(receiver)
+    }
+
+    /// Mark `transfer_id` as no longer uploading
+    /// The file stays queued after a failure so the upload can be tried again.
+    fn finish<T>(&self, transfer_id: &str, result: &Result<T, UploadError>) {
+        self.active.lock().unwrap().remove(transfer_id);
+        if matches!(result, Ok(_) | Err(UploadError::Cancelled)) {
+            self.forget(transfer_id);
+        }
+    }
+
+    /// Cancel the upload of `transfer_id`; returns whether it was running
+    pub fn cancel(&self, transfer_id: &str) -> bool {
+        match self.active.lock().unwrap().get(transfer_id) {
+            Some(sender) => {
+                sender.send_replace(true);
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Upload `file` for `transfer_id` as a multipart form to `url`, retrying transient failures
+/// Waits 1, 2, then 4 seconds between attempts. `sent` counts the bytes read from disk for the
+/// current attempt. Returns the `data` of the backend's response.
+///
+/// Does not depend on the app, so it can run against any HTTP server.
+pub async fn upload_with_retry(
+    client: &Client,
+    url: &str,
+    auth_token: Option<&str>,
+    transfer_id: &str,
+    file: &SelectedFile,
+    cancel: &mut watch::Receiver<bool>,
+    sent: &Arc<AtomicU64>,
+) -> Result<serde_json::Value, UploadError> {
+    let mut attempt = 1;
+    let mut backoff = INITIAL_BACKOFF;
+
+    loop {
+        let result = tokio::select! {
+            result = upload_once(client, url, auth_token, transfer_id, file, sent) => result,
+            Ok(_) = cancel.wait_for(|cancelled| *cancelled) => return Err(UploadError::Cancelled),
+        };
+
+        match result {
+            Err(e) if e.is_transient() && attempt < MAX_ATTEMPTS => {
+                eprintln!(
+                    "Upload of transfer {} failed (attempt {} of {}), retrying in {:?}: {}",
+                    transfer_id, attempt, MAX_ATTEMPTS, backoff, e
+                );
+                tokio::select! {
+                    _ = tokio::time::sleep(backoff) => {}
+                    Ok(_) = cancel.wait_for(|cancelled| *cancelled) => {
+                        return Err(UploadError::Cancelled);
+                    }
+                }
+                attempt += 1;
+                backoff *= 2;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Upload `file` once, streaming it from disk
+async fn upload_once(
+    client: &Client,
+    url: &str,
+    auth_token: Option<&str>,
+    transfer_id: &str,
+    file: &SelectedFile,
+    sent: &Arc<AtomicU64>,
+) -> Result<serde_json::Value, UploadError> {
+    let path = Path::new(&file.path);
+    let reader = tokio::fs::File::open(path)
+        .await
+        .map_err(|e| read_error(path, &e))?;
+    let size = reader
+        .metadata()
+        .await
+        .map_err(|e| read_error(path, &e))?
+        .len();
+    if size > MAX_UPLOAD_BYTES {
+        return Err(UploadError::TooLarge {
+            limit: MAX_UPLOAD_BYTES,
+        });
+    }
+
+    sent.store(0, Ordering::Relaxed);
+    let counter = Arc::clone(sent);
+    let stream = ReaderStream::new(reader).inspect(move |chunk| {
+        if let Ok(chunk) = chunk {
+            counter.fetch_add(chunk.len() as u64, Ordering::Relaxed);
+        }
+    });
+
+    let part = Part::stream_with_length(Body::wrap_stream(stream), size)
+        .file_name(file.name.clone())
+        .mime_str(&file.mime_type)
+        .map_err(|e| UploadError::Read {
+            path: file.path.clone(),
+            message: e.to_string(),
+        })?;
+    // The backend only sees fields sent before the file
+    let form = Form::new()
+        .text("transfer_id", transfer_id.to_string())
+        .part("file", part);
+
+    let mut request = client.post(url).multipart(form);
+    if let Some(token) = auth_token {
+        request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+    }
+    let response = request.send().await.map_err(network_error)?;
+
+    let status = response.status();
+    let body = response.text().await.map_err(network_error)?;
+    let body: serde_json::Value = serde_json::from_str(&body).unwrap_or_default();
+
+    if status.is_success() {
+        Ok(body.get("data").cloned().unwrap_or_default())
+    } else {
+        Err(UploadError::Http {
+            status: status.as_u16(),
+            message: body
+                .get("error")
+                .and_then(|error| error.as_str())
+                .unwrap_or_else(|| status.canonical_reason().unwrap_or("Unknown error"))
+                .to_string(),
+        })
+    }
+}
+
+fn read_error(path: &Path, error: &std::io::Error) -> UploadError {
+    UploadError::Read {
+        path: path.to_string_lossy().to_string(),
+        message: error.to_string(),
+    }
+}
+
+fn network_error(error: reqwest::Error) -> UploadError {
+    UploadError::Network {
+        message: error.to_string(),
+    }
+}
+
+fn emit<S: Serialize + Clone>(app: &AppHandle, event: &str, payload: S) {
+    if let Err(e) = app.emit(event, payload) {
+        eprintln!("Failed to emit {} event: {}", event, e);
+    }
+}
+
+/// Queue the file behind `grant_id` to be sent for `transfer_id` once it is accepted
+/// The grant is used up; the file is read from disk only when uploading.
+#[tauri::command]
+pub fn queue_upload(
+    broker: tauri::State<FileAccessBroker>,
+    manager: tauri::State<UploadManager>,
+    transfer_id: String,
+    grant_id: String,
+) -> Result<(), String> {
+    let path = broker.resolve(&grant_id, "upload")?;
+    broker.revoke(&grant_id);
+
+    let file = SelectedFile::describe(&path)?;
+    manager.queue(transfer_id, file, Instant::now());
+    Ok(())
+}
+
+/// Send the file queued for an accepted transfer, streaming it from disk to the backend
+/// Progress is reported with `file-upload-progress` events and the outcome with a
+/// `file-upload-complete` event. Transient failures are retried with backoff. Returns the
+/// backend's response data.
+#[tauri::command]
+pub async fn upload_file(
+    app: AppHandle,
+    manager: tauri::State<'_, UploadManager>,
+    transfer_id: String,
+    auth_token: Option<String>,
+) -> Result<serde_json::Value, UploadError> {
+    let file = manager.queued(&transfer_id)?;
+    let mut cancel = manager.begin(&transfer_id)?;
+    let sent = Arc::new(AtomicU64::new(0));
+    // Only ever sent to the configured backend, along with the user's token
+    let url = backend::api_url("/api/files/upload");
+
+    let mut upload = std::pin::pin!(upload_with_retry(
+        &manager.client,
+        &url,
+        auth_token.as_deref(),
+        &transfer_id,
+        &file,
+        &mut cancel,
+        &sent,
+    ));
+    let mut ticks = tokio::time::interval(PROGRESS_INTERVAL);
+    let result = loop {
+        tokio::select! {
+            result = &mut upload => break result,
+            _ = ticks.tick() => {
+                let progress = match file.size {
+                    0 => 0,
+                    size => (sent.load(Ordering::Relaxed).min(size) * 100 / size) as u32,
+                };
+                emit(
+                    &app,
+                    PROGRESS_EVENT,
+                    UploadProgress {
+                        transfer_id: &transfer_id,
+                        progress,
+                    },
+                );
+            }
+        }
+    };
+    manager.finish(&transfer_id, &result);
+
+    if let Err(e) = &result {
+        eprintln!("Upload of transfer {} stopped: {}", transfer_id, e);
+    }
+    emit(
+        &app,
+        COMPLETE_EVENT,
+        UploadComplete {
+            transfer_id: &transfer_id,
+            success: result.is_ok(),
+            error: result.as_ref().err().map(|e| e.to_string()),
+        },
+    );
+
+    result
+}
+
+/// Cancel a running upload; returns whether it was running
+#[tauri::command]
+pub fn cancel_upload(manager: tauri::State<UploadManager>, transfer_id: String) -> bool {
+    manager.cancel(&transfer_id)
+}
+
+/// Drop the file queued for a transfer that was declined, expired or cancelled before it was
+/// accepted; returns whether one was queued
+#[tauri::command]
+pub fn forget_upload(manager: tauri::State<UploadManager>, transfer_id: String) -> bool {
+    manager.forget(&transfer_id)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+    use tokio::time::Instant;
+
+    /// Serve one reply per request, in order, on a local port
+    /// `None` closes the connection without answering. Returns the URL and the bodies received.
+    fn serve(replies: Vec<Option<(u16, &'static str)>>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/api/files/upload", listener.local_addr().unwrap());
+        let bodies = Arc::new(Mutex::new(Vec::new()));
+
+        let received = bodies.clone();
+        thread::spawn(move || {
+            for (stream, reply) in listener.incoming().zip(replies) {
+                let mut stream = stream.unwrap();
+                let body = read_request(&mut stream);
+                received.lock().unwrap().push(body);
+
+                if let Some((status, body)) = reply {
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.1 {} X\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{}",
+                        status,
+                        body.len(),
+                        body
+                    );
+                }
+            }
+        });
+
+        (url, bodies)
+    }
+
+    /// Read a request with a Content-Length or chunked body; returns the body
+    fn read_request(stream: &mut std::net::TcpStream) -> String {
+        let mut reader = BufReader::new(stream);
+        let mut length = None;
+        let mut chunked = false;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            let line = line.trim().to_lowercase();
+            if line.is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("content-length:") {
+                length = value.trim().parse::<usize>().ok();
+            }
+            chunked |= line == "transfer-encoding: chunked";
+        }
+
+        let mut body = Vec::new();
+        if let Some(length) = length {
+            body.resize(length, 0);
+            reader.read_exact(&mut body).unwrap();
+        } else if chunked {
+            loop {
+                let mut size = String::new();
+                reader.read_line(&mut size).unwrap();
+                let size = usize::from_str_radix(size.trim(), 16).unwrap();
+                let mut chunk = vec![0; size + 2];
+                reader.read_exact(&mut chunk).unwrap();
+                body.extend_from_slice(&chunk[..size]);
+                if size == 0 {
+                    break;
+                }
+            }
+        }
+        String::from_utf8_lossy(&body).to_string()
+    }
+
+    /// A file to upload, kept alive by the returned directory
+    fn selected_file() -> (tempfile::TempDir, SelectedFile) {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hello.txt");
+        fs::write(&path, "hello world").unwrap();
+        let file = SelectedFile::describe(&path).unwrap();
+        (dir, file)
+    }
+
+    /// Without idle connections no pool timer runs, so the paused clock only moves for backoff
+    fn client() -> Client {
+        Client::builder().pool_max_idle_per_host(0).build().unwrap()
+    }
+
+    async fn upload(
+        url: &str,
+        file: &SelectedFile,
+        cancel: &mut watch::Receiver<bool>,
+    ) -> Result<serde_json::Value, UploadError> {
+        let sent = Arc::new(AtomicU64::new(0));
+        upload_with_retry(
+            &client(),
+            url,
+            Some("token"),
+            "transfer-1",
+            file,
+            cancel,
+            &sent,
+        )
+        .await
+    }
+
+    const OK: &str = r#"{"success":true,"data":{"file":{"id":"f1"}}}"#;
+
+    #[tokio::test(start_paused = true)]
+    async fn sends_the_transfer_id_before_the_file() {
+        let (url, bodies) = serve(vec![Some((200, OK))]);
+        let (_dir, file) = selected_file();
+        let (_cancel, mut cancelled) = watch::channel(false);
+
+        let data = upload(&url, &file, &mut cancelled).await.unwrap();
+
+        assert_eq!(data["file"]["id"], "f1");
+        let bodies = bodies.lock().unwrap();
+        assert_eq!(bodies.len(), 1);
+        let transfer_id = bodies[0].find("name=\"transfer_id\"").unwrap();
+        let file_part = bodies[0].find("filename=\"hello.txt\"").unwrap();
+        assert!(transfer_id < file_part);
+        assert!(bodies[0].contains("hello world"));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_server_errors_with_backoff() {
+        let (url, bodies) = serve(vec![
+            Some((500, "")),
+            Some((429, "")),
+            Some((503, "")),
+            Some((200, OK)),
+        ]);
+        let (_dir, file) = selected_file();
+        let (_cancel, mut cancelled) = watch::channel(false);
+
+        let started = Instant::now();
+        assert!(upload(&url, &file, &mut cancelled).await.is_ok());
+
+        // Waited 1, 2 and 4 seconds between the attempts
+        assert_eq!(started.elapsed(), Duration::from_secs(7));
+        assert_eq!(bodies.lock().unwrap().len(), 4);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn retries_network_errors() {
+        let (url, bodies) = serve(vec![None, Some((200, OK))]);
+        let (_dir, file) = selected_file();
+        let (_cancel, mut cancelled) = watch::channel(false);
+
+        let started = Instant::now();
+        assert!(upload(&url, &file, &mut cancelled).await.is_ok());
+
+        assert_eq!(started.elapsed(), Duration::from_secs(1));
+        assert_eq!(bodies.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_after_the_last_attempt() {
+        let (url, bodies) = serve(vec![Some((502, "")); 5]);
+        let (_dir, file) = selected_file();
+        let (_cancel, mut cancelled) = watch::channel(false);
+
+        let result = upload(&url, &file, &mut cancelled).await;
+
+        assert!(matches!(result, Err(UploadError::Http { status: 502, .. })));
+        assert_eq!(bodies.lock().unwrap().len(), MAX_ATTEMPTS as usize);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn does_not_retry_client_errors() {
+        let (url, bodies) = serve(vec![
+            Some((
+                400,
+                r#"{"success":false,"error":"File size does not match"}"#,
+            )),
+            Some((200, OK)),
+        ]);
+        let (_dir, file) = selected_file();
+        let (_cancel, mut cancelled) = watch::channel(false);
+
+        let started = Instant::now();
+        let result = upload(&url, &file, &mut cancelled).await;
+
+        match result {
+            Err(UploadError::Http { status, message }) => {
+                assert_eq!(status, 400);
+                assert_eq!(message, "File size does not match");
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert_eq!(started.elapsed(), Duration::ZERO);
+        assert_eq!(bodies.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancelling_stops_the_backoff() {
+        let (url, bodies) = serve(vec![Some((500, "")), Some((200, OK))]);
+        let (_dir, file) = selected_file();
+        let (cancel, mut cancelled) = watch::channel(false);
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(500)).await;
+            cancel.send_replace(true);
+        });
+
+        let started = Instant::now();
+        let result = upload(&url, &file, &mut cancelled).await;
+
+        assert!(matches!(result, Err(UploadError::Cancelled)));
+        assert_eq!(started.elapsed(), Duration::from_millis(500));
+        assert_eq!(bodies.lock().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn failed_uploads_stay_queued_for_a_retry() {
+        let (_dir, file) = selected_file();
+        let manager = UploadManager::new();
+        manager.queue("transfer-1".to_string(), file, std::time::Instant::now());
+
+        manager.begin("transfer-1").unwrap();
+        let failed: Result<(), UploadError> = Err(UploadError::Network {
+            message: String::new(),
+        });
+        manager.finish("transfer-1", &failed);
+
+        assert!(manager.queued("transfer-1").is_ok());
+        assert!(manager.begin("transfer-1").is_ok());
+    }
+
+    #[test]
+    fn sent_or_cancelled_uploads_are_dropped() {
+        let (_dir, file) = selected_file();
+        let manager = UploadManager::new();
+        manager.queue("sent".to_string(), file.clone(), std::time::Instant::now());
+        manager.queue("cancelled".to_string(), file, std::time::Instant::now());
+
+        manager.finish("sent", &Ok(()));
+        manager.finish::<()>("cancelled", &Err(UploadError::Cancelled));
+
+        assert!(matches!(
+            manager.queued("sent"),
+            Err(UploadError::NotQueued { .. })
+        ));
+        assert!(matches!(
+            manager.queued("cancelled"),
+            Err(UploadError::NotQueued { .. })
+        ));
+    }
+
+    #[test]
+    fn declined_transfers_can_be_forgotten() {
+        let (_dir, file) = selected_file();
+        let manager = UploadManager::new();
+        manager.queue("transfer-1".to_string(), file, std::time::Instant::now());
+
+        assert!(manager.forget("transfer-1"));
+        assert!(!manager.forget("transfer-1"));
+        assert!(manager.queued("transfer-1").is_err());
+    }
+
+    #[test]
+    fn transfers_never_accepted_expire() {
+        let (_dir, file) = selected_file();
+        let manager = UploadManager::new();
+        let start = std::time::Instant::now();
+        manager.queue("old".to_string(), file.clone(), start);
+        manager.queue("recent".to_string(), file.clone(), start + QUEUED_TTL / 2);
+
+        manager.queue("new".to_string(), file, start + QUEUED_TTL);
+
+        assert!(manager.queued("old").is_err());
+        assert!(manager.queued("recent").is_ok());
+        assert!(manager.queued("new").is_ok());
+    }
+
+    #[test]
+    fn only_transient_failures_are_retried() {
+        let http = |status| UploadError::Http {
+            status,
+            message: String::new(),
+        };
+        for status in [408, 429, 500, 502, 503, 504] {
+            assert!(http(status).is_transient(), "{}", status);
+        }
+        for status in [400, 401, 403, 404, 413] {
+            assert!(!http(status).is_transient(), "{}", status);
+        }
+        assert!(UploadError::Network {
+            message: String::new()
+        }
+        .is_transient());
+        assert!(!UploadError::Cancelled.is_transient());
+        assert!(!UploadError::TooLarge { limit: 0 }.is_transient());
+    }
+}
@@ -1,4 +1,4 @@
-import { Bot, PresenceStatus } from '@/types';
+import { Bot, ChatTarget, PresenceStatus } from '@/types';
 import { invoke } from '@tauri-apps/api/core';
 import AIAvatar from './ai-avatar';
 
@@ -24,10 +24,12 @@ export function BotItem({ bot, hasUnread }: BotItemProps) {
     };
 
     const handleDoubleClick = async () => {
-        await invoke("open_chat_window", {
-            dialogWindow: bot.id,
-            contactName: bot.displayName || bot.username,
-        });
+        const target: ChatTarget = {
+            type: 'bot',
+            botId: bot.id,
+            displayName: bot.displayName || bot.username,
+        };
+        await invoke("open_chat_window", { target });
     }
 
     return (
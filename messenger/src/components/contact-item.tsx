@@ -1,5 +1,5 @@
 import { useState, useRef, useEffect } from 'react';
-import { Contact, PresenceStatus } from '@/types';
+import { ChatTarget, Contact, PresenceStatus } from '@/types';
 import { invoke } from '@tauri-apps/api/core';
 import { createWindow } from '@/lib/utils/window-utils';
 import { useBlockContact, useUnblockContact } from '@/lib/hooks/contact-hooks';
@@ -52,10 +52,12 @@ export function ContactItem({ contact, hasUnread = false }: ContactItemProps) {
     };
 
     const handleDoubleClick = async () => {
-        await invoke("open_chat_window", {
-            dialogWindow: contactUser.id,
-            contactName: contactUser.displayName || contactUser.username,
-        });
+        const target: ChatTarget = {
+            type: 'contact',
+            userId: contactUser.id,
+            displayName: contactUser.displayName || contactUser.username,
+        };
+        await invoke("open_chat_window", { target });
     }
 
     const handleContextMenu = (e: React.MouseEvent) => {
@@ -9,8 +9,6 @@ import { useUnreadCounts } from '@/lib/hooks/message-hooks';
 import { ContactRequestNotification } from './contact-request-notification';
 import { ContactGroupHeader } from './contact-group-header';
 import { getCurrentWindow } from '@tauri-apps/api/window';
-import { listen } from '@tauri-apps/api/event';
-import { invoke } from '@tauri-apps/api/core';
 import { WINDOW_EVENTS } from '@/lib/utils/constants';
 import {
     DndContext,
@@ -129,34 +127,6 @@ export function ContactList() {
         }
     }, [refetchGroups])
 
-    useEffect(() => {
-        // Listen for notification clicks to open chat windows
-        const unsubscribe = listen<{ senderId: string }>('chat-notification-clicked', async (event) => {
-            const { senderId } = event.payload;
-
-            // Find the contact to get their display name
-            const contact = acceptedContacts?.find(c => c.contactUser?.id === senderId);
-            let senderName = ''
-            if (contact) {
-                senderName = contact?.contactUser?.displayName || contact?.contactUser?.email
-            } else {
-                // Retrieve from cached bots
-                const bot = bots?.find(b => b.id === senderId);
-                senderName = bot?.displayName || bot?.email || ''
-            }
-
-            // Open chat window with the sender
-            await invoke('open_chat_window', {
-                dialogWindow: senderId,
-                contactName: senderName,
-            });
-        });
-
-        return () => {
-            unsubscribe.then(fn => fn()).catch(err => console.error(err));
-        };
-    }, [acceptedContacts, bots])
-
     const toggleGroup = (groupId: string) => {
         setCollapsedGroups((prev) => {
             const next = new Set(prev);
@@ -4,6 +4,7 @@ import { useAcceptFileTransfer, useCancelFileTransfer, useDeclineFileTransfer, u
 import { KeyboardEventHandler, useEffect, useState } from 'react';
 import { listen } from '@tauri-apps/api/event';
 import { useFileUploadStore, FileUploadProgressPayload, FileUploadCompletePayload } from '@/lib/store/file-upload-store';
+import { cancelDownload, cancelUpload, FileTransferProgressPayload, forgetUpload } from '@/lib/services/file-service';
 
 interface FileTransferRequestProps {
     message: MessageWithSender
@@ -36,6 +37,7 @@ export function FileTransferRequestMessage({
 
     const [uploadProgress, setUploadProgress] = useState<number>(0);
     const [uploadStatus, setUploadStatus] = useState<'idle' | 'uploading' | 'completed' | 'failed'>('idle');
+    const [downloadProgress, setDownloadProgress] = useState<number | null>(null);
 
     const isSender = message.senderId === user?.id;
 
@@ -88,9 +90,23 @@ export function FileTransferRequestMessage({
                 }
             );
 
+            const unlistenDownload = await listen<FileTransferProgressPayload>(
+                'file-transfer-progress',
+                (event) => {
+                    if (event.payload.transferId !== transferRequest.id) return;
+                    if (event.payload.state !== 'downloading') {
+                        setDownloadProgress(null);
+                        return;
+                    }
+                    const total = event.payload.totalBytes || transferRequest.fileSize;
+                    setDownloadProgress(total ? Math.floor((event.payload.receivedBytes / total) * 100) : 0);
+                }
+            );
+
             return () => {
                 unlistenProgress();
                 unlistenComplete();
+                unlistenDownload();
             };
         };
 
@@ -111,6 +127,20 @@ export function FileTransferRequestMessage({
         }
     }, [transferRequest?.status, isSender, uploadStatus, emitFileUploadStart, transferRequest?.id]);
 
+    // A transfer that ends before it was accepted no longer needs its file queued
+    useEffect(() => {
+        const status = transferRequest?.status;
+        if (
+            transferRequest?.id &&
+            isSender &&
+            (status === 'declined' || status === 'expired' || status === 'cancelled')
+        ) {
+            forgetUpload(transferRequest.id).catch((error) => {
+                console.error('Failed to forget queued upload:', error);
+            });
+        }
+    }, [transferRequest?.status, isSender, transferRequest?.id]);
+
     const formatFileSize = (bytes?: number): string => {
         if (!bytes) return `0 B`
         if (bytes < 1024) return `${bytes} B`;
@@ -160,16 +190,29 @@ export function FileTransferRequestMessage({
         try {
             await downloadFileMutation.mutateAsync({
                 transferId: transferRequest.id,
-                filename: transferRequest.filename
+                filename: transferRequest.filename,
+                contactName: message.sender?.displayName || message.sender?.username
             });
         } catch (error) {
             console.error('Failed to download file:', error);
         }
     };
 
+    const handleCancelDownload = async () => {
+        if (!transferRequest) return;
+        try {
+            await cancelDownload(transferRequest.id);
+        } catch (error) {
+            console.error('Failed to cancel download:', error);
+        }
+    };
+
     const handleCancelTransfer = async () => {
         if (!transferRequest) return;
         try {
+            if (isSender) {
+                await cancelUpload(transferRequest.id);
+            }
             await cancelTransferMutation.mutateAsync(transferRequest.id);
         } catch (error) {
             console.error('Failed to cancel file transfer:', error);
@@ -330,6 +373,26 @@ export function FileTransferRequestMessage({
                     </>
                 );
             case 'completed':
+                if (downloadProgress !== null) {
+                    return (
+                        <>
+                            <ProgressBar
+                                progress={downloadProgress}
+                                statusText={`Saving... ${downloadProgress}%`}
+                            />
+                            <ActionLink
+                                onKeyDown={e => {
+                                    if (e.altKey && e.key === 'q') {
+                                        handleCancelDownload()
+                                    }
+                                }}
+                                onClick={handleCancelDownload}
+                                label="Cancel"
+                                shortcut="Alt+Q"
+                            />
+                        </>
+                    );
+                }
                 return (
                     <ActionLink
                         onKeyDown={e => {
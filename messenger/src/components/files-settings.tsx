@@ -52,6 +52,10 @@ export function FilesSettings() {
         }
     };
 
+    const handlePerContactFoldersChange = (enabled: boolean) => {
+        updateFileSettings({ perContactFolders: enabled });
+    };
+
     const handleOpenFolder = async () => {
         try {
             const pathToOpen = fileSettings.downloadLocation || displayPath;
@@ -103,6 +107,16 @@ export function FilesSettings() {
                 </button>
             </div>
 
+            <div className="field-row ml-6">
+                <input
+                    type="checkbox"
+                    id="perContactFolders"
+                    checked={fileSettings.perContactFolders}
+                    onChange={(e) => handlePerContactFoldersChange(e.target.checked)}
+                />
+                <label htmlFor="perContactFolders">Save files from each contact in a separate folder</label>
+            </div>
+
             <div className="ml-6 text-sm mt-2">
                 <p className="text-gray-600">
                     Files will be saved to your selected location. If no location is specified,
@@ -1,6 +1,13 @@
 import { useNotificationSettings, useSettingsActions, useStartupSettings } from "@/lib";
 import { invoke } from "@tauri-apps/api/core";
 import { useEffect } from "react";
+import type { CloseBehavior } from "@/types";
+
+const closeBehaviors: { value: CloseBehavior; label: string }[] = [
+    { value: 'minimizeToTray', label: 'Minimize to system tray' },
+    { value: 'quit', label: 'Quit Spirit Messenger' },
+    { value: 'ask', label: 'Ask me' },
+];
 
 /**
  * General Settings Component
@@ -33,6 +40,10 @@ export function GeneralSettings() {
         updateStartupSettings({ startMinimized: enabled });
     };
 
+    const handleCloseBehaviorChange = (closeBehavior: CloseBehavior) => {
+        updateStartupSettings({ closeBehavior });
+    };
+
     const handleNotificationsEnabledChange = (enabled: boolean) => {
         updateNotificationSettings({ enabled });
     };
@@ -67,6 +78,27 @@ export function GeneralSettings() {
                 <label htmlFor="startMinimized">Start minimized to system tray</label>
             </div>
 
+            {/* Close Button Section */}
+            <div className="flex items-center whitespace-nowrap gap-1 mt-4">
+                <div style={{ fontFamily: 'Pixelated MS Sans Serif' }}>
+                    When I close the main window
+                </div>
+                <div className="w-full h-[1px] bg-gray-400" />
+            </div>
+
+            {closeBehaviors.map(({ value, label }) => (
+                <div key={value} className="field-row ml-6">
+                    <input
+                        type="radio"
+                        id={`closeBehavior-${value}`}
+                        name="closeBehavior"
+                        checked={startupSettings.closeBehavior === value}
+                        onChange={() => handleCloseBehaviorChange(value)}
+                    />
+                    <label htmlFor={`closeBehavior-${value}`}>{label}</label>
+                </div>
+            ))}
+
             {/* Notifications Section */}
             <div className="flex items-center whitespace-nowrap gap-1 mt-4">
                 <div style={{ fontFamily: 'Pixelated MS Sans Serif' }}>
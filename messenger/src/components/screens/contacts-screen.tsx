@@ -7,7 +7,17 @@ import { PresenceStatus } from '@/types';
 import { ContactsTabs } from '../contacts-tabs';
 import { createWindow } from '@/lib/utils/window-utils';
 import { AIChat } from '../ai-chat';
-import { useUser } from '@/lib';
+import { useSignOut, useUser } from '@/lib';
+import { useSetPresenceStatus } from '@/lib/hooks/presence-hooks';
+import { useUnreadCounts } from '@/lib/hooks/message-hooks';
+import { getCurrentWindow } from '@tauri-apps/api/window';
+import { invoke } from '@tauri-apps/api/core';
+
+// Actions picked in the tray menu, matching TrayAction in src-tauri/src/tray.rs
+type TrayAction =
+    | { action: 'setPresence'; status: PresenceStatus; previous: PresenceStatus | null }
+    | { action: 'signOut' }
+    | { action: 'openOptions' };
 
 const tabs = [
     { icon: '/msn-person.png', color: 'fill-blue-400', label: 'Contacts' },
@@ -32,6 +42,64 @@ export function ContactsScreen() {
         setPresenceStatus(status);
     }
 
+    const setPresenceMutation = useSetPresenceStatus();
+    const signOutMutation = useSignOut();
+
+    const { data: unreadCounts } = useUnreadCounts();
+    const unreadCount = Object.values(unreadCounts || {}).reduce((total, count) => total + count, 0);
+
+    // Keep the tray's icon, tooltip and status check mark in sync
+    useEffect(() => {
+        invoke('set_tray_state', {
+            state: {
+                presence: presenceStatus,
+                displayName: user?.displayName ?? null,
+                personalMessage: user?.personalMessage ?? null,
+                unreadCount,
+            },
+        }).catch(err => console.error(err));
+    }, [presenceStatus, user?.displayName, user?.personalMessage, unreadCount]);
+
+    // Handle status changes, sign out and options picked from the tray menu
+    useEffect(() => {
+        const unlisten = getCurrentWindow().listen<TrayAction>('tray-action', async (event) => {
+            switch (event.payload.action) {
+                case 'setPresence': {
+                    const { status, previous } = event.payload;
+                    setPresenceStatus(status);
+                    try {
+                        await setPresenceMutation.mutateAsync(status);
+                    } catch (error) {
+                        console.error('Failed to update presence status:', error);
+                        // Put back the status the tray showed, which also restores the tray
+                        if (previous) {
+                            setPresenceStatus(previous);
+                        }
+                    }
+                    break;
+                }
+                case 'signOut':
+                    await signOutMutation.mutateAsync();
+                    break;
+                case 'openOptions':
+                    createWindow('options', '/options.html?option=profile', {
+                        title: 'Options',
+                        width: 480,
+                        height: 600,
+                        resizable: false,
+                        decorations: false,
+                        transparent: true,
+                        center: true,
+                    });
+                    break;
+            }
+        });
+
+        return () => {
+            unlisten.then((fn) => fn());
+        };
+    }, []);
+
     const handleAddContact = () => {
         createWindow('add-contact', '/add-contact.html', {
             title: 'Add a Contact',
@@ -22,6 +22,10 @@ export function SoundsSettings() {
         updateNotificationSettings({ desktopAlerts: enabled });
     };
 
+    const handleShakeOnNudgeChange = (enabled: boolean) => {
+        updateNotificationSettings({ shakeOnNudge: enabled });
+    };
+
     const handlePreviewSound = async (soundType: 'message' | 'contact_online' | 'contact_offline' | 'nudge') => {
         try {
             await soundService.previewSound(soundType);
@@ -75,6 +79,16 @@ export function SoundsSettings() {
                 <label htmlFor="desktopAlerts">Show desktop notifications</label>
             </div>
 
+            <div className="field-row ml-6">
+                <input
+                    type="checkbox"
+                    id="shakeOnNudge"
+                    checked={notificationSettings.shakeOnNudge}
+                    onChange={(e) => handleShakeOnNudgeChange(e.target.checked)}
+                />
+                <label htmlFor="shakeOnNudge">Shake the conversation window when I receive a nudge</label>
+            </div>
+
             {/* Sound Preview Section */}
             <div className="flex items-center whitespace-nowrap gap-1 mt-4">
                 <div style={{ fontFamily: 'Pixelated MS Sans Serif' }}>
@@ -1,14 +1,27 @@
 import { TitleBar } from "../title-bar";
 import { useSendContactRequest } from "@/lib/hooks/contact-hooks";
-import { useState } from "react";
+import { useEffect, useState } from "react";
 import { getCurrentWindow } from "@tauri-apps/api/window";
 
 export function AddContactWindow() {
-    const [email, setEmail] = useState('');
+    // spirit://add-contact links prefill the email, either on open or while the window is open
+    const [email, setEmail] = useState(() => new URLSearchParams(window.location.search).get('email') ?? '');
     const [successMessage, setSuccessMessage] = useState<string | null>(null);
     const sendContactMutation = useSendContactRequest();
     const [sectionIndex, setSectionIndex] = useState(0)
 
+    useEffect(() => {
+        const unlisten = getCurrentWindow().listen<{ email: string }>('deep-link-add-contact', (event) => {
+            setEmail(event.payload.email);
+            setSuccessMessage(null);
+            setSectionIndex(0);
+        });
+
+        return () => {
+            unlisten.then((fn) => fn());
+        };
+    }, []);
+
     const validateEmail = (email: string): boolean => {
         const emailRegex = /^[^\s@]+@[^\s@]+\.[^\s@]+$/;
         return emailRegex.test(email);
@@ -1,9 +1,10 @@
-import { useState, useRef, useEffect, useMemo, useCallback } from "react";
+import { useState, useRef, useEffect, useMemo } from "react";
 import { User } from "@/types";
 import { TitleBar } from "../title-bar";
 import { useUser } from "@/lib";
-import { useSendMessage, useConversationMessagesInfinite, useConversationRealtimeUpdates, useSendNudge, useMarkMessagesAsRead } from "@/lib/hooks/message-hooks";
-import { getCurrentWindow, PhysicalPosition } from '@tauri-apps/api/window';
+import { useSendMessage, useConversationMessagesInfinite, useConversationRealtimeUpdates, useSendNudge, useMarkMessagesAsRead, useConversation as useConversationById } from "@/lib/hooks/message-hooks";
+import { getCurrentWindow } from '@tauri-apps/api/window';
+import { invoke } from '@tauri-apps/api/core';
 import { emit, Event, listen } from '@tauri-apps/api/event';
 import { useTypingIndicator } from "@/lib/hooks/typing-hooks";
 import { useConversation, useParticipantRealtimeUpdates } from "@/lib/hooks/conversation-hooks";
@@ -14,8 +15,9 @@ import { TextFormatter } from "../text-formatter";
 import { Emoticon, findEmoticonMatches } from "@/lib/emoticons";
 import { FileTransferRequestMessage } from "../file-transfer-request-message";
 import { useInitiateFileTransfer } from "@/lib/hooks/file-hooks";
-import { createFileInput, validateFile } from "@/lib/utils/file-utils";
-import { useFileUploadStore, fileToArrayBuffer } from "@/lib/store/file-upload-store";
+import { openFileDialog, validateFile } from "@/lib/utils/file-utils";
+import type { GrantedFile } from "@/lib/services/file-service";
+import { useFileUploadStore } from "@/lib/store/file-upload-store";
 import { HandwritingCanvas } from "../handwriting-canvas";
 import { useBlockContact, useContacts } from "@/lib/hooks/contact-hooks";
 import { WINDOW_EVENTS } from "@/lib/utils/constants";
@@ -34,17 +36,20 @@ import { WinkOverlay } from "../wink-overlay";
 import { useWinkStore } from "@/lib/store/wink-store";
 
 export function ChatWindow() {
-    // Extract contactUserId and contactName from URL query parameters
+    // Extract the chat target from URL query parameters (see ChatTarget)
     const params = new URLSearchParams(window.location.search);
     const contactUserId = params.get('contactUserId');
+    const conversationId = params.get('conversationId');
     const contactName = params.get('contactName');
 
-    // Find or create conversation with the contact
+    // Load the group conversation, or find or create the conversation with the contact
+    const oneOnOneConversation = useConversation(conversationId ? null : contactUserId);
+    const groupConversation = useConversationById(conversationId || '');
     const {
         data: conversation,
         isLoading: isLoadingConversation,
         error: conversationError,
-    } = useConversation(contactUserId);
+    } = conversationId ? groupConversation : oneOnOneConversation;
 
     const { data: user } = useUser();
     const [messageInput, setMessageInput] = useState("");
@@ -80,7 +85,6 @@ export function ChatWindow() {
     const { data: iceServers } = useIceServers();
 
     const sendMessageMutation = useSendMessage(conversation?.id || '');
-    const sendNudgeMutation = useSendNudge(conversation?.id || '');
     const markAsReadMutation = useMarkMessagesAsRead();
     const blockContactMutation = useBlockContact();
 
@@ -129,39 +133,6 @@ export function ChatWindow() {
         return messagesData.some(m => m.senderId !== user.id && !m.readAt);
     }, [isWindowFocused, user, messagesData]);
 
-    // Shake window when nudge is received
-    const handleNudgeReceived = useCallback(async () => {
-        const window = getCurrentWindow();
-        const position = await window.outerPosition();
-        const originalX = position.x;
-        const originalY = position.y;
-
-        const shakeIntensity = 10;
-        const shakeDuration = 500;
-        const shakeInterval = 50;
-
-        const iterations = shakeDuration / shakeInterval;
-        let count = 0;
-
-        const interval = setInterval(async () => {
-            if (count >= iterations) {
-                clearInterval(interval);
-                await window.setPosition(new PhysicalPosition(originalX, originalY));
-                return;
-            }
-
-            const offsetX = (Math.random() - 0.5) * shakeIntensity * 2;
-            const offsetY = (Math.random() - 0.5) * shakeIntensity * 2;
-
-            await window.setPosition(new PhysicalPosition(
-                Math.round(originalX + offsetX),
-                Math.round(originalY + offsetY)
-            ));
-
-            count++;
-        }, shakeInterval);
-    }, []);
-
     useConversationRealtimeUpdates(
         conversation?.id,
         (insertedMessage) => {
@@ -176,24 +147,6 @@ export function ChatWindow() {
         }
     );
 
-    // Listen for nudge events from the global message listener
-    useEffect(() => {
-        if (!conversation?.id) return;
-
-        const unlisten = listen<{ conversationId: string; senderId: string }>(
-            WINDOW_EVENTS.NUDGE_RECEIVED,
-            (event) => {
-                if (event.payload.conversationId === conversation.id) {
-                    handleNudgeReceived();
-                }
-            }
-        );
-
-        return () => {
-            unlisten.then((fn) => fn());
-        };
-    }, [conversation?.id, handleNudgeReceived]);
-
     // Typing indicator hook
     const { typingUsers, setTyping } = useTypingIndicator(conversation?.id || null);
 
@@ -203,6 +156,9 @@ export function ChatWindow() {
 
     useParticipantRealtimeUpdates(participants, conversation?.id);
 
+    const participantIds = useMemo(() => participants.map(p => p.id), [participants]);
+    const sendNudgeMutation = useSendNudge(conversation?.id || '', participantIds);
+
     // Check if any participant is blocked based on contacts list
     const { data: blockedContacts, refetch: fetchBlockedContacts } = useContacts('blocked');
 
@@ -640,14 +596,16 @@ export function ChatWindow() {
         setShowEmoticonPicker(false);
     };
 
-    const handleSendFileClick = () => {
-        createFileInput((file) => {
-            const validation = validateFile(file);
-            if (!validation.valid) {
-                return;
-            }
-            handleInitiateFileTransfer(file);
-        });
+    const handleSendFileClick = async () => {
+        const file = await openFileDialog();
+        if (!file) {
+            return;
+        }
+        const validation = validateFile(file);
+        if (!validation.valid) {
+            return;
+        }
+        handleInitiateFileTransfer(file);
     };
 
     const handleAudioClick = async () => {
@@ -685,7 +643,7 @@ export function ChatWindow() {
         }
     };
 
-    const handleInitiateFileTransfer = async (file: File) => {
+    const handleInitiateFileTransfer = async (file: GrantedFile) => {
         if (!conversation?.id || !user) return;
 
         // For one-on-one conversations, get the receiver ID
@@ -700,21 +658,18 @@ export function ChatWindow() {
                 receiverId,
                 filename: file.name,
                 fileSize: file.size,
-                mimeType: file.type || 'application/octet-stream',
+                mimeType: file.mimeType,
             });
 
-            // Convert file to array buffer for cross-window transfer
-            const fileData = await fileToArrayBuffer(file);
-
-            // Emit event to main window to store the file for upload
+            // Emit event to main window to upload the file from disk once accepted
             await emitFileTransferInitiated({
                 transferId: result.transferRequest.id,
                 conversationId: conversation.id,
                 receiverId,
                 filename: file.name,
                 fileSize: file.size,
-                mimeType: file.type || 'application/octet-stream',
-                fileData,
+                mimeType: file.mimeType,
+                grantId: file.grantId,
             });
 
         } catch (error) {
@@ -11,7 +11,9 @@ import { Loading } from "../loading";
 import { useFileUploadStore } from "@/lib/store/file-upload-store";
 import { useCallUpdates } from "@/lib/hooks/call-hooks";
 import { PresenceStatus } from "@/types";
-import { useAuthStore } from "@/lib";
+import { setOfflineOnExit, useAuthStore } from "@/lib";
+import { onAppShutdown } from "@/lib/utils/shutdown-utils";
+import { invoke } from "@tauri-apps/api/core";
 
 type AuthView = 'signin' | 'register' | 'main';
 
@@ -37,9 +39,24 @@ export function MainWindow() {
             setCurrentView('main');
         } else if (isAuthInitialized && !isAuthenticated) {
             setCurrentView('signin');
+            // Signed out: the tray has no status, name or unread messages to show
+            invoke('set_tray_state', { state: { presence: null } }).catch(err => console.error(err));
         }
     }, [isAuthInitialized, isAuthenticated]);
 
+    // Go offline before the app quits, instead of waiting for the backend to notice
+    useEffect(() => {
+        let cleanup: (() => void) | undefined;
+
+        onAppShutdown('presence', setOfflineOnExit).then((cleanupFn) => {
+            cleanup = cleanupFn;
+        });
+
+        return () => {
+            cleanup?.();
+        };
+    }, []);
+
     // Initialize file upload manager for main window
     useEffect(() => {
         let cleanup: (() => void) | undefined;
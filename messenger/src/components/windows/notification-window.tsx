@@ -1,45 +1,14 @@
-import { useEffect } from "react";
+import { invoke } from "@tauri-apps/api/core";
 import { getCurrentWindow } from "@tauri-apps/api/window";
-import { moveWindow, Position } from "@tauri-apps/plugin-positioner";
-import { platform } from "@tauri-apps/plugin-os";
 
+// Placement, auto-close and click routing are handled by the toast manager in Rust
 export function NotificationWindow() {
     const params = new URLSearchParams(window.location.search);
     const message = params.get('message') || 'Unknown';
     const description = params.get('description') || ''
-    const senderId = params.get('senderId')
-
-    useEffect(() => {
-        // Position window based on platform
-        // macOS: top right (like native notifications)
-        // Windows/Linux: bottom right
-        const positionWindow = async () => {
-            try {
-                const os = platform();
-                const position = os === 'macos' ? Position.TopRight : Position.BottomRight;
-                await moveWindow(position);
-            } catch (error) {
-                console.error('Failed to position notification window:', error);
-            }
-        };
-        positionWindow();
-
-        // Auto-close after 5 seconds
-        const timeout = setTimeout(() => {
-            const appWindow = getCurrentWindow();
-            appWindow.close();
-        }, 5000);
-
-        return () => clearTimeout(timeout);
-    }, []);
 
     const handleClick = async () => {
-        const appWindow = getCurrentWindow();
-        // Emit event to open chat window with this sender
-        if (senderId) {
-            await appWindow.emit('chat-notification-clicked', { senderId });
-        }
-        appWindow.close();
+        await invoke('toast_clicked');
     };
 
     const handleClose = (e: React.MouseEvent) => {
@@ -48,6 +17,15 @@ export function NotificationWindow() {
         appWindow.close();
     };
 
+    // Keep the toast up while the pointer is over it
+    const handleMouseEnter = () => {
+        invoke('pause_toast').catch(err => console.error(err));
+    };
+
+    const handleMouseLeave = () => {
+        invoke('resume_toast').catch(err => console.error(err));
+    };
+
     return (
         <div
             className="w-screen h-screen flex flex-col cursor-pointer"
@@ -55,6 +33,8 @@ export function NotificationWindow() {
                 background: "linear-gradient(#c9d9f1, #f6f6f6 50%, #c9d9f1)"
             }}
             onClick={handleClick}
+            onMouseEnter={handleMouseEnter}
+            onMouseLeave={handleMouseLeave}
         >
             <div className="flex flex-col flex-1 z-40 h-full">
                 <div
@@ -0,0 +1,62 @@
+import { useEffect, useState } from "react";
+import { invoke } from "@tauri-apps/api/core";
+import { TitleBar } from "../title-bar";
+
+interface ProfileInfo {
+    name: string | null; // null for the default profile
+    isActive: boolean;
+}
+
+/**
+ * Profile picker shown on startup when several profiles exist
+ * Choosing a profile other than the active one relaunches the app into it
+ */
+export function ProfilePickerWindow() {
+    const [profiles, setProfiles] = useState<ProfileInfo[]>([]);
+    const [selected, setSelected] = useState<string | null>(null);
+    const [error, setError] = useState<string | null>(null);
+
+    useEffect(() => {
+        invoke<ProfileInfo[]>('list_profiles')
+            .then((profiles) => {
+                setProfiles(profiles);
+                const active = profiles.find((p) => p.isActive);
+                setSelected(active?.name ?? null);
+            })
+            .catch((error) => setError(String(error)));
+    }, []);
+
+    const handleOpen = async () => {
+        try {
+            await invoke('switch_profile', { name: selected });
+        } catch (error) {
+            setError(String(error));
+        }
+    };
+
+    return (
+        <div className="window w-full h-screen flex flex-col">
+            <TitleBar title="Choose a profile" />
+            <div className="window-body flex-1 overflow-auto !my-[0px] !mx-[3px]">
+                <div className="p-4 flex flex-col gap-4">
+                    <ul className="tree-view flex-1 overflow-auto">
+                        {profiles.map((profile) => (
+                            <li
+                                key={profile.name ?? ''}
+                                className={`cursor-pointer ${selected === profile.name ? 'font-bold' : ''}`}
+                                onClick={() => setSelected(profile.name)}
+                                onDoubleClick={handleOpen}
+                            >
+                                {profile.name ?? 'Default'}
+                            </li>
+                        ))}
+                    </ul>
+                    {error && <div className="text-red-600">{error}</div>}
+                    <div className="flex justify-center gap-2">
+                        <button onClick={handleOpen}>Open</button>
+                    </div>
+                </div>
+            </div>
+        </div>
+    );
+}
@@ -104,13 +104,7 @@ export function useUploadFile() {
     const queryClient = useQueryClient();
 
     return useMutation({
-        mutationFn: ({
-            request,
-            onProgress,
-        }: {
-            request: UploadFileRequest;
-            onProgress?: (progress: number) => void;
-        }) => fileService.uploadFile(request, onProgress),
+        mutationFn: (request: UploadFileRequest) => fileService.uploadFile(request),
         onSuccess: (data) => {
             // Invalidate messages query to update transfer status
             queryClient.invalidateQueries({
@@ -128,8 +122,8 @@ export function useUploadFile() {
  */
 export function useDownloadFile() {
     return useMutation({
-        mutationFn: ({ transferId, filename }: { transferId: string; filename: string }) =>
-            fileService.downloadFile(transferId, filename),
+        mutationFn: ({ transferId, filename, contactName }: { transferId: string; filename: string; contactName?: string }) =>
+            fileService.downloadFile(transferId, filename, contactName),
         onError: (error) => {
             console.error('Failed to download file:', error);
         },
@@ -6,7 +6,7 @@
 import { useEffect } from 'react';
 import { useMutation, useQuery, useQueryClient, useInfiniteQuery } from '@tanstack/react-query';
 import { supabase } from '../supabase';
-import { emit } from '@tauri-apps/api/event';
+import { invoke } from '@tauri-apps/api/core';
 import { WebviewWindow } from '@tauri-apps/api/webviewWindow';
 import {
     sendMessage,
@@ -24,8 +24,7 @@ import {
 } from '../services/message-service';
 import { soundService } from '../services/sound-service';
 import { showNotificationWindow } from '../utils/window-utils';
-import type { User, Contact, Bot, MessageType } from '@/types';
-import { WINDOW_EVENTS } from '../utils/constants';
+import type { User, Contact, Bot, ChatTarget, MessageType } from '@/types';
 import { useUser } from './auth-hooks';
 
 /**
@@ -352,13 +351,29 @@ export function useConversationRealtimeUpdates(
 
 /**
  * Hook for sending a nudge to a conversation
+ * @param contactIds - User IDs of the other participants, whose nudge limits the nudge counts towards
  */
-export function useSendNudge(conversationId: string) {
+export function useSendNudge(conversationId: string, contactIds: string[]) {
     const queryClient = useQueryClient();
 
     return useMutation({
-        mutationFn: () => {
-            return sendNudge(conversationId);
+        mutationFn: async () => {
+            // Fails when any of the contacts was nudged too recently
+            await invoke('reserve_sent_nudge', { contactIds });
+
+            // Only nudges that went out count towards the limit
+            const release = () => invoke('release_sent_nudge', { contactIds })
+                .catch(err => console.error('Failed to release nudge:', err));
+            try {
+                const response = await sendNudge(conversationId);
+                if (!response.success) {
+                    await release();
+                }
+                return response;
+            } catch (error) {
+                await release();
+                throw error;
+            }
         },
         onSuccess: (response) => {
             if (response.success) {
@@ -421,48 +436,43 @@ export function useGlobalMessageUpdates(
 
                     // Check if this is a nudge message
                     if (newMessage.metadata?.action === 'nudge') {
-                        // Play nudge sound
-                        soundService.playNudgeSound();
-
-                        // Emit event to notify the specific chat window
-                        await emit(WINDOW_EVENTS.NUDGE_RECEIVED, {
-                            conversationId,
-                            senderId,
-                        });
+                        // Plays the nudge sound and shakes the chat window, unless the sender nudged too recently
+                        await invoke('receive_nudge', { conversationId, senderId })
+                            .catch(err => console.error('Failed to handle nudge:', err));
                     } else {
-                        // Check if chat window for this conversation is open and focused
-                        // Window is labeled with the contact's user ID, and senderId is who sent the message
-                        const chatWindowLabel = `chat-${senderId}`;
-                        const chatWindow = await WebviewWindow.getByLabel(chatWindowLabel);
+                        // Clicking the toast opens the group chat if it is open, otherwise the one-on-one chat
+                        const groupWindow = await WebviewWindow.getByLabel(`chat-group-${conversationId}`);
 
                         // Get sender name from cached contacts
                         const contacts = queryClient.getQueryData<Contact[]>(['contacts', 'accepted']);
                         const contact = contacts?.find(c => c.contactUser?.id === senderId);
                         let senderName = 'Unknown'
+                        let chat: ChatTarget
                         if (contact) {
                             senderName = contact?.contactUser?.displayName || contact?.contactUser?.email
+                            chat = { type: 'contact', userId: senderId, displayName: senderName }
                         } else {
                             // Retrieve from cached bots
                             const bots = queryClient.getQueryData<Bot[]>(['bots']);
                             const bot = bots?.find(b => b.id === senderId);
                             senderName = bot?.displayName || bot?.email || 'Unknown'
+                            chat = bot
+                                ? { type: 'bot', botId: senderId, displayName: senderName }
+                                : { type: 'contact', userId: senderId }
+                        }
+                        if (groupWindow) {
+                            chat = { type: 'conversation', conversationId }
                         }
 
                         const message = `${senderName} says:`
                         const description = newMessage.content || '';
 
-                        if (!chatWindow) {
-                            soundService.playMessageSound();
-                            // Show notification with sender name from cache
-                            showNotificationWindow(message, description, senderId)
-                        } else {
-                            const isFocused = await chatWindow.isFocused();
-                            if (!isFocused) {
-                                soundService.playMessageSound();
-                                // Show notification with sender name from cache
-                                showNotificationWindow(message, description, senderId)
-                            }
-                        }
+                        // The notification policy mutes these while the chat showing the message is focused
+                        soundService.playMessageSound(senderId, conversationId);
+                        showNotificationWindow(message, description, chat, senderId, conversationId)
+                        // Flash the chat window in the taskbar and its title until it is focused
+                        invoke('flash_chat_window', { senderId, senderName, conversationId })
+                            .catch(err => console.error('Failed to flash chat window:', err));
                     }
 
                     // Invalidate the specific conversation messages
@@ -3,9 +3,9 @@
  * Handles file transfer operations with the Backend Service
  */
 
-import { apiGet, apiPost, createAuthHeaders } from '../api-client';
-
-const API_BASE_URL = import.meta.env.VITE_BACKEND_API_URL || 'http://localhost:6666';
+import { invoke } from '@tauri-apps/api/core';
+import { apiGet, apiPost } from '../api-client';
+import { supabase } from '../supabase';
 
 export interface FileTransferRequest {
     id: string;
@@ -55,9 +55,66 @@ export interface InitiateFileTransferResponse {
     };
 }
 
+/**
+ * Payload of the file-transfer-progress event emitted while a file downloads
+ */
+export interface FileTransferProgressPayload {
+    transferId: string;
+    state: 'downloading' | 'completed' | 'failed' | 'cancelled';
+    receivedBytes: number;
+    totalBytes: number | null;
+    path: string | null;
+    error: string | null;
+}
+
+/**
+ * A file the app granted access to, such as one picked to send
+ * The grant is short-lived and stands in for the file's path.
+ */
+export interface GrantedFile {
+    grantId: string;
+    name: string;
+    size: number;
+    mimeType: string;
+}
+
 export interface UploadFileRequest {
     transferId: string;
-    file: File;
+}
+
+/**
+ * Why uploadFile failed, as rejected by the app
+ */
+export interface UploadError {
+    kind: 'notQueued' | 'alreadyUploading' | 'cancelled' | 'read' | 'tooLarge' | 'http' | 'network';
+    message?: string;
+    status?: number;
+    limit?: number;
+}
+
+/**
+ * Message to show for an error thrown by uploadFile
+ */
+export function uploadErrorMessage(error: unknown): string {
+    if (error instanceof Error) {
+        return error.message;
+    }
+
+    const uploadError = error as Partial<UploadError> | null;
+    switch (uploadError?.kind) {
+        case 'cancelled':
+            return 'Upload cancelled';
+        case 'tooLarge':
+            return 'File is too large to send';
+        case 'read':
+            return 'Could not read the file';
+        case 'http':
+            return uploadError.message || `Upload failed with status ${uploadError.status}`;
+        case 'network':
+            return uploadError.message ? `Network error: ${uploadError.message}` : 'Network error';
+        default:
+            return uploadError?.message || 'Upload failed';
+    }
 }
 
 export interface UploadFileResponse {
@@ -146,71 +203,42 @@ export async function cancelFileTransfer(
 }
 
 /**
- * Upload a file after transfer has been accepted
+ * Queue a granted file to be uploaded once the transfer is accepted
+ */
+export async function queueUpload(transferId: string, grantId: string): Promise<void> {
+    return invoke<void>('queue_upload', { transferId, grantId });
+}
+
+/**
+ * Upload the file queued for a transfer after it has been accepted
+ * The app streams the file from disk, retrying transient failures; progress and the outcome
+ * arrive as file-upload-progress and file-upload-complete events.
  */
 export async function uploadFile(
-    request: UploadFileRequest,
-    onProgress?: (progress: number) => void
+    request: UploadFileRequest
 ): Promise<UploadFileResponse> {
-    const formData = new FormData();
-    formData.append('transfer_id', request.transferId);
-    formData.append('file', request.file);
-
-    return new Promise(async (resolve, reject) => {
-        const xhr = new XMLHttpRequest();
-
-        // Track upload progress
-        if (onProgress) {
-            xhr.upload.addEventListener('progress', (e) => {
-                if (e.lengthComputable) {
-                    const progress = (e.loaded / e.total) * 100;
-                    onProgress(progress);
-                }
-            });
-        }
-
-        xhr.addEventListener('load', () => {
-            if (xhr.status >= 200 && xhr.status < 300) {
-                try {
-                    const response = JSON.parse(xhr.responseText);
-                    if (response.success && response.data) {
-                        resolve(response.data);
-                    } else {
-                        reject(new Error(response.error || 'Upload failed'));
-                    }
-                } catch (error) {
-                    reject(new Error('Failed to parse response'));
-                }
-            } else {
-                try {
-                    const response = JSON.parse(xhr.responseText);
-                    reject(new Error(response.error || `Upload failed with status ${xhr.status}`));
-                } catch {
-                    reject(new Error(`Upload failed with status ${xhr.status}`));
-                }
-            }
-        });
-
-        xhr.addEventListener('error', () => {
-            reject(new Error('Network error during upload'));
-        });
-
-        xhr.addEventListener('abort', () => {
-            reject(new Error('Upload cancelled'));
-        });
-
-        xhr.open('POST', `${API_BASE_URL}/api/files/upload`);
-
-        // Add auth headers
-        const headers = await createAuthHeaders();
-        Object.entries(headers).forEach(([key, value]) => {
-            xhr.setRequestHeader(key, value);
-        });
-
-        xhr.send(formData);
+    const { data: { session } } = await supabase.auth.getSession();
+
+    return invoke<UploadFileResponse>('upload_file', {
+        transferId: request.transferId,
+        authToken: session?.access_token ?? null,
     });
 }
 
+/**
+ * Cancel a running upload
+ */
+export async function cancelUpload(transferId: string): Promise<boolean> {
+    return invoke<boolean>('cancel_upload', { transferId });
+}
+
+/**
+ * Drop the file queued for a transfer that ended without being accepted
+ */
+export async function forgetUpload(transferId: string): Promise<boolean> {
+    return invoke<boolean>('forget_upload', { transferId });
+}
+
 /**
  * Get all transfer requests for a conversation
  */
@@ -246,31 +274,30 @@ export async function getTransferRequestByMessageId(
 }
 
 /**
- * Download a file by transfer request ID
+ * Download a file by transfer request ID into the download location
+ * The file is streamed to disk by the app; progress arrives as file-transfer-progress events.
+ * Resolves with the path the file was saved to.
  */
 export async function downloadFile(
     transferId: string,
-    filename: string
-): Promise<void> {
-    const headers = await createAuthHeaders();
-
-    const response = await fetch(`${API_BASE_URL}/api/files/transfer/${transferId}/download`, {
-        headers,
+    filename: string,
+    contactName?: string
+): Promise<string> {
+    const { data: { session } } = await supabase.auth.getSession();
+
+    return invoke<string>('start_file_download', {
+        transferId,
+        authToken: session?.access_token ?? null,
+        filename,
+        contactName: contactName ?? null,
     });
+}
 
-    if (!response.ok) {
-        throw new Error('Failed to download file');
-    }
-
-    const blob = await response.blob();
-    const url = window.URL.createObjectURL(blob);
-    const a = document.createElement('a');
-    a.href = url;
-    a.download = filename;
-    document.body.appendChild(a);
-    a.click();
-    window.URL.revokeObjectURL(url);
-    document.body.removeChild(a);
+/**
+ * Cancel a running download; the partly downloaded file is removed
+ */
+export async function cancelDownload(transferId: string): Promise<boolean> {
+    return invoke<boolean>('cancel_file_download', { transferId });
 }
 
 export const fileService = {
@@ -278,8 +305,12 @@ export const fileService = {
     acceptFileTransfer,
     declineFileTransfer,
     cancelFileTransfer,
+    queueUpload,
     uploadFile,
+    cancelUpload,
+    forgetUpload,
     getTransferRequests,
     getTransferRequestByMessageId,
     downloadFile,
+    cancelDownload,
 };
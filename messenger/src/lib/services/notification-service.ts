@@ -1,5 +1,17 @@
 import { invoke } from '@tauri-apps/api/core';
 
+/**
+ * Kinds of events run through the Rust notification policy
+ */
+export type NotificationEventKind =
+    | 'message'
+    | 'contactOnline'
+    | 'contactOffline'
+    | 'contactRequest'
+    | 'nudge'
+    | 'call'
+    | 'file';
+
 /**
  * Service for displaying system notifications
  */
@@ -24,15 +36,22 @@ export class NotificationService {
      * Show a system notification with title and body
      * @param title - The notification title
      * @param body - The notification body text
+     * @param senderId - User ID of the contact the notification is about, if any
+     * @param kind - Kind of event, used by the notification policy (defaults to message)
      */
-    async show(title: string, body: string): Promise<void> {
+    async show(
+        title: string,
+        body: string,
+        senderId?: string,
+        kind?: NotificationEventKind
+    ): Promise<void> {
         try {
             // Request permission on first use if not already requested
             if (!this.permissionRequested) {
                 await this.requestPermission();
             }
 
-            await invoke('show_notification', { title, body });
+            await invoke('show_notification', { title, body, senderId, kind });
         } catch (error) {
             console.error('Failed to show notification:', error);
             throw error;
@@ -41,44 +60,50 @@ export class NotificationService {
 
     /**
      * Show a notification for a new message
+     * @param senderId - User ID of the message sender, so each contact is rate limited and
+     * overridden on its own
      * @param senderName - Name of the message sender
      * @param messagePreview - Preview of the message content
      */
-    async showMessageNotification(senderName: string, messagePreview: string): Promise<void> {
-        await this.show(`New message from ${senderName}`, messagePreview);
+    async showMessageNotification(senderId: string, senderName: string, messagePreview: string): Promise<void> {
+        await this.show(`New message from ${senderName}`, messagePreview, senderId, 'message');
     }
 
     /**
      * Show a notification for a contact coming online
+     * @param userId - User ID of the contact
      * @param contactName - Name of the contact
      */
-    async showContactOnlineNotification(contactName: string): Promise<void> {
-        await this.show('Contact Online', `${contactName} is now online`);
+    async showContactOnlineNotification(userId: string, contactName: string): Promise<void> {
+        await this.show('Contact Online', `${contactName} is now online`, userId, 'contactOnline');
     }
 
     /**
      * Show a notification for a contact going offline
+     * @param userId - User ID of the contact
      * @param contactName - Name of the contact
      */
-    async showContactOfflineNotification(contactName: string): Promise<void> {
-        await this.show('Contact Offline', `${contactName} is now offline`);
+    async showContactOfflineNotification(userId: string, contactName: string): Promise<void> {
+        await this.show('Contact Offline', `${contactName} is now offline`, userId, 'contactOffline');
     }
 
     /**
      * Show a notification for a contact request
+     * @param userId - User ID of the user requesting
      * @param contactName - Name of the contact requesting
      */
-    async showContactRequestNotification(contactName: string): Promise<void> {
-        await this.show('Contact Request', `${contactName} wants to add you as a contact`);
+    async showContactRequestNotification(userId: string, contactName: string): Promise<void> {
+        await this.show('Contact Request', `${contactName} wants to add you as a contact`, userId, 'contactRequest');
     }
 
     /**
      * Show a notification for a file transfer
+     * @param senderId - User ID of the file sender
      * @param senderName - Name of the file sender
      * @param filename - Name of the file
      */
-    async showFileTransferNotification(senderName: string, filename: string): Promise<void> {
-        await this.show('File Transfer', `${senderName} is sending you ${filename}`);
+    async showFileTransferNotification(senderId: string, senderName: string, filename: string): Promise<void> {
+        await this.show('File Transfer', `${senderName} is sending you ${filename}`, senderId, 'file');
     }
 };
 
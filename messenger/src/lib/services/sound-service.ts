@@ -51,12 +51,9 @@ export class SoundService {
     /**
      * Play a sound file directly using the Audio API
      * @param soundFile - Path to the sound file relative to public directory
-     * @param volume - Volume level (0.0 to 1.0)
+     * @param volume - Effective volume level resolved by the Rust backend (0.0 to 1.0)
      */
     private playAudioFile(soundFile: string, volume: number): void {
-        const settings = this.getSettings();
-        if (!settings.soundEnabled) return;
-
         try {
             // Get or create audio element for this sound
             let audio = this.audioElements.get(soundFile);
@@ -68,8 +65,7 @@ export class SoundService {
 
             // Reset to beginning if already playing
             audio.currentTime = 0;
-            // Convert volume from 0-100 to 0-1 scale
-            audio.volume = volume * (settings.soundVolume / 100);
+            audio.volume = volume;
 
             // Play the sound
             audio.play().catch((error) => {
@@ -82,18 +78,14 @@ export class SoundService {
 
     /**
      * Play a sound by type
+     * The Rust backend decides whether and how loud to play, applying per-contact overrides
      * @param soundType - Type of sound to play
+     * @param senderId - User ID of the contact that triggered the sound, if any
+     * @param conversationId - Conversation the sound is about, so a focused group chat mutes it too
      */
-    async play(soundType: SoundType): Promise<void> {
-        const settings = this.getSettings();
-        if (!settings.soundEnabled) return;
-
+    async play(soundType: SoundType, senderId?: string, conversationId?: string): Promise<void> {
         try {
-            // Convert volume from 0-100 to 0-1 scale for Rust backend
-            await invoke('play_sound', {
-                soundType,
-                volume: settings.soundVolume / 100,
-            });
+            await invoke('play_sound', { soundType, senderId, conversationId });
         } catch (error) {
             console.error(`Failed to play sound ${soundType}:`, error);
             throw error;
@@ -102,30 +94,37 @@ export class SoundService {
 
     /**
      * Play the new message sound
+     * Muted by the notification policy while the chat showing the message is focused
+     * @param senderId - User ID of the message sender
+     * @param conversationId - Conversation the message arrived in
      */
-    async playMessageSound(): Promise<void> {
-        await this.play('message');
+    async playMessageSound(senderId?: string, conversationId?: string): Promise<void> {
+        await this.play('message', senderId, conversationId);
     }
 
     /**
      * Play the contact online sound
+     * @param userId - User ID of the contact that came online, so each contact is rate limited
+     * and overridden on its own
      */
-    async playContactOnlineSound(): Promise<void> {
-        await this.play('contact_online');
+    async playContactOnlineSound(userId: string): Promise<void> {
+        await this.play('contact_online', userId);
     }
 
     /**
      * Play the contact offline sound
+     * @param userId - User ID of the contact that went offline
      */
-    async playContactOfflineSound(): Promise<void> {
-        await this.play('contact_offline');
+    async playContactOfflineSound(userId: string): Promise<void> {
+        await this.play('contact_offline', userId);
     }
 
     /**
      * Play the nudge sound
+     * @param senderId - User ID of the nudge sender
      */
-    async playNudgeSound(): Promise<void> {
-        await this.play('nudge');
+    async playNudgeSound(senderId?: string): Promise<void> {
+        await this.play('nudge', senderId);
     }
 
     /**
@@ -172,18 +171,13 @@ export class SoundService {
 
     /**
      * Preview a sound (useful for settings UI)
-     * Always plays regardless of enabled setting
+     * Always plays regardless of enabled setting, at the global volume
      * @param soundType - Type of sound to preview
      */
     async previewSound(soundType: SoundType): Promise<void> {
-        const settings = this.getSettings();
-
         try {
-            // Always play preview with current volume
-            await invoke('play_sound', {
-                soundType,
-                volume: settings.soundVolume / 100,
-            });
+            // Preview with the current global volume
+            await invoke('preview_sound', { soundType });
         } catch (error) {
             console.error(`Failed to preview sound ${soundType}:`, error);
         }
@@ -9,8 +9,8 @@ export interface FileTransferInitiatedPayload {
   filename: string;
   fileSize: number;
   mimeType: string;
-  // File data as base64 or array buffer for cross-window transfer
-  fileData: number[];
+  // Grant for the picked file; the app uploads it from disk
+  grantId: string;
 }
 
 export interface FileUploadProgressPayload {
@@ -37,7 +37,6 @@ export interface PendingUpload {
   filename: string;
   fileSize: number;
   mimeType: string;
-  file: File;
   progress: number;
   status: FileUploadStatus;
   error?: string;
@@ -130,10 +129,14 @@ export const useFileUploadStore = create<FileUploadState>((set, get) => ({
       async (event) => {
         const payload = event.payload;
 
-        // Convert array buffer back to File
-        const uint8Array = new Uint8Array(payload.fileData);
-        const blob = new Blob([uint8Array], { type: payload.mimeType });
-        const file = new File([blob], payload.filename, { type: payload.mimeType });
+        // Hand the file to the app now, while its grant is fresh
+        const { queueUpload } = await import('../services/file-service');
+        try {
+          await queueUpload(payload.transferId, payload.grantId);
+        } catch (error) {
+          console.error('Failed to queue file for upload:', error);
+          return;
+        }
 
         get().addUpload({
           transferId: payload.transferId,
@@ -142,7 +145,6 @@ export const useFileUploadStore = create<FileUploadState>((set, get) => ({
           filename: payload.filename,
           fileSize: payload.fileSize,
           mimeType: payload.mimeType,
-          file,
         });
 
         console.log('Main window received file transfer:', payload.filename);
@@ -163,25 +165,23 @@ export const useFileUploadStore = create<FileUploadState>((set, get) => ({
         }
 
         // Import file service dynamically to avoid circular dependencies
-        const { uploadFile } = await import('../services/file-service');
+        const { uploadFile, uploadErrorMessage } = await import('../services/file-service');
 
         get().updateStatus(transferId, 'uploading');
 
-        try {
-          await uploadFile({ transferId, file: upload.file }, (progress) => {
-            get().updateProgress(transferId, progress);
-            // Emit progress to chat windows
-            emit('file-upload-progress', {
-              transferId,
-              progress,
-            } as FileUploadProgressPayload);
-          });
+        // The app emits file-upload-progress and file-upload-complete to the chat windows
+        const unlistenProgress = await listen<FileUploadProgressPayload>(
+          'file-upload-progress',
+          (progressEvent) => {
+            if (progressEvent.payload.transferId === transferId) {
+              get().updateProgress(transferId, progressEvent.payload.progress);
+            }
+          }
+        );
 
+        try {
+          await uploadFile({ transferId });
           get().updateStatus(transferId, 'completed');
-          emit('file-upload-complete', {
-            transferId,
-            success: true,
-          } as FileUploadCompletePayload);
 
           // Clean up after a delay
           setTimeout(() => {
@@ -189,13 +189,10 @@ export const useFileUploadStore = create<FileUploadState>((set, get) => ({
           }, 5000);
 
         } catch (error) {
-          const errorMessage = error instanceof Error ? error.message : 'Upload failed';
-          get().updateStatus(transferId, 'failed', errorMessage);
-          emit('file-upload-complete', {
-            transferId,
-            success: false,
-            error: errorMessage,
-          } as FileUploadCompletePayload);
+          // The app rejects with an UploadError object rather than an Error
+          get().updateStatus(transferId, 'failed', uploadErrorMessage(error));
+        } finally {
+          unlistenProgress();
         }
       }
     );
@@ -218,9 +215,3 @@ export const useFileUploadStore = create<FileUploadState>((set, get) => ({
     await emit('file-upload-start', { transferId } as FileUploadStartPayload);
   },
 }));
-
-// Helper function to convert File to array buffer for Tauri event transfer
-export async function fileToArrayBuffer(file: File): Promise<number[]> {
-  const arrayBuffer = await file.arrayBuffer();
-  return Array.from(new Uint8Array(arrayBuffer));
-}
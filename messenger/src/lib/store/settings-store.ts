@@ -7,6 +7,7 @@
 import { create } from 'zustand';
 import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
+import type { CloseBehavior } from '@/types';
 
 /**
  * Application settings interface
@@ -18,14 +19,17 @@ export interface AppSettings {
         soundEnabled: boolean;
         soundVolume: number; // 0-100
         desktopAlerts: boolean;
+        shakeOnNudge: boolean;
     };
     startup: {
         autoLaunch: boolean;
         startMinimized: boolean;
+        closeBehavior: CloseBehavior;
     };
     files: {
         downloadLocation: string;
         autoAcceptFrom: string[]; // User IDs to auto-accept files from
+        perContactFolders: boolean; // Save each contact's files in a folder named after them
     };
 }
 
@@ -38,14 +42,17 @@ const DEFAULT_SETTINGS: AppSettings = {
         soundEnabled: true,
         soundVolume: 80,
         desktopAlerts: true,
+        shakeOnNudge: true,
     },
     startup: {
         autoLaunch: false,
         startMinimized: false,
+        closeBehavior: 'minimizeToTray',
     },
     files: {
         downloadLocation: '', // Will be set to system default on first run
         autoAcceptFrom: [],
+        perContactFolders: false,
     },
 };
 
@@ -2,7 +2,6 @@
 export const WINDOW_EVENTS = {
     ADD_GROUP: 'ADD_GROUP',
     UPDATE_GROUPS: 'UPDATE_GROUPS',
-    NUDGE_RECEIVED: 'NUDGE_RECEIVED',
     BLOCK_UPDATED: 'BLOCK_UPDATED',
     CALL_EVENTS: 'CALL_EVENTS',
 }
\ No newline at end of file
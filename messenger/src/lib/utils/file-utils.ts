@@ -3,12 +3,15 @@
  * Helper functions for file validation and formatting
  */
 
+import { invoke } from '@tauri-apps/api/core';
+import type { GrantedFile } from '../services/file-service';
+
 const MAX_FILE_SIZE = 100 * 1024 * 1024; // 100 MB
 
 /**
  * Validate a file for transfer
  */
-export function validateFile(file: File): { valid: boolean; error?: string } {
+export function validateFile(file: { size: number }): { valid: boolean; error?: string } {
     // Check file size
     if (file.size > MAX_FILE_SIZE) {
         return {
@@ -56,21 +59,11 @@ export function getMimeType(file: File): string {
 
 /**
  * Open file dialog using Tauri
+ * Returns a grant for the picked file and its details, without reading its contents
  */
-export async function openFileDialog(): Promise<File | null> {
+export async function openFileDialog(): Promise<GrantedFile | null> {
     try {
-        // @ts-ignore - Tauri API
-        const { invoke } = window.__TAURI__.core;
-        const filePath = await invoke<string | null>('open_file_dialog');
-
-        if (!filePath) {
-            return null;
-        }
-
-        // Read the file using the File System API or fetch
-        // For now, we'll use a file input element as a workaround
-        // In a real implementation, you'd use Tauri's file system API
-        return null;
+        return await invoke<GrantedFile | null>('open_file_dialog');
     } catch (error) {
         console.error('Failed to open file dialog:', error);
         return null;
@@ -5,6 +5,7 @@
 
 import { invoke } from '@tauri-apps/api/core';
 import { startRecording as tauriStartRecording, stopRecording as tauriStopRecording } from 'tauri-plugin-mic-recorder-api';
+import type { GrantedFile } from '../services/file-service';
 
 export interface RecorderConfig {
   mimeType: string;
@@ -50,12 +51,14 @@ export interface RecordingResult {
  */
 export async function stopRecording(): Promise<RecordingResult> {
   try {
-    // Stop recording and get the file path
+    // Stop recording and get a grant to read the recording
     console.log('Stopping recording...');
-    const audioPath = await tauriStopRecording();
+    const recording = await invoke<GrantedFile>('stop_voice_recording');
 
-    // Read the file bytes using Tauri command
-    const bytes = await invoke<number[]>('read_file_bytes', { path: audioPath });
+    // Read the file bytes using Tauri command, then give up the grant since it is read only once
+    const bytes = await invoke<number[]>('read_granted_file', { grantId: recording.grantId })
+      .finally(() => invoke('revoke_file_grant', { grantId: recording.grantId })
+        .catch(err => console.error('Failed to revoke recording grant:', err)));
 
     // Create blob from bytes
     const blob = new Blob([new Uint8Array(bytes)], { type: 'audio/wav' });
@@ -0,0 +1,31 @@
+import { invoke } from '@tauri-apps/api/core';
+import { getCurrentWindow } from '@tauri-apps/api/window';
+
+/**
+ * Event broadcast to every window when the app starts shutting down,
+ * matching SHUTDOWN_EVENT in src-tauri/src/shutdown.rs
+ */
+export const APP_SHUTTING_DOWN_EVENT = 'app-shutting-down';
+
+/**
+ * Run `handler` when the app quits, before it exits.
+ * The app waits for the handler (up to a timeout) before flushing its state and exiting.
+ * Returns a function that unregisters the handler.
+ */
+export async function onAppShutdown(name: string, handler: () => Promise<void>): Promise<() => void> {
+    const unlisten = await getCurrentWindow().listen(APP_SHUTTING_DOWN_EVENT, async () => {
+        try {
+            await handler();
+        } catch (error) {
+            console.error(`Shutdown hook '${name}' failed:`, error);
+        } finally {
+            await invoke('complete_shutdown_hook', { name });
+        }
+    });
+    await invoke('register_shutdown_hook', { name });
+
+    return () => {
+        unlisten();
+        invoke('complete_shutdown_hook', { name }).catch(err => console.error(err));
+    };
+}
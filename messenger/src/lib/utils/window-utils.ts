@@ -1,5 +1,7 @@
 import { WebviewWindow } from '@tauri-apps/api/webviewWindow';
+import { invoke } from '@tauri-apps/api/core';
 import type { WindowOptions } from '@tauri-apps/api/window';
+import type { ChatTarget } from '@/types';
 
 /**
  * Get the correct URL for opening a Tauri window.
@@ -49,30 +51,29 @@ export function createWindow(
 }
 
 /**
- * Show a notification window positioned at the bottom right of the screen.
- * The window will auto-close after 5 seconds.
- * @param senderName - Name of the message sender
- * @param messagePreview - Preview of the message content
- * @param senderId - ID of the sender (used when clicking to open chat)
+ * Show a toast notification window.
+ * Placement, stacking, timing and queueing are handled by the toast manager in Rust, and the
+ * notification policy drops the toast while the chat showing the message is focused.
+ * @param message - Title line, e.g. "<name> says:"
+ * @param description - Preview of the message content
+ * @param chat - Chat to open when the toast is clicked
+ * @param senderId - User ID of the message sender
+ * @param conversationId - Conversation the message arrived in
  */
-export function showNotificationWindow(
+export async function showNotificationWindow(
     message: string,
     description: string,
-    senderId?: string
-): WebviewWindow {
-    const label = `notification-${Date.now()}`;
-    const encodedMessage = encodeURIComponent(message);
-    const encodedDescription = encodeURIComponent(description);
-    const path = `/notification.html?message=${encodedMessage}&description=${encodedDescription}&senderId=${senderId}`;
-
-    return createWindow(label, path, {
-        width: 300,
-        height: 120,
-        decorations: false,
-        transparent: true,
-        alwaysOnTop: true,
-        skipTaskbar: true,
-        resizable: false,
-        focus: false,
+    chat?: ChatTarget,
+    senderId?: string,
+    conversationId?: string
+): Promise<void> {
+    await invoke('show_toast', {
+        toast: {
+            message,
+            description,
+            chat: chat ?? null,
+            senderId: senderId ?? null,
+            conversationId: conversationId ?? null,
+        },
     });
 }
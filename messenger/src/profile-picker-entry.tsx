@@ -0,0 +1,8 @@
+import "./index.css";
+import { renderWindowEntry } from "./components/windows/window";
+import { ProfilePickerWindow } from "./components/windows/profile-picker-window";
+
+renderWindowEntry(
+    document.getElementById("root") as HTMLElement,
+    <ProfilePickerWindow />,
+);
@@ -94,6 +94,12 @@ export interface Conversation {
   updatedAt: Date;
 }
 
+// Target of a chat window, matching ChatTarget in src-tauri/src/chat_target.rs
+export type ChatTarget =
+  | { type: 'contact'; userId: string; displayName?: string }
+  | { type: 'conversation'; conversationId: string; title?: string }
+  | { type: 'bot'; botId: string; displayName?: string };
+
 export interface ChatbotPersonality {
   id: string;
   name: string;
@@ -102,20 +108,26 @@ export interface ChatbotPersonality {
   systemPrompt: string;
 }
 
+// What closing the main window does, matching CloseBehavior in src-tauri/src/settings.rs
+export type CloseBehavior = 'minimizeToTray' | 'quit' | 'ask';
+
 export interface AppSettings {
   notifications: {
     enabled: boolean;
     soundEnabled: boolean;
     soundVolume: number;
     desktopAlerts: boolean;
+    shakeOnNudge: boolean;
   };
   startup: {
     autoLaunch: boolean;
     startMinimized: boolean;
+    closeBehavior: CloseBehavior;
   };
   files: {
     downloadLocation: string;
     autoAcceptFrom: string[];
+    perContactFolders: boolean;
   };
 }
 
@@ -21,6 +21,7 @@ export default defineConfig(async () => ({
         'alert-dialog': resolve(__dirname, 'alert-dialog.html'),
         'add-to-group': resolve(__dirname, 'add-to-group.html'),
         'notification': resolve(__dirname, 'notification.html'),
+        'profile-picker': resolve(__dirname, 'profile-picker.html'),
       },
     },
   },